@@ -4,10 +4,28 @@ use serde::{Deserialize, Serialize};
 pub type Ident = String;
 
 /// A Coq vernacular file.
+#[derive(Default)]
 pub struct Vernacular {
     pub statements: Vec<Statement>,
 }
 
+impl Vernacular {
+    /// Append a statement, unless an identical one has already been included.
+    pub fn push(&mut self, stmt: Statement) {
+        if !self.statements.contains(&stmt) {
+            self.statements.push(stmt);
+        }
+    }
+}
+
+impl Extend<Statement> for Vernacular {
+    fn extend<T: IntoIterator<Item = Statement>>(&mut self, iter: T) {
+        for stmt in iter {
+            self.push(stmt);
+        }
+    }
+}
+
 impl From<Statement> for Vernacular {
     fn from(stmt: Statement) -> Self {
         Vernacular {
@@ -18,7 +36,19 @@ impl From<Statement> for Vernacular {
 
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 pub enum Statement {
+    /// Load and import a module from the standard library, e.g. `ZArith`.
+    Require(Ident),
     Definition(Definition),
+    Fixpoint(Fixpoint),
+    ProgramFixpoint(ProgramFixpoint),
+    Record(Record),
+    Inductive(Inductive),
+    Lemma(Lemma),
+    Class(Class),
+    Instance(Instance),
+
+    /// The notation for binding the value in a monad, which `Expr::Bind` is written with.
+    BindNotation(Monad),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
@@ -27,18 +57,234 @@ pub struct Definition {
     pub args: Vec<Binder>,
     pub ret: Ty,
     pub body: Expr,
+
+    /// Whether the body may panic, so it's defined with `Program`, which leaves an obligation to
+    /// prove each `Panic` in it is never reached.
+    pub program: bool,
+}
+
+/// A recursive function, which must be structurally decreasing on one of its arguments.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Fixpoint {
+    pub name: Ident,
+    pub args: Vec<Binder>,
+    pub ret: Ty,
+    pub body: Expr,
+
+    /// The argument on which each recursive call is structurally decreasing.
+    pub decreasing: Ident,
+
+    /// Whether the body may panic, like `Definition::program`.
+    pub program: bool,
+}
+
+/// A recursive function defined with `Program`, which terminates because the natural number
+/// `measure` decreases on each recursive call. Coq leaves proving that as an obligation.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ProgramFixpoint {
+    pub name: Ident,
+    pub args: Vec<Binder>,
+    pub ret: Ty,
+    pub body: Expr,
+    pub measure: Expr,
+}
+
+/// A lemma stating that two expressions are equal for all values of its arguments, along with
+/// the tactics proving it.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Lemma {
+    pub name: Ident,
+    pub args: Vec<Binder>,
+    pub lhs: Expr,
+    pub rhs: Expr,
+    pub proof: String,
+}
+
+/// A record type, with a field for each of its binders.
+///
+/// The constructor is Coq's default, `Build_<name>`. Since Coq puts the names of fields in the
+/// same namespace as everything else, each is prefixed with the name of the record.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Record {
+    pub name: Ident,
+    pub fields: Vec<Binder>,
+}
+
+impl Record {
+    /// The Coq name of the projection for the Rust field `field` of the record `record`.
+    pub fn projection(record: &str, field: &str) -> Ident {
+        format!("{record}_{field}")
+    }
+
+    /// The name of the function replacing the value of a field, used for struct update syntax.
+    pub fn setter(record: &str, field: &str) -> Ident {
+        format!("{record}_set_{field}")
+    }
+}
+
+/// An inductive type, with a constructor for each variant of a Rust enum.
+///
+/// Constructors are prefixed with the name of the type, like the fields of a `Record`. The fields
+/// of a struct variant are gathered into an auxiliary record, which is its constructor's only
+/// argument.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Inductive {
+    pub name: Ident,
+    pub constructors: Vec<Constructor>,
+}
+
+impl Inductive {
+    /// The Coq name of the constructor for the Rust variant `variant` of the type `inductive`.
+    pub fn constructor(inductive: &str, variant: &str) -> Ident {
+        format!("{inductive}_{variant}")
+    }
+
+    /// The name of the record holding the fields of a struct variant, given its constructor.
+    pub fn fields(constructor: &str) -> Ident {
+        format!("{constructor}_fields")
+    }
+}
+
+/// A typeclass over the types bound by its arguments, with a method for each of its binders.
+///
+/// Like the fields of a `Record`, each method is prefixed with the name of the class. A class
+/// whose arguments include an instance of another class, such as `` `{PartialEq T} ``, extends it.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Class {
+    pub name: Ident,
+    pub args: Vec<Binder>,
+    pub methods: Vec<Binder>,
+}
+
+impl Class {
+    /// The Coq name of the method `method` of the class `class`.
+    pub fn method(class: &str, method: &str) -> Ident {
+        format!("{class}_{method}")
+    }
+}
+
+/// An instance of a class, `class`, which defines each of its methods.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Instance {
+    pub name: Ident,
+    pub args: Vec<Binder>,
+    pub class: Ty,
+    pub methods: Vec<(Ident, Expr)>,
+}
+
+impl Instance {
+    /// The Coq name of the instance of the class `class` for the type named `ty`.
+    pub fn name(class: &str, ty: &str) -> Ident {
+        format!("{class}_{ty}")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Constructor {
+    pub name: Ident,
+    pub args: Vec<Ty>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 pub struct Binder {
     pub name: Ident,
     pub ty: Ty,
+
+    /// Whether the argument is implicit, like the type parameters of a generic function, so it's
+    /// written in braces and inferred from the other arguments when the function is applied.
+    pub implicit: bool,
+}
+
+impl Binder {
+    /// An implicit argument for an instance of the class `class` for `ty`, which is found by
+    /// typeclass resolution, such as `` `{Ord T} ``. It has no name, since it's only used through
+    /// the class's methods.
+    pub fn instance(class: &str, ty: Ty) -> Binder {
+        Binder {
+            name: Ident::new(),
+            ty: Ty::Apply {
+                func: class.into(),
+                args: vec![ty],
+            },
+            implicit: true,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 pub enum Ty {
     Nat,
+    N,
+    Z,
     Bool,
+
+    /// A type defined by a statement, such as a `Record` or `Inductive`.
+    Named(Ident),
+
+    /// A type parameter, bound by an implicit argument of type `Type`.
+    Var(Ident),
+
+    /// The type of types, such as type parameters.
+    Type,
+
+    /// The type of functions, such as the methods of a class, taking arguments of each of the
+    /// types but the last, in turn, and returning the last.
+    Arrow(Vec<Ty>),
+
+    /// A type constructor applied to types, such as `option nat`.
+    Apply {
+        func: Ident,
+        args: Vec<Ty>,
+    },
+}
+
+impl Ty {
+    /// An optional value, built with `Some` and `None`.
+    pub fn option(ty: Ty) -> Ty {
+        Ty::Apply {
+            func: "option".into(),
+            args: vec![ty],
+        }
+    }
+
+    /// Either of two types, used for Rust's `Result`: `Ok` is `inl` and `Err` is `inr`.
+    pub fn sum(left: Ty, right: Ty) -> Ty {
+        Ty::Apply {
+            func: "sum".into(),
+            args: vec![left, right],
+        }
+    }
+
+    /// A pair. Longer tuples are nested pairs, `prod (prod A B) C`, like Coq's `(a, b, c)`.
+    pub fn prod(left: Ty, right: Ty) -> Ty {
+        Ty::Apply {
+            func: "prod".into(),
+            args: vec![left, right],
+        }
+    }
+
+    /// A list of elements of type `ty`.
+    pub fn list(ty: Ty) -> Ty {
+        Ty::Apply {
+            func: "list".into(),
+            args: vec![ty],
+        }
+    }
+
+    /// The type of functions taking arguments of types `args`, in turn, and returning `ret`.
+    pub fn arrow(args: Vec<Ty>, ret: Ty) -> Ty {
+        let mut tys = args;
+        tys.push(ret);
+        Ty::Arrow(tys)
+    }
+
+    /// The type with a single value, `tt`.
+    pub fn unit() -> Ty {
+        Ty::Apply {
+            func: "unit".into(),
+            args: vec![],
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
@@ -55,23 +301,275 @@ pub enum Expr {
     /// A variable name.
     Var(Ident),
 
-    /// A 'let in' expression.
+    /// A 'let in' expression, optionally annotated with the type of the variable.
     LetIn {
         ident: Ident,
+        ty: Option<Ty>,
+        value: Box<Expr>,
+        child: Box<Expr>,
+    },
+
+    /// A 'let in' expression which destructures the value with an irrefutable pattern
+    /// (`let '(a, b) := v in`), optionally annotated with the type of the value.
+    LetPattern {
+        pattern: Box<Pattern>,
+        ty: Option<Ty>,
         value: Box<Expr>,
         child: Box<Expr>,
     },
 
+    /// Call a function which is passed the variables in `state` by `&mut`, binding its result to
+    /// `pattern` and the variables to their new values, which it returns after its result
+    /// (`let '(p, x, y) := f x y in`). The typing pass gives the variables the types they had
+    /// before, and replaces this with a `LetPattern`.
+    LetState {
+        pattern: Box<Pattern>,
+        state: Vec<Ident>,
+        value: Box<Expr>,
+        child: Box<Expr>,
+    },
+
+    /// A binary operator, which the typing pass replaces with the Coq function implementing it
+    /// for the type of its operands.
+    BinOp {
+        op: BinOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+        loc: Loc,
+    },
+
+    /// A method of `PartialEq`, `PartialOrd` or `Add`, like `a.lt(&b)`, which the typing pass
+    /// replaces like the operator `op` it implements, as long as it's called on a value of a type
+    /// parameter.
+    TraitOp {
+        op: BinOp,
+        receiver: Box<Expr>,
+        arg: Box<Expr>,
+        loc: Loc,
+    },
+
+    /// A unary operator, which the typing pass replaces like a `BinOp`.
+    UnOp {
+        op: UnOp,
+        operand: Box<Expr>,
+        loc: Loc,
+    },
+
     /// A `nat` literal.
     Nat(u64),
 
+    /// An `N` literal.
+    N(u64),
+
+    /// A `Z` literal, wide enough for any `u64` or `i64`.
+    Z(i128),
+
     /// A `bool` literal.
     Bool(bool),
 
     /// A `tt` literal (Coq's unit type).
     Tt,
+
+    /// A tuple, built from nested pairs.
+    Tuple(Vec<Expr>),
+
+    /// A list literal, built from `cons` and `nil`.
+    List(Vec<Expr>),
+
+    /// An operation on lists, which the typing pass replaces with the Coq function implementing
+    /// it for the type of the elements, like a `BinOp`. The list is the first argument, except
+    /// for `Repeat`.
+    ListOp {
+        op: ListOp,
+        args: Vec<Expr>,
+        loc: Loc,
+    },
+
+    /// An anonymous function (`fun x => e`), translated from a closure. Each parameter is
+    /// optionally annotated with its type, which the typing pass fills in where it's known.
+    Lambda {
+        params: Vec<(Ident, Option<Ty>)>,
+        body: Box<Expr>,
+    },
+
+    /// A `for` loop over the range `start..end`, which evaluates `body` for each value of `var`
+    /// in turn. The body reassigns the `state` variables, and evaluates to a tuple of their new
+    /// values; the loop evaluates to their final values.
+    ///
+    /// The typing pass replaces it with a call to an auxiliary `Fixpoint` named `func`, which
+    /// takes the number of iterations left, the value of `var`, the variables the body captures
+    /// and the state, along with a lemma giving its defining equation.
+    ForRange {
+        func: Ident,
+        var: Ident,
+        start: Box<Expr>,
+        end: Box<Expr>,
+        state: Vec<Ident>,
+        body: Box<Expr>,
+        loc: Loc,
+    },
+
+    /// A `while` loop, which evaluates `body` for as long as `cond` holds. Like a `ForRange`,
+    /// the body evaluates to the new values of the `state` variables.
+    ///
+    /// The typing pass replaces it with a call to an auxiliary `ProgramFixpoint` named `func`,
+    /// which takes the variables the loop captures and the state, and terminates because
+    /// `measure` decreases on each iteration.
+    While {
+        func: Ident,
+        cond: Box<Expr>,
+        measure: Box<Expr>,
+        state: Vec<Ident>,
+        body: Box<Expr>,
+        loc: Loc,
+    },
+
+    /// Return `value` from the function early (`return value`).
+    ///
+    /// The frontend moves the rest of the function into the branches which don't return, so
+    /// this only remains at the end of a branch of the function's body, where it's replaced by
+    /// `value`. The typing pass rejects any other `Return`.
+    Return { value: Box<Expr>, loc: Loc },
+
+    /// Bind `ident` to the value in `value`, an `option` or `sum`, for the rest of the function,
+    /// `child`, or return its `None` or error early (`let ident = value?;`).
+    ///
+    /// Like a `Return`, this is only at the end of a branch of the function's body. The typing
+    /// pass replaces it with a `Bind` for the type of `value`.
+    Try {
+        ident: Ident,
+        value: Box<Expr>,
+        child: Box<Expr>,
+        loc: Loc,
+    },
+
+    /// Bind `ident` to the value in `value` for `child` (`let? ident := value in child`), using
+    /// the notation defined by `Statement::BindNotation` for `monad`.
+    Bind {
+        monad: Monad,
+        ident: Ident,
+        value: Box<Expr>,
+        child: Box<Expr>,
+    },
+
+    /// A call to `panic!` or `unreachable!`, or a failed assertion, which must never be reached
+    /// (`False_rect _ _`).
+    ///
+    /// The proof of `False` is left as an obligation by `Program`, in a context with the
+    /// conditions of the branches leading to the panic.
+    Panic,
+
+    /// A value of a record type, giving the value of each field (`{| f := v |}`).
+    Record {
+        name: Ident,
+        fields: Vec<(Ident, Expr)>,
+    },
+
+    /// Access a field of a record, which the typing pass replaces with the projection for the
+    /// record's type.
+    Field {
+        base: Box<Expr>,
+        field: Ident,
+        loc: Loc,
+    },
+
+    /// Replace a field of a record with `value`, which the typing pass replaces with the setter
+    /// for the record's type.
+    SetField {
+        base: Box<Expr>,
+        field: Ident,
+        value: Box<Expr>,
+        loc: Loc,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Shl,
+    Shr,
+    BitAnd,
+    BitOr,
+    BitXor,
+    And,
+    Or,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+pub enum UnOp {
+    Neg,
+    Not,
+}
+
+/// The operations on lists, named after the Rust methods they translate.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+pub enum ListOp {
+    Len,
+    IsEmpty,
+    First,
+    Get,
+    Contains,
+    Sum,
+
+    /// Apply a function to each element (`map`).
+    Map,
+
+    /// Keep the elements satisfying a predicate (`filter`).
+    Filter,
+
+    /// Combine the elements from the left, starting with an initial value (`fold_left`). The
+    /// arguments are the list, the initial value and the function, as in Rust.
+    Fold,
+
+    /// Whether any element satisfies a predicate (`existsb`).
+    Any,
+
+    /// Whether every element satisfies a predicate (`forallb`).
+    All,
+
+    /// Append an element, returning the new list.
+    Push,
+
+    /// A list of `n` copies of an element, `vec![x; n]`.
+    Repeat,
+}
+
+/// The types which `?` can be applied to, each of which has a notation for binding its value.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+pub enum Monad {
+    /// `option`, where `let?` returns `None` early.
+    Option,
+
+    /// `sum`, where `let!` returns the error, on the right, early.
+    Sum,
+}
+
+impl Monad {
+    /// The keyword of the notation for binding a value in the monad.
+    pub fn keyword(self) -> &'static str {
+        match self {
+            Monad::Option => "let?",
+            Monad::Sum => "let!",
+        }
+    }
+}
+
+/// An opaque reference to a location in the source code, used to report type errors.
+///
+/// The frontend which built the AST decides what each number refers to.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Loc(pub u32);
+
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 pub struct MatchCase {
     pub pattern: Pattern,
@@ -82,5 +580,25 @@ pub struct MatchCase {
 pub enum Pattern {
     /// Match a particular expression.
     Expr(Expr),
-    // TODO: add more pattern types if necessary.
+
+    /// Match anything, without binding it (`_`).
+    Wildcard,
+
+    /// Match anything, binding it to a variable.
+    Ident(Ident),
+
+    /// Match a constructor applied to sub-patterns (e.g. `S n`).
+    Constructor { name: Ident, args: Vec<Pattern> },
+
+    /// Match a pattern, additionally binding the whole value to a variable (`p as x`).
+    As { pattern: Box<Pattern>, ident: Ident },
+
+    /// Match each element of a tuple.
+    Tuple(Vec<Pattern>),
+
+    /// Match some of the fields of a record (`{| f := p |}`); the others match anything.
+    Record { fields: Vec<(Ident, Pattern)> },
+
+    /// Match any one of several alternatives, which must all bind the same variables.
+    Or(Vec<Pattern>),
 }
@@ -14,17 +14,58 @@ impl fmt::Display for ast::Vernacular {
 impl fmt::Display for ast::Statement {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
+            ast::Statement::Require(module) => write!(f, "Require Import {module}."),
             ast::Statement::Definition(defn) => write!(f, "{}", defn),
+            ast::Statement::Fixpoint(fixpoint) => write!(f, "{}", fixpoint),
+            ast::Statement::ProgramFixpoint(fixpoint) => write!(f, "{}", fixpoint),
+            ast::Statement::Record(record) => write!(f, "{}", record),
+            ast::Statement::Inductive(inductive) => write!(f, "{}", inductive),
+            ast::Statement::Lemma(lemma) => write!(f, "{}", lemma),
+            ast::Statement::Class(class) => write!(f, "{}", class),
+            ast::Statement::Instance(instance) => write!(f, "{}", instance),
+            ast::Statement::BindNotation(monad) => {
+                let (bind, exit) = match monad {
+                    ast::Monad::Option => ("Some x", "None => None"),
+                    ast::Monad::Sum => ("inl x", "inr err => inr err"),
+                };
+                write!(
+                    f,
+                    "Notation \"'{}' x ':=' e 'in' k\" :=\n\t(match e with {bind} => k | {exit} end)\n\t\
+                     (at level 200, x ident, e at level 100, k at level 200).",
+                    monad.keyword()
+                )
+            }
         }
     }
 }
 
 impl fmt::Display for ast::Definition {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.program {
+            write!(f, "Program ")?;
+        }
         write!(f, "Definition {}", self.name)?;
         for binder in &self.args {
-            write!(f, " ({binder})")?;
+            write!(f, " {}", Bracketed(binder))?;
+        }
+        write!(f, " : {}", self.ret)?;
+        writeln!(f, " :=")?;
+        write!(f, "{}", Indent::tab(&self.body))?;
+        writeln!(f, ".")?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for ast::Fixpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.program {
+            write!(f, "Program ")?;
+        }
+        write!(f, "Fixpoint {}", self.name)?;
+        for binder in &self.args {
+            write!(f, " {}", Bracketed(binder))?;
         }
+        write!(f, " {{struct {}}}", self.decreasing)?;
         write!(f, " : {}", self.ret)?;
         writeln!(f, " :=")?;
         write!(f, "{}", Indent::tab(&self.body))?;
@@ -33,17 +74,160 @@ impl fmt::Display for ast::Definition {
     }
 }
 
+impl fmt::Display for ast::ProgramFixpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Program Fixpoint {}", self.name)?;
+        for binder in &self.args {
+            write!(f, " {}", Bracketed(binder))?;
+        }
+        write!(f, " {{measure {}}}", self.measure.to_string().trim_end())?;
+        write!(f, " : {}", self.ret)?;
+        writeln!(f, " :=")?;
+        write!(f, "{}", Indent::tab(&self.body))?;
+        writeln!(f, ".")?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for ast::Lemma {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Lemma {} :", self.name)?;
+        if !self.args.is_empty() {
+            write!(f, " forall")?;
+            for binder in &self.args {
+                write!(f, " {}", Bracketed(binder))?;
+            }
+            write!(f, ",")?;
+        }
+        writeln!(f)?;
+        write!(
+            f,
+            "{}",
+            Indent::tab(format!("{} =\n{}", self.lhs, self.rhs))
+        )?;
+        writeln!(f, ".")?;
+        writeln!(f, "Proof.")?;
+        write!(f, "{}", Indent::tab(&self.proof))?;
+        writeln!(f, "Qed.")?;
+        Ok(())
+    }
+}
+
 impl fmt::Display for ast::Binder {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}: {}", self.name, self.ty)
     }
 }
 
+/// An argument in the brackets it's written in: braces if it's implicit, and parentheses
+/// otherwise. An unnamed instance of a class is written `` `{Ord T} ``, which also binds the
+/// instances of any classes it extends.
+struct Bracketed<'a>(&'a ast::Binder);
+
+impl fmt::Display for Bracketed<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            ast::Binder {
+                name,
+                ty,
+                implicit: true,
+            } if name.is_empty() => write!(f, "`{{{ty}}}"),
+            binder if binder.implicit => write!(f, "{{{binder}}}"),
+            binder => write!(f, "({binder})"),
+        }
+    }
+}
+
+impl fmt::Display for ast::Record {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Record {} := {{", self.name)?;
+        for (i, field) in self.fields.iter().enumerate() {
+            let sep = if i + 1 < self.fields.len() { ";" } else { "" };
+            writeln!(f, "\t{field}{sep}")?;
+        }
+        writeln!(f, "}}.")
+    }
+}
+
+impl fmt::Display for ast::Class {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Class {}", self.name)?;
+        for binder in &self.args {
+            write!(f, " {}", Bracketed(binder))?;
+        }
+        writeln!(f, " := {{")?;
+        for (i, method) in self.methods.iter().enumerate() {
+            let sep = if i + 1 < self.methods.len() { ";" } else { "" };
+            writeln!(f, "\t{method}{sep}")?;
+        }
+        writeln!(f, "}}.")
+    }
+}
+
+impl fmt::Display for ast::Instance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Instance {}", self.name)?;
+        for binder in &self.args {
+            write!(f, " {}", Bracketed(binder))?;
+        }
+        writeln!(f, " : {} := {{", self.class)?;
+        for (i, (method, value)) in self.methods.iter().enumerate() {
+            let sep = if i + 1 < self.methods.len() { ";" } else { "" };
+            let value = value.to_string();
+            writeln!(f, "\t{method} := {}{sep}", value.trim_end())?;
+        }
+        writeln!(f, "}}.")
+    }
+}
+
+impl fmt::Display for ast::Inductive {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Inductive {} :=", self.name)?;
+        for constructor in &self.constructors {
+            write!(f, "\n| {} :", constructor.name)?;
+            for arg in &constructor.args {
+                write!(f, " {arg} ->")?;
+            }
+            write!(f, " {}", self.name)?;
+        }
+        writeln!(f, ".")
+    }
+}
+
 impl fmt::Display for ast::Ty {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             ast::Ty::Nat => write!(f, "nat"),
+            ast::Ty::N => write!(f, "N"),
+            ast::Ty::Z => write!(f, "Z"),
             ast::Ty::Bool => write!(f, "bool"),
+            ast::Ty::Named(name) | ast::Ty::Var(name) => write!(f, "{name}"),
+            ast::Ty::Type => write!(f, "Type"),
+            ast::Ty::Apply { func, args } => {
+                write!(f, "{func}")?;
+                for arg in args {
+                    match arg {
+                        ast::Ty::Apply { args, .. } if !args.is_empty() => write!(f, " ({arg})")?,
+                        ast::Ty::Arrow(_) => write!(f, " ({arg})")?,
+                        _ => write!(f, " {arg}")?,
+                    }
+                }
+                Ok(())
+            }
+
+            // Arrows associate to the right, so only a function argument needs parentheses.
+            ast::Ty::Arrow(tys) => {
+                for (i, ty) in tys.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " -> ")?;
+                    }
+                    match ty {
+                        ast::Ty::Arrow(_) if i + 1 < tys.len() => write!(f, "({ty})")?,
+                        _ => write!(f, "{ty}")?,
+                    }
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -61,17 +245,58 @@ impl fmt::Display for ast::Expr {
             }
             ast::Expr::Var(ident) => write!(f, "{ident}"),
             ast::Expr::Nat(n) => write!(f, "{n}"),
+            ast::Expr::N(n) => write!(f, "{n}%N"),
+            ast::Expr::Z(n) if *n < 0 => write!(f, "({n})%Z"),
+            ast::Expr::Z(n) => write!(f, "{n}%Z"),
             ast::Expr::Bool(b) => write!(f, "{}", if *b { "true" } else { "false" }),
             ast::Expr::LetIn {
                 ident,
+                ty,
                 value,
                 child,
             } => {
-                write!(f, "let {ident} := {value} in")?;
+                match ty {
+                    Some(ty) => write!(f, "let {ident} : {ty} := {value} in")?,
+                    None => write!(f, "let {ident} := {value} in")?,
+                }
                 writeln!(f)?;
                 write!(f, "{}", &child)?;
                 Ok(())
             }
+            ast::Expr::LetPattern {
+                pattern,
+                ty,
+                value,
+                child,
+            } => {
+                match ty {
+                    Some(ty) => write!(f, "let '{pattern} := ({value} : {ty}) in")?,
+                    None => write!(f, "let '{pattern} := {value} in")?,
+                }
+                writeln!(f)?;
+                write!(f, "{}", &child)?;
+                Ok(())
+            }
+            ast::Expr::LetState {
+                pattern,
+                state,
+                value,
+                child,
+            } => {
+                writeln!(f, "let '({pattern}, {}) := {value} in", state.join(", "))?;
+                write!(f, "{}", &child)?;
+                Ok(())
+            }
+            ast::Expr::Bind {
+                monad,
+                ident,
+                value,
+                child,
+            } => {
+                writeln!(f, "{} {ident} := {value} in", monad.keyword())?;
+                write!(f, "{}", &child)?;
+                Ok(())
+            }
             ast::Expr::Match { scrutinee, cases } => {
                 writeln!(f, "match {scrutinee} with")?;
                 for case in cases {
@@ -80,7 +305,150 @@ impl fmt::Display for ast::Expr {
                 }
                 writeln!(f, "end")
             }
+            ast::Expr::BinOp { op, lhs, rhs, .. }
+            | ast::Expr::TraitOp {
+                op,
+                receiver: lhs,
+                arg: rhs,
+                ..
+            } => write!(f, "({lhs} {op} {rhs})"),
+            ast::Expr::UnOp { op, operand, .. } => write!(f, "({op} {operand})"),
             ast::Expr::Tt => write!(f, "tt"),
+            ast::Expr::Tuple(elems) => {
+                write!(f, "(")?;
+                for (i, elem) in elems.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{elem}")?;
+                }
+                write!(f, ")")
+            }
+            ast::Expr::List(elems) => {
+                if elems.is_empty() {
+                    return write!(f, "nil");
+                }
+                write!(f, "(")?;
+                for elem in elems {
+                    write!(f, "{elem} :: ")?;
+                }
+                write!(f, "nil)")
+            }
+            ast::Expr::ListOp { op, args, .. } => {
+                write!(f, "{op}(")?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{arg}")?;
+                }
+                write!(f, ")")
+            }
+            ast::Expr::Lambda { params, body } => {
+                write!(f, "(fun")?;
+                for (param, ty) in params {
+                    match ty {
+                        Some(ty) => write!(f, " ({param} : {ty})")?,
+                        None => write!(f, " {param}")?,
+                    }
+                }
+                write!(f, " => {body})")
+            }
+            ast::Expr::ForRange {
+                var,
+                start,
+                end,
+                body,
+                ..
+            } => write!(f, "for {var} in {start}..{end} ({body})"),
+            ast::Expr::While {
+                cond,
+                measure,
+                body,
+                ..
+            } => write!(f, "while {cond} decreasing {measure} ({body})"),
+            ast::Expr::Return { value, .. } => write!(f, "return {value}"),
+            ast::Expr::Panic => write!(f, "(False_rect _ _)"),
+            ast::Expr::Try {
+                ident,
+                value,
+                child,
+                ..
+            } => {
+                writeln!(f, "let {ident} := {value}? in")?;
+                write!(f, "{}", &child)?;
+                Ok(())
+            }
+            ast::Expr::Field { base, field, .. } => write!(f, "{base}.({field})"),
+            ast::Expr::SetField {
+                base, field, value, ..
+            } => write!(f, "{{| {base} with {field} := {value} |}}"),
+            ast::Expr::Record { fields, .. } => {
+                write!(f, "{{|")?;
+                for (i, (field, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ";")?;
+                    }
+                    write!(f, " {field} := {value}")?;
+                }
+                write!(f, " |}}")
+            }
+        }
+    }
+}
+
+impl fmt::Display for ast::BinOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = match self {
+            ast::BinOp::Add => "+",
+            ast::BinOp::Sub => "-",
+            ast::BinOp::Mul => "*",
+            ast::BinOp::Div => "/",
+            ast::BinOp::Rem => "%",
+            ast::BinOp::Shl => "<<",
+            ast::BinOp::Shr => ">>",
+            ast::BinOp::BitAnd => "&",
+            ast::BinOp::BitOr => "|",
+            ast::BinOp::BitXor => "^",
+            ast::BinOp::And => "&&",
+            ast::BinOp::Or => "||",
+            ast::BinOp::Eq => "==",
+            ast::BinOp::Ne => "!=",
+            ast::BinOp::Lt => "<",
+            ast::BinOp::Le => "<=",
+            ast::BinOp::Gt => ">",
+            ast::BinOp::Ge => ">=",
+        };
+        write!(f, "{symbol}")
+    }
+}
+
+impl fmt::Display for ast::ListOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ast::ListOp::Len => "len",
+            ast::ListOp::IsEmpty => "is_empty",
+            ast::ListOp::First => "first",
+            ast::ListOp::Get => "get",
+            ast::ListOp::Contains => "contains",
+            ast::ListOp::Sum => "sum",
+            ast::ListOp::Map => "map",
+            ast::ListOp::Filter => "filter",
+            ast::ListOp::Fold => "fold",
+            ast::ListOp::Any => "any",
+            ast::ListOp::All => "all",
+            ast::ListOp::Push => "push",
+            ast::ListOp::Repeat => "repeat",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl fmt::Display for ast::UnOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ast::UnOp::Neg => write!(f, "-"),
+            ast::UnOp::Not => write!(f, "!"),
         }
     }
 }
@@ -89,6 +457,69 @@ impl fmt::Display for ast::Pattern {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             ast::Pattern::Expr(e) => write!(f, "{e}"),
+            ast::Pattern::Wildcard => write!(f, "_"),
+            ast::Pattern::Ident(ident) => write!(f, "{ident}"),
+            ast::Pattern::Constructor { name, args } => {
+                write!(f, "{name}")?;
+                for arg in args {
+                    // Only nullary constructors and variables can be written without parentheses.
+                    match arg {
+                        ast::Pattern::Constructor { args, .. } if args.is_empty() => {
+                            write!(f, " {arg}")?
+                        }
+                        ast::Pattern::Wildcard
+                        | ast::Pattern::Ident(_)
+                        | ast::Pattern::Tuple(_)
+                        | ast::Pattern::Record { .. }
+                        | ast::Pattern::Expr(
+                            ast::Expr::Nat(_)
+                            | ast::Expr::N(_)
+                            | ast::Expr::Z(_)
+                            | ast::Expr::Bool(_),
+                        ) => write!(f, " {arg}")?,
+                        _ => write!(f, " ({arg})")?,
+                    }
+                }
+                Ok(())
+            }
+            ast::Pattern::As { pattern, ident } => write!(f, "{pattern} as {ident}"),
+            ast::Pattern::Tuple(elems) => {
+                write!(f, "(")?;
+                for (i, elem) in elems.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    match elem {
+                        ast::Pattern::Or(_) | ast::Pattern::As { .. } => write!(f, "({elem})")?,
+                        _ => write!(f, "{elem}")?,
+                    }
+                }
+                write!(f, ")")
+            }
+            ast::Pattern::Record { fields } => {
+                write!(f, "{{|")?;
+                for (i, (field, pattern)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ";")?;
+                    }
+                    write!(f, " {field} := {pattern}")?;
+                }
+                write!(f, " |}}")
+            }
+            ast::Pattern::Or(alternatives) => {
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " | ")?;
+                    }
+                    match alternative {
+                        ast::Pattern::Or(_) | ast::Pattern::As { .. } => {
+                            write!(f, "({alternative})")?
+                        }
+                        _ => write!(f, "{alternative}")?,
+                    }
+                }
+                Ok(())
+            }
         }
     }
 }
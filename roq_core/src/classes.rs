@@ -0,0 +1,145 @@
+//! The Coq typeclasses which bounds on Rust's standard traits are translated to.
+//!
+//! Each class is named after the trait it mirrors, and has a method for each operator or
+//! function of the trait which can be translated, such as `Ord_ltb` for `<`. It comes with
+//! instances for the types integers and `bool`s are modelled with, so that a generic function
+//! can be applied to them.
+
+use crate::ast::{self, Ty};
+use crate::typing::operator;
+
+/// The class which a bound on the trait `name` is translated to. `Eq` and `PartialOrd` are the
+/// same as `PartialEq` and `Ord`, since every value of a type modelled in Coq is comparable.
+pub fn class_of_trait(name: &str) -> Option<&'static str> {
+    match name {
+        "PartialEq" | "Eq" => Some("PartialEq"),
+        "PartialOrd" | "Ord" => Some("Ord"),
+        "Add" => Some("Add"),
+        "Default" => Some("Default"),
+        _ => None,
+    }
+}
+
+/// The class which `class` extends, whose methods can be used on a type bounded by it too.
+pub fn superclass(class: &str) -> Option<&'static str> {
+    match class {
+        "Ord" => Some("PartialEq"),
+        _ => None,
+    }
+}
+
+/// The class which defines the method implementing `op`, if any does.
+pub fn class_of_operator(op: ast::BinOp) -> Option<&'static str> {
+    match op {
+        ast::BinOp::Eq | ast::BinOp::Ne => Some("PartialEq"),
+        ast::BinOp::Lt | ast::BinOp::Le | ast::BinOp::Gt | ast::BinOp::Ge => Some("Ord"),
+        ast::BinOp::Add => Some("Add"),
+        _ => None,
+    }
+}
+
+/// The statements defining the class `class`, followed by its instances, which use functions
+/// from `NArith`, `ZArith` and `Bool`. The class it extends, if any, must be defined first.
+pub fn class_statements(class: &str) -> Option<Vec<ast::Statement>> {
+    let t = || Ty::Var("T".into());
+    let binary = |ret| Ty::arrow(vec![t(), t()], ret);
+    let (methods, extends) = match class {
+        "PartialEq" => (vec![("eqb", binary(Ty::Bool))], vec![]),
+        "Ord" => (
+            vec![("ltb", binary(Ty::Bool)), ("leb", binary(Ty::Bool))],
+            vec![ast::Binder::instance("PartialEq", t())],
+        ),
+        "Add" => (vec![("add", binary(t()))], vec![]),
+        "Default" => (vec![("default", t())], vec![]),
+        _ => return None,
+    };
+    let mut args = vec![ast::Binder {
+        name: "T".into(),
+        ty: Ty::Type,
+        implicit: false,
+    }];
+    args.extend(extends);
+    let mut statements = vec![ast::Statement::Class(ast::Class {
+        name: class.into(),
+        args,
+        methods: methods
+            .into_iter()
+            .map(|(method, ty)| ast::Binder {
+                name: ast::Class::method(class, method),
+                ty,
+                implicit: false,
+            })
+            .collect(),
+    })];
+
+    // The integer types implement each class with the functions their operators are translated
+    // to.
+    let ops = |ty: &Ty, ops: &[(&'static str, ast::BinOp)]| {
+        ops.iter()
+            .map(|(method, op)| {
+                let func = operator(*op, ty).expect("integer types should support operator");
+                (*method, ast::Expr::Var(func.into()))
+            })
+            .collect::<Vec<_>>()
+    };
+    let instances: Vec<(Ty, Vec<(&str, ast::Expr)>)> = match class {
+        "PartialEq" => [Ty::Nat, Ty::N, Ty::Z, Ty::Bool]
+            .into_iter()
+            .map(|ty| {
+                let methods = ops(&ty, &[("eqb", ast::BinOp::Eq)]);
+                (ty, methods)
+            })
+            .collect(),
+        "Ord" => [Ty::Nat, Ty::N, Ty::Z]
+            .into_iter()
+            .map(|ty| {
+                let methods = ops(&ty, &[("ltb", ast::BinOp::Lt), ("leb", ast::BinOp::Le)]);
+                (ty, methods)
+            })
+            .collect(),
+        "Add" => [Ty::Nat, Ty::N, Ty::Z]
+            .into_iter()
+            .map(|ty| {
+                let methods = ops(&ty, &[("add", ast::BinOp::Add)]);
+                (ty, methods)
+            })
+            .collect(),
+        _ => vec![
+            (Ty::Nat, vec![("default", ast::Expr::Nat(0))]),
+            (Ty::N, vec![("default", ast::Expr::N(0))]),
+            (Ty::Z, vec![("default", ast::Expr::Z(0))]),
+            (Ty::Bool, vec![("default", ast::Expr::Bool(false))]),
+            (Ty::list(t()), vec![("default", ast::Expr::List(vec![]))]),
+            (
+                Ty::option(t()),
+                vec![("default", ast::Expr::Var("None".into()))],
+            ),
+        ],
+    };
+    for (ty, methods) in instances {
+        let (head, args) = match &ty {
+            Ty::Apply { func, .. } => (
+                func.clone(),
+                vec![ast::Binder {
+                    name: "T".into(),
+                    ty: Ty::Type,
+                    implicit: true,
+                }],
+            ),
+            ty => (ty.to_string(), vec![]),
+        };
+        statements.push(ast::Statement::Instance(ast::Instance {
+            name: ast::Instance::name(class, &head),
+            args,
+            class: Ty::Apply {
+                func: class.into(),
+                args: vec![ty],
+            },
+            methods: methods
+                .into_iter()
+                .map(|(method, value)| (ast::Class::method(class, method), value))
+                .collect(),
+        }));
+    }
+    Some(statements)
+}
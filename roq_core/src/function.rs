@@ -0,0 +1,275 @@
+//! Functions translated from Rust, which are elaborated once the items they use are known.
+//!
+//! A function's body can only be elaborated once the signatures of the functions it calls are
+//! known, and those are defined by other invocations of the macros. So each function is
+//! elaborated when the vernacular of the module generated alongside it is built, which also
+//! builds the vernacular of its callees.
+
+use std::cell::RefCell;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ast::{self, Binder, Expr, Ident, Statement, Ty, Vernacular};
+use crate::typing::{Numbers, Signature, TypeError, Typing};
+
+/// A function translated from Rust, before its body is elaborated.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Function {
+    /// The name of the function, which recursive calls refer to it by, before it's qualified by
+    /// the module defining it.
+    pub name: Ident,
+    pub args: Vec<Binder>,
+    pub ret: Ty,
+    pub body: Expr,
+
+    /// The argument each recursive call is structurally decreasing on, for a `Fixpoint`.
+    pub decreasing: Option<Ident>,
+
+    /// The measure which decreases on each recursive call, for a `Program Fixpoint`.
+    pub measure: Option<Expr>,
+
+    /// The classes each type parameter is bounded by, as `(param, class)`.
+    pub bounds: Vec<(Ident, Ident)>,
+
+    /// Whether arithmetic on unsigned integers panics where Rust's would.
+    pub checked: bool,
+
+    /// The type unsigned integers are modelled with.
+    pub numbers: Numbers,
+
+    /// Where the function is in the Rust source, as `file:line:column`, for reporting the errors
+    /// in it which aren't at any [`Loc`](ast::Loc).
+    pub location: String,
+
+    /// Where each [`Loc`](ast::Loc) in the body is in the Rust source, as `file:line:column`.
+    pub locations: Vec<String>,
+}
+
+impl Function {
+    /// The function's type, and its Coq name, qualified by `prefix` (see [`prefix`]).
+    pub fn signature(&self, prefix: &str) -> Signature {
+        Signature {
+            name: format!("{prefix}{}", self.name),
+            params: self
+                .args
+                .iter()
+                .filter(|arg| arg.ty == Ty::Type)
+                .map(|arg| arg.name.clone())
+                .collect(),
+            args: self
+                .args
+                .iter()
+                .filter(|arg| !arg.implicit)
+                .map(|arg| arg.ty.clone())
+                .collect(),
+            ret: self.ret.clone(),
+            numbers: self.numbers,
+        }
+    }
+
+    /// Elaborate the function's body, given the signatures of the functions it calls and the
+    /// records it uses, and return the statement defining it. The modules it needs and its
+    /// auxiliary statements are left in `typing`.
+    pub fn elaborate(&self, typing: &mut Typing) -> Result<Statement, TypeError> {
+        typing.checked = self.checked;
+        typing.numbers = self.numbers;
+        for (param, class) in &self.bounds {
+            typing.bound(param.clone(), class);
+        }
+        let signature = self.signature(&typing.prefix);
+        let name = signature.name.clone();
+        typing.declare(self.name.clone(), signature);
+        for arg in self.args.iter().filter(|arg| !arg.implicit) {
+            typing.bind(arg.name.clone(), Some(arg.ty.clone()));
+        }
+        let body = typing.check(&self.body, &self.ret)?;
+        let measure = self
+            .measure
+            .as_ref()
+            .map(|measure| typing.check_measure(measure))
+            .transpose()?;
+
+        let (args, ret) = (self.args.clone(), self.ret.clone());
+        let statement = match (&self.decreasing, measure) {
+            (_, Some(measure)) => {
+                typing.require("Program");
+                Statement::ProgramFixpoint(ast::ProgramFixpoint {
+                    name,
+                    args,
+                    ret,
+                    body,
+                    measure,
+                })
+            }
+            (Some(decreasing), None) => Statement::Fixpoint(ast::Fixpoint {
+                name,
+                args,
+                ret,
+                body,
+                decreasing: decreasing.clone(),
+                program: typing.panics,
+            }),
+            (None, None) => Statement::Definition(ast::Definition {
+                name,
+                args,
+                ret,
+                body,
+                program: typing.panics,
+            }),
+        };
+        Ok(statement)
+    }
+}
+
+/// A function called by another, as exposed by the module generated alongside it.
+pub struct Callee {
+    /// The path the function is called by, such as `util::max`, which the caller applies it by
+    /// before it's elaborated.
+    pub path: &'static str,
+    pub signature: fn() -> Signature,
+    pub vernacular: fn() -> Result<Vernacular, TranslationError>,
+}
+
+/// A type error found elaborating a function, once the functions and types it uses are known,
+/// located in the Rust source.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TranslationError {
+    /// The Coq name of the function.
+    pub name: Ident,
+
+    /// Where the error is, as `file:line:column`.
+    pub location: String,
+
+    pub message: String,
+}
+
+impl TranslationError {
+    fn new(function: &Function, name: &str, err: TypeError) -> TranslationError {
+        let location = err
+            .loc
+            .and_then(|loc| function.locations.get(loc.0 as usize))
+            .unwrap_or(&function.location);
+        TranslationError {
+            name: name.into(),
+            location: location.clone(),
+            message: err.message,
+        }
+    }
+}
+
+impl fmt::Display for TranslationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: can't translate `{}` to Coq: {}",
+            self.location, self.name, self.message
+        )
+    }
+}
+
+impl std::error::Error for TranslationError {}
+
+/// The prefix qualifying the Coq names of the functions defined in the module at `module_path`
+/// (as given by `module_path!()`), so that those of the same name in different modules don't
+/// clash: `a_b_` in `krate::a::b`, or nothing at the root of the crate.
+pub fn prefix(module_path: &str) -> String {
+    module_path
+        .split("::")
+        .skip(1)
+        .map(|module| format!("{module}_"))
+        .collect()
+}
+
+/// The vernacular of `functions`, named with `prefix`, preceded by the modules they need, the
+/// vernacular of the types and functions they use, and their auxiliary statements.
+///
+/// Returns an error, located where it is in the Rust source, if a function or one of its callees
+/// can't be elaborated. Panics if a function is mutually recursive with one of its callees, since
+/// Coq can't define them one after the other.
+pub fn vernacular(
+    prefix: &str,
+    functions: &[Function],
+    types: &[fn() -> Vernacular],
+    callees: &[Callee],
+) -> Result<Vernacular, TranslationError> {
+    let names = functions
+        .iter()
+        .map(|function| format!("{prefix}{}", function.name))
+        .collect::<Vec<_>>();
+    let _visiting = Visiting::enter(&names);
+    let mut dependencies = Vernacular::default();
+    for ty in types {
+        dependencies.extend(ty().statements);
+    }
+    for callee in callees {
+        dependencies.extend((callee.vernacular)()?.statements);
+    }
+
+    let mut requires = vec![];
+    let mut auxiliary = Vernacular::default();
+    let mut statements = vec![];
+    for (function, name) in functions.iter().zip(&names) {
+        let mut typing = Typing::default();
+        typing.prefix = prefix.into();
+        for statement in &dependencies.statements {
+            if let Statement::Record(record) = statement {
+                typing.define(record);
+            }
+        }
+        for callee in callees {
+            typing.declare(callee.path.into(), (callee.signature)());
+        }
+        let statement = function
+            .elaborate(&mut typing)
+            .map_err(|err| TranslationError::new(function, name, err))?;
+        requires.extend(typing.requires);
+        auxiliary.extend(typing.auxiliary);
+        statements.push(statement);
+    }
+
+    let mut vernacular = Vernacular::default();
+    vernacular.extend(requires.into_iter().map(Statement::Require));
+    vernacular.extend(dependencies.statements);
+    vernacular.extend(auxiliary.statements);
+    vernacular.extend(statements);
+    Ok(vernacular)
+}
+
+thread_local! {
+    /// The functions whose vernacular is being built, outermost first.
+    static VISITING: RefCell<Vec<Ident>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Marks functions as having their vernacular built until it's dropped.
+struct Visiting(usize);
+
+impl Visiting {
+    /// Mark the functions `names` as being built, panicking if any already is, since it must
+    /// call itself through its callees.
+    fn enter(names: &[Ident]) -> Visiting {
+        VISITING.with(|visiting| {
+            let mut visiting = visiting.borrow_mut();
+            for name in names {
+                if let Some(i) = visiting.iter().position(|visited| visited == name) {
+                    let mut cycle = format!("`{name}`");
+                    for (j, callee) in visiting[i + 1..].iter().chain([name]).enumerate() {
+                        let calls = if j == 0 { " calls" } else { ", which calls" };
+                        cycle.push_str(&format!("{calls} `{callee}`"));
+                    }
+                    drop(visiting);
+                    panic!("can't translate mutually recursive functions to Coq: {cycle}");
+                }
+            }
+            let depth = visiting.len();
+            visiting.extend(names.iter().cloned());
+            Visiting(depth)
+        })
+    }
+}
+
+impl Drop for Visiting {
+    fn drop(&mut self) {
+        VISITING.with(|visiting| visiting.borrow_mut().truncate(self.0));
+    }
+}
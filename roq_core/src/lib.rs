@@ -1,3 +1,17 @@
 pub mod ast;
+pub mod classes;
+pub mod function;
+pub mod rename;
+pub mod typing;
 
 mod ast_display;
+
+/// A Rust type with a Coq translation, such as a struct annotated with `#[record]`.
+pub trait CoqType {
+    /// The names the type's statements define, as they're named in the Rust source, along with
+    /// their Coq names, which are qualified by the module defining the type (see [`rename`]).
+    fn names() -> Vec<(ast::Ident, ast::Ident)>;
+
+    /// The statements defining the type, preceded by those of any types it refers to.
+    fn vernacular() -> ast::Vernacular;
+}
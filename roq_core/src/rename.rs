@@ -0,0 +1,279 @@
+//! Qualifying the names a user-defined type defines by the module defining it.
+//!
+//! The statements of a `#[record]` or `#[inductive]` type, and the functions using it, are
+//! translated with the names in the Rust source, like `Point` and its projection `Point_x`, since
+//! the module the type is defined in is only known once the crate is compiled. The names are then
+//! replaced with their qualified ones, like `geometry_Point` and `geometry_Point_x`, when the
+//! vernacular is built, so that types of the same name in different modules don't clash.
+
+use std::collections::HashMap;
+
+use crate::ast::{Binder, Expr, Ident, MatchCase, Pattern, Statement, Ty};
+use crate::function::Function;
+
+/// The qualified name of each name defined by a user-defined type, by its name in the Rust source.
+pub type Names = HashMap<Ident, Ident>;
+
+/// The names `statements` define, other than those of local variables: the names of records,
+/// inductive types and definitions, along with the projections of records and the constructors
+/// of inductive types.
+pub fn defined(statements: &[Statement]) -> Vec<Ident> {
+    let mut names = vec![];
+    for statement in statements {
+        match statement {
+            Statement::Record(record) => {
+                names.push(record.name.clone());
+                names.extend(record.fields.iter().map(|field| field.name.clone()));
+            }
+            Statement::Inductive(inductive) => {
+                names.push(inductive.name.clone());
+                names.extend(inductive.constructors.iter().map(|c| c.name.clone()));
+            }
+            Statement::Definition(definition) => names.push(definition.name.clone()),
+            _ => {}
+        }
+    }
+    names
+}
+
+/// Replace the names found in `names` with their qualified ones.
+pub trait Rename {
+    fn rename(&mut self, names: &Names);
+}
+
+fn rename_ident(ident: &mut Ident, names: &Names) {
+    if let Some(qualified) = names.get(ident) {
+        *ident = qualified.clone();
+    }
+}
+
+impl<T: Rename> Rename for Vec<T> {
+    fn rename(&mut self, names: &Names) {
+        for item in self {
+            item.rename(names);
+        }
+    }
+}
+
+impl<T: Rename> Rename for Option<T> {
+    fn rename(&mut self, names: &Names) {
+        if let Some(item) = self {
+            item.rename(names);
+        }
+    }
+}
+
+impl<T: Rename> Rename for Box<T> {
+    fn rename(&mut self, names: &Names) {
+        (**self).rename(names);
+    }
+}
+
+impl Rename for Statement {
+    fn rename(&mut self, names: &Names) {
+        match self {
+            Statement::Require(_) | Statement::BindNotation(_) => {}
+            Statement::Definition(definition) => {
+                rename_ident(&mut definition.name, names);
+                definition.args.rename(names);
+                definition.ret.rename(names);
+                definition.body.rename(names);
+            }
+            Statement::Fixpoint(fixpoint) => {
+                rename_ident(&mut fixpoint.name, names);
+                fixpoint.args.rename(names);
+                fixpoint.ret.rename(names);
+                fixpoint.body.rename(names);
+            }
+            Statement::ProgramFixpoint(fixpoint) => {
+                rename_ident(&mut fixpoint.name, names);
+                fixpoint.args.rename(names);
+                fixpoint.ret.rename(names);
+                fixpoint.body.rename(names);
+                fixpoint.measure.rename(names);
+            }
+            Statement::Record(record) => {
+                rename_ident(&mut record.name, names);
+                record.fields.rename(names);
+            }
+            Statement::Inductive(inductive) => {
+                rename_ident(&mut inductive.name, names);
+                for constructor in &mut inductive.constructors {
+                    rename_ident(&mut constructor.name, names);
+                    constructor.args.rename(names);
+                }
+            }
+            Statement::Lemma(lemma) => {
+                lemma.args.rename(names);
+                lemma.lhs.rename(names);
+                lemma.rhs.rename(names);
+            }
+            Statement::Class(class) => {
+                class.args.rename(names);
+                class.methods.rename(names);
+            }
+            Statement::Instance(instance) => {
+                instance.args.rename(names);
+                instance.class.rename(names);
+                for (_, method) in &mut instance.methods {
+                    method.rename(names);
+                }
+            }
+        }
+    }
+}
+
+impl Rename for Function {
+    fn rename(&mut self, names: &Names) {
+        self.args.rename(names);
+        self.ret.rename(names);
+        self.body.rename(names);
+        self.measure.rename(names);
+    }
+}
+
+impl Rename for Binder {
+    fn rename(&mut self, names: &Names) {
+        rename_ident(&mut self.name, names);
+        self.ty.rename(names);
+    }
+}
+
+impl Rename for Ty {
+    fn rename(&mut self, names: &Names) {
+        match self {
+            Ty::Nat | Ty::N | Ty::Z | Ty::Bool | Ty::Var(_) | Ty::Type => {}
+            Ty::Named(name) => rename_ident(name, names),
+            Ty::Arrow(tys) => tys.rename(names),
+            Ty::Apply { func, args } => {
+                rename_ident(func, names);
+                args.rename(names);
+            }
+        }
+    }
+}
+
+impl Rename for Expr {
+    fn rename(&mut self, names: &Names) {
+        match self {
+            Expr::Apply { func, args } => {
+                rename_ident(func, names);
+                args.rename(names);
+            }
+            Expr::Match { scrutinee, cases } => {
+                scrutinee.rename(names);
+                cases.rename(names);
+            }
+            Expr::Var(ident) => rename_ident(ident, names),
+            Expr::LetIn {
+                ty, value, child, ..
+            } => {
+                ty.rename(names);
+                value.rename(names);
+                child.rename(names);
+            }
+            Expr::LetPattern {
+                pattern,
+                ty,
+                value,
+                child,
+            } => {
+                pattern.rename(names);
+                ty.rename(names);
+                value.rename(names);
+                child.rename(names);
+            }
+            Expr::LetState {
+                pattern,
+                value,
+                child,
+                ..
+            } => {
+                pattern.rename(names);
+                value.rename(names);
+                child.rename(names);
+            }
+            Expr::BinOp { lhs, rhs, .. } => {
+                lhs.rename(names);
+                rhs.rename(names);
+            }
+            Expr::TraitOp { receiver, arg, .. } => {
+                receiver.rename(names);
+                arg.rename(names);
+            }
+            Expr::UnOp { operand, .. } => operand.rename(names),
+            Expr::Nat(_) | Expr::N(_) | Expr::Z(_) | Expr::Bool(_) | Expr::Tt | Expr::Panic => {}
+            Expr::Tuple(elems) | Expr::List(elems) | Expr::ListOp { args: elems, .. } => {
+                elems.rename(names)
+            }
+            Expr::Lambda { params, body } => {
+                for (_, ty) in params {
+                    ty.rename(names);
+                }
+                body.rename(names);
+            }
+            Expr::ForRange {
+                start, end, body, ..
+            } => {
+                start.rename(names);
+                end.rename(names);
+                body.rename(names);
+            }
+            Expr::While {
+                cond,
+                measure,
+                body,
+                ..
+            } => {
+                cond.rename(names);
+                measure.rename(names);
+                body.rename(names);
+            }
+            Expr::Return { value, .. } => value.rename(names),
+            Expr::Try { value, child, .. } | Expr::Bind { value, child, .. } => {
+                value.rename(names);
+                child.rename(names);
+            }
+            Expr::Record { name, fields } => {
+                rename_ident(name, names);
+                for (field, value) in fields {
+                    rename_ident(field, names);
+                    value.rename(names);
+                }
+            }
+            Expr::Field { base, .. } => base.rename(names),
+            Expr::SetField { base, value, .. } => {
+                base.rename(names);
+                value.rename(names);
+            }
+        }
+    }
+}
+
+impl Rename for MatchCase {
+    fn rename(&mut self, names: &Names) {
+        self.pattern.rename(names);
+        self.body.rename(names);
+    }
+}
+
+impl Rename for Pattern {
+    fn rename(&mut self, names: &Names) {
+        match self {
+            Pattern::Expr(expr) => expr.rename(names),
+            Pattern::Wildcard | Pattern::Ident(_) => {}
+            Pattern::Constructor { name, args } => {
+                rename_ident(name, names);
+                args.rename(names);
+            }
+            Pattern::As { pattern, .. } => pattern.rename(names),
+            Pattern::Tuple(patterns) | Pattern::Or(patterns) => patterns.rename(names),
+            Pattern::Record { fields } => {
+                for (field, pattern) in fields {
+                    rename_ident(field, names);
+                    pattern.rename(names);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,2366 @@
+//! Type inference over the Coq AST.
+//!
+//! The frontend emits operators as `BinOp` and `UnOp` nodes, and integer literals as `Nat`s,
+//! since it can't tell what type their operands have. This pass infers the type of every
+//! expression from the function's arguments, `let` annotations and literals, replaces each
+//! operator with the Coq function implementing it for that type, and gives each literal the type
+//! of its context.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ast::{self, Ty};
+use crate::classes;
+
+/// A type error, reported at the operator it was found in if there is one.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TypeError {
+    pub loc: Option<ast::Loc>,
+    pub message: String,
+}
+
+impl TypeError {
+    fn new(message: impl Into<String>) -> TypeError {
+        TypeError {
+            loc: None,
+            message: message.into(),
+        }
+    }
+
+    /// Attribute the error to `loc`, unless it has already been attributed to a nested operator.
+    fn at(mut self, loc: ast::Loc) -> TypeError {
+        self.loc.get_or_insert(loc);
+        self
+    }
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for TypeError {}
+
+/// The type of a function which may be called from the expressions being elaborated.
+#[derive(Debug, Clone)]
+pub struct Signature {
+    /// The function's Coq name, which calls to it are elaborated to.
+    pub name: ast::Ident,
+
+    /// The function's type parameters, which its implicit arguments bind.
+    pub params: Vec<ast::Ident>,
+
+    pub args: Vec<Ty>,
+    pub ret: Ty,
+
+    /// The type the function's unsigned integers are modelled with, which its callers' may not
+    /// be.
+    pub numbers: Numbers,
+}
+
+/// The Coq type Rust's unsigned integers (`u64` and `usize`) are modelled with.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, Eq, PartialEq)]
+pub enum Numbers {
+    /// Unary `nat`s, which are easiest to prove things about by induction, but whose literals
+    /// take space proportional to their value.
+    #[default]
+    Nat,
+
+    /// Binary `N`s, which are efficient to compute with.
+    N,
+
+    /// `Z`s, the same type as signed integers.
+    Z,
+
+    /// `N`s which wrap around at `2^64`, like `u64`s without overflow checks.
+    Word,
+}
+
+impl Numbers {
+    /// The type of an unsigned integer.
+    pub fn ty(self) -> Ty {
+        match self {
+            Numbers::Nat => Ty::Nat,
+            Numbers::N | Numbers::Word => Ty::N,
+            Numbers::Z => Ty::Z,
+        }
+    }
+}
+
+impl FromStr for Numbers {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Numbers, String> {
+        match s {
+            "nat" => Ok(Numbers::Nat),
+            "N" => Ok(Numbers::N),
+            "Z" => Ok(Numbers::Z),
+            "word" => Ok(Numbers::Word),
+            _ => Err(format!(
+                "unsupported numbers `{s}`, expected `nat`, `N`, `Z` or `word`"
+            )),
+        }
+    }
+}
+
+/// The state of the typing pass: the variables in scope, and the functions which can be called.
+#[derive(Debug, Default)]
+pub struct Typing {
+    /// Variables in scope, innermost last. Variables of unknown type still shadow outer ones.
+    locals: Vec<(ast::Ident, Option<Ty>)>,
+
+    /// The index in `locals` of the first variable of the function being elaborated, since the
+    /// auxiliary functions loops are replaced with can't refer to variables outside them.
+    frame: usize,
+
+    /// Functions with known signatures, along with the names they're called by in the expressions
+    /// being elaborated.
+    functions: Vec<(ast::Ident, Signature)>,
+
+    /// The fields of the records with known definitions, named by their projections.
+    fields: Vec<ast::Binder>,
+
+    /// Standard library modules defining the types and functions used, in order of first use.
+    pub requires: Vec<ast::Ident>,
+
+    /// Statements defining the auxiliary functions which loops were replaced with, lemmas about
+    /// them, and the notations used for `?`, in the order they need to be defined.
+    pub auxiliary: Vec<ast::Statement>,
+
+    /// Whether the expressions elaborated may panic, outside the bodies of loops, so the function
+    /// must be defined with `Program`.
+    pub panics: bool,
+
+    /// Whether arithmetic on `nat`s panics where the `u64`s they model would overflow or
+    /// underflow, or be divided by zero.
+    pub checked: bool,
+
+    /// The type unsigned integers have, which is also used for literals whose type isn't known.
+    pub numbers: Numbers,
+
+    /// The classes each type parameter is bounded by, as `(param, class)`.
+    bounds: Vec<(ast::Ident, ast::Ident)>,
+
+    /// Whether the functions and records used may not be known, as when a function is checked
+    /// before its vernacular is built, so that the fields of values of unknown type can be used.
+    pub partial: bool,
+
+    /// The prefix qualifying the name of the function being elaborated, and those of the
+    /// auxiliary functions its loops are replaced with, by the module it's defined in.
+    pub prefix: String,
+}
+
+impl Typing {
+    /// Make a function callable from the expressions being elaborated, which apply it by the name
+    /// `called`.
+    pub fn declare(&mut self, called: ast::Ident, signature: Signature) {
+        self.functions.push((called, signature));
+    }
+
+    /// Make the types of a record's fields known, so that accessing one has its type.
+    pub fn define(&mut self, record: &ast::Record) {
+        self.fields.extend(record.fields.iter().cloned());
+    }
+
+    /// The type of the field with the projection `projection`, if its record is defined.
+    fn field_ty(&self, projection: &str) -> Option<Ty> {
+        let field = self.fields.iter().find(|field| field.name == projection)?;
+        Some(field.ty.clone())
+    }
+
+    /// Bound a type parameter by a class, so that its methods can be used on values of that type.
+    pub fn bound(&mut self, param: ast::Ident, class: &str) {
+        self.use_class(class);
+        self.bounds.push((param, class.into()));
+    }
+
+    /// Whether values of the type parameter `param` have the methods of `class`, because it's
+    /// bounded by it or by a class extending it.
+    fn has_bound(&self, param: &str, class: &str) -> bool {
+        self.bounds.iter().any(|(bounded, bound)| {
+            bounded == param
+                && std::iter::successors(Some(bound.as_str()), |bound| classes::superclass(bound))
+                    .any(|bound| bound == class)
+        })
+    }
+
+    /// Define a class from `classes`, along with its instances and the class it extends, before
+    /// any other class which might extend it and anything else which might use it.
+    fn use_class(&mut self, class: &str) {
+        if let Some(superclass) = classes::superclass(class) {
+            self.use_class(superclass);
+        }
+        let statements = classes::class_statements(class).expect("class should be defined");
+        let mut at = self
+            .auxiliary
+            .iter()
+            .rposition(|stmt| {
+                matches!(stmt, ast::Statement::Class(_) | ast::Statement::Instance(_))
+            })
+            .map_or(0, |i| i + 1);
+        for statement in statements {
+            if !self.auxiliary.contains(&statement) {
+                self.auxiliary.insert(at, statement);
+                at += 1;
+            }
+        }
+        for module in ["NArith", "ZArith", "Bool"] {
+            self.require(module);
+        }
+    }
+
+    /// Bring a variable into scope.
+    pub fn bind(&mut self, name: ast::Ident, ty: Option<Ty>) {
+        if let Some(ty) = &ty {
+            self.record(ty);
+        }
+        self.locals.push((name, ty));
+    }
+
+    /// Elaborate an expression which must have type `ty`.
+    pub fn check(&mut self, expr: &ast::Expr, ty: &Ty) -> Result<ast::Expr, TypeError> {
+        Ok(self.elaborate(expr, Some(ty))?.0)
+    }
+
+    /// Elaborate the measure of a recursive function or loop. This isn't evaluated by Rust, so its
+    /// arithmetic is never checked, and never wraps around. A measure of unsigned integers which
+    /// aren't `nat`s is converted to one.
+    pub fn check_measure(&mut self, measure: &ast::Expr) -> Result<ast::Expr, TypeError> {
+        let checked = std::mem::replace(&mut self.checked, false);
+        let numbers = self.numbers;
+        if numbers == Numbers::Word {
+            self.numbers = Numbers::N;
+        }
+        let measure = match self.infer(measure) {
+            Ok((measure, Some(Ty::Nat))) => Ok(measure),
+            Ok((measure, Some(ty @ (Ty::N | Ty::Z)))) => Ok(ast::Expr::Apply {
+                func: format!("{ty}.to_nat"),
+                args: vec![measure],
+            }),
+            Ok((_, Some(ty))) => Err(TypeError::new(format!(
+                "mismatched types: expected an integer measure, found `{ty}`"
+            ))),
+            Ok((_, None)) => self.check(measure, &Ty::Nat),
+            Err(err) => Err(err),
+        };
+        self.checked = checked;
+        self.numbers = numbers;
+        measure
+    }
+
+    /// Elaborate an expression, inferring its type if possible.
+    ///
+    /// Integer literals which aren't constrained by their context remain `nat`s, but have no type
+    /// as far as inference is concerned, so that they can still be combined with a `Z`.
+    pub fn infer(&mut self, expr: &ast::Expr) -> Result<(ast::Expr, Option<Ty>), TypeError> {
+        self.elaborate(expr, None)
+    }
+
+    /// Import the modules defining the types which `ty` is built from.
+    pub fn record(&mut self, ty: &Ty) {
+        match ty {
+            Ty::N => self.require("NArith"),
+            Ty::Z => self.require("ZArith"),
+            Ty::Apply { args, .. } => {
+                for arg in args {
+                    self.record(arg);
+                }
+            }
+            Ty::Arrow(tys) => {
+                for ty in tys {
+                    self.record(ty);
+                }
+            }
+            Ty::Nat | Ty::Bool | Ty::Named(_) | Ty::Var(_) | Ty::Type => {}
+        }
+    }
+
+    /// Import a module from the standard library, unless it already has been.
+    pub fn require(&mut self, module: &str) {
+        if !self.requires.iter().any(|required| required == module) {
+            self.requires.push(module.into());
+        }
+    }
+
+    /// Apply the Coq function implementing a binary operator on operands of type `ty`.
+    ///
+    /// Coq only defines `eqb`, `ltb` and `leb`, so the other comparisons are written in terms of
+    /// them: `a != b` is `negb (eqb a b)`, and `a > b` is `ltb b a`. On a type parameter, the
+    /// operator is the method of the class it's bounded by, such as `Ord_ltb`.
+    fn apply_operator(
+        &mut self,
+        op: ast::BinOp,
+        ty: &Ty,
+        lhs: ast::Expr,
+        rhs: ast::Expr,
+    ) -> Result<ast::Expr, TypeError> {
+        let original = op;
+        let (op, args) = match op {
+            ast::BinOp::Ne => {
+                let eq = self.apply_operator(ast::BinOp::Eq, ty, lhs, rhs)?;
+                return Ok(ast::Expr::Apply {
+                    func: "negb".into(),
+                    args: vec![eq],
+                });
+            }
+            ast::BinOp::Gt => (ast::BinOp::Lt, vec![rhs, lhs]),
+            ast::BinOp::Ge => (ast::BinOp::Le, vec![rhs, lhs]),
+            _ => (op, vec![lhs, rhs]),
+        };
+
+        let func = match ty {
+            Ty::Var(param) => self.class_operator(original, op, param)?,
+            ty => operator(op, ty)
+                .ok_or_else(|| {
+                    TypeError::new(format!("operator `{original}` isn't supported on `{ty}`"))
+                })?
+                .into(),
+        };
+        if func.starts_with("Bool.") {
+            self.require("Bool");
+        }
+        Ok(ast::Expr::Apply { func, args })
+    }
+
+    /// The method implementing `op` on values of the type parameter `param`, which must be
+    /// bounded by the class defining it. `original` is the operator as it was written, before
+    /// being rewritten as `op`.
+    fn class_operator(
+        &self,
+        original: ast::BinOp,
+        op: ast::BinOp,
+        param: &str,
+    ) -> Result<ast::Ident, TypeError> {
+        let unsupported = || format!("operator `{original}` isn't supported on `{param}`");
+        let class = classes::class_of_operator(op).ok_or_else(|| TypeError::new(unsupported()))?;
+        if !self.has_bound(param, class) {
+            let bound = match class {
+                "Add" => format!("Add<Output = {param}>"),
+                class => class.into(),
+            };
+            return Err(TypeError::new(format!(
+                "{}, without a bound `{param}: {bound}`",
+                unsupported()
+            )));
+        }
+        let method = match op {
+            ast::BinOp::Eq => "eqb",
+            ast::BinOp::Lt => "ltb",
+            ast::BinOp::Le => "leb",
+            _ => "add",
+        };
+        Ok(ast::Class::method(class, method))
+    }
+
+    fn elaborate(
+        &mut self,
+        expr: &ast::Expr,
+        expected: Option<&Ty>,
+    ) -> Result<(ast::Expr, Option<Ty>), TypeError> {
+        let (expr, ty) = self.synthesize(expr, expected)?;
+        match (expected, ty) {
+            (Some(expected), Some(ty)) if *expected != ty => Err(TypeError::new(format!(
+                "mismatched types: expected `{expected}`, found `{ty}`"
+            ))),
+            (_, ty) => {
+                let ty = ty.or_else(|| expected.cloned());
+                if let Some(ty) = &ty {
+                    self.record(ty);
+                }
+                Ok((expr, ty))
+            }
+        }
+    }
+
+    fn synthesize(
+        &mut self,
+        expr: &ast::Expr,
+        expected: Option<&Ty>,
+    ) -> Result<(ast::Expr, Option<Ty>), TypeError> {
+        match expr {
+            // A variable whose type isn't known yet takes on the first type expected of it.
+            ast::Expr::Var(name) => {
+                let ty = match self
+                    .locals
+                    .iter_mut()
+                    .rev()
+                    .find(|(local, _)| local == name)
+                {
+                    Some((_, ty @ None)) => {
+                        *ty = expected.cloned();
+                        ty.clone()
+                    }
+                    Some((_, ty)) => ty.clone(),
+                    None if *name == ast::Class::method("Default", "default") => {
+                        return self.default_value(expected);
+                    }
+                    None => None,
+                };
+                Ok((expr.clone(), ty))
+            }
+
+            // Integer literals take on the type expected of them.
+            ast::Expr::Nat(n) => match expected {
+                Some(Ty::Z) => Ok((ast::Expr::Z((*n).into()), Some(Ty::Z))),
+                Some(Ty::Nat) => Ok((expr.clone(), Some(Ty::Nat))),
+                Some(Ty::N) => Ok((ast::Expr::N(*n), Some(Ty::N))),
+                Some(ty) => Err(TypeError::new(format!(
+                    "mismatched types: expected `{ty}`, found an integer"
+                ))),
+                None => Ok((expr.clone(), None)),
+            },
+            ast::Expr::N(_) => Ok((expr.clone(), Some(Ty::N))),
+            ast::Expr::Z(_) => Ok((expr.clone(), Some(Ty::Z))),
+            ast::Expr::Bool(_) => Ok((expr.clone(), Some(Ty::Bool))),
+            ast::Expr::Tt => Ok((expr.clone(), None)),
+
+            // A tuple is a product if the types of all its elements are known.
+            ast::Expr::Tuple(elems) => {
+                let components = expected.and_then(|ty| components(ty, elems.len()));
+                let mut elaborated = vec![];
+                let mut tys = vec![];
+                for (i, elem) in elems.iter().enumerate() {
+                    let ty = components.as_ref().map(|components| &components[i]);
+                    let (elem, ty) = self.elaborate(elem, ty)?;
+                    elaborated.push(elem);
+                    tys.push(ty);
+                }
+                let ty = tys
+                    .into_iter()
+                    .collect::<Option<Vec<_>>>()
+                    .and_then(|tys| tys.into_iter().reduce(Ty::prod));
+                Ok((ast::Expr::Tuple(elaborated), ty))
+            }
+
+            // The elements of a list all have the type of the first one whose type is known.
+            ast::Expr::List(elems) => {
+                let elem_ty = match expected.and_then(list_elem) {
+                    Some(ty) => Some(ty.clone()),
+                    None => {
+                        let mut found = None;
+                        for elem in elems {
+                            found = found.or(self.infer(elem)?.1);
+                        }
+                        found
+                    }
+                };
+                let elems = elems
+                    .iter()
+                    .map(|elem| Ok(self.elaborate(elem, elem_ty.as_ref())?.0))
+                    .collect::<Result<_, _>>()?;
+                Ok((ast::Expr::List(elems), elem_ty.map(Ty::list)))
+            }
+
+            ast::Expr::ListOp { op, args, loc } => self
+                .list_op(*op, args, expected)
+                .map_err(|err| err.at(*loc)),
+
+            ast::Expr::Lambda { params, .. } => {
+                let params = params.iter().map(|(_, ty)| ty.clone()).collect::<Vec<_>>();
+                let (lambda, _) = self.lambda(expr, &params, None)?;
+                Ok((lambda, None))
+            }
+
+            ast::Expr::ForRange {
+                func,
+                var,
+                start,
+                end,
+                state,
+                body,
+                loc,
+            } => self
+                .for_range(func, var, start, end, state, body)
+                .map_err(|err| err.at(*loc)),
+
+            ast::Expr::While {
+                func,
+                cond,
+                measure,
+                state,
+                body,
+                loc,
+            } => self
+                .while_loop(func, cond, measure, state, body)
+                .map_err(|err| err.at(*loc)),
+
+            ast::Expr::Record { name, fields } => {
+                let fields = fields
+                    .iter()
+                    .map(|(field, value)| {
+                        let ty = self.field_ty(field);
+                        Ok((field.clone(), self.elaborate(value, ty.as_ref())?.0))
+                    })
+                    .collect::<Result<_, _>>()?;
+                let expr = ast::Expr::Record {
+                    name: name.clone(),
+                    fields,
+                };
+                Ok((expr, Some(Ty::Named(name.clone()))))
+            }
+
+            // The constructors of `option` and `sum` have the type expected of them, if it's known.
+            // Otherwise, `Some` has the type of its argument.
+            ast::Expr::Apply { func, args }
+                if args.len() == 1 && matches!(func.as_str(), "Some" | "inl" | "inr") =>
+            {
+                let arg_ty = constructor_arg(func, expected);
+                let (arg, found) = self.elaborate(&args[0], arg_ty)?;
+                let ty = match (func.as_str(), arg_ty, found) {
+                    (_, Some(_), _) => expected.cloned(),
+                    ("Some", None, Some(found)) => Some(Ty::option(found)),
+                    _ => None,
+                };
+                let expr = ast::Expr::Apply {
+                    func: func.clone(),
+                    args: vec![arg],
+                };
+                Ok((expr, ty))
+            }
+
+            ast::Expr::Apply { func, args } => {
+                let signature = self
+                    .functions
+                    .iter()
+                    .find(|(called, _)| called == func)
+                    .map(|(_, signature)| signature.clone());
+                match signature {
+                    Some(signature) => self.call(&signature, args, expected),
+                    None => {
+                        let args = args
+                            .iter()
+                            .map(|arg| Ok(self.infer(arg)?.0))
+                            .collect::<Result<_, _>>()?;
+                        let expr = ast::Expr::Apply {
+                            func: func.clone(),
+                            args,
+                        };
+                        Ok((expr, None))
+                    }
+                }
+            }
+
+            ast::Expr::LetIn {
+                ident,
+                ty,
+                value,
+                child,
+            } => {
+                let (mut value_expr, value_ty) = self.elaborate(value, ty.as_ref())?;
+                self.bind(ident.clone(), value_ty.clone());
+                let child = self.elaborate(child, expected);
+                let (_, resolved) = self.locals.pop().unwrap();
+                let (child, child_ty) = child?;
+
+                // If the variable's type was only determined by how it's used, the literals in
+                // its value need to have that type too.
+                if let (None, Some(resolved)) = (value_ty, resolved) {
+                    value_expr = self.check(value, &resolved)?;
+                }
+
+                let expr = ast::Expr::LetIn {
+                    ident: ident.clone(),
+                    ty: ty.clone(),
+                    value: Box::new(value_expr),
+                    child: Box::new(child),
+                };
+                Ok((expr, child_ty))
+            }
+
+            ast::Expr::LetPattern {
+                pattern,
+                ty,
+                value,
+                child,
+            } => {
+                let (mut value_expr, value_ty) = self.elaborate(value, ty.as_ref())?;
+                let scope = self.locals.len();
+                let pattern = self.pattern(pattern, value_ty.as_ref());
+                let child =
+                    pattern.and_then(|pattern| Ok((pattern, self.elaborate(child, expected)?)));
+                let bound = self.locals.split_off(scope);
+                let (pattern, (child, child_ty)) = child?;
+
+                // As for `LetIn`, the literals in the value need the types its variables were
+                // given by how they're used.
+                if value_ty.is_none() {
+                    value_expr = self.destructured(value, &pattern, &bound)?;
+                }
+
+                let expr = ast::Expr::LetPattern {
+                    pattern: Box::new(pattern),
+                    ty: ty.clone(),
+                    value: Box::new(value_expr),
+                    child: Box::new(child),
+                };
+                Ok((expr, child_ty))
+            }
+
+            ast::Expr::LetState {
+                pattern,
+                state,
+                value,
+                child,
+            } => {
+                let tys = state
+                    .iter()
+                    .map(|var| {
+                        let local = self.locals.iter().rev().find(|(local, _)| local == var);
+                        local.and_then(|(_, ty)| ty.clone())
+                    })
+                    .collect::<Vec<_>>();
+                let (value, _) = self.infer(value)?;
+                let scope = self.locals.len();
+                let result = self.pattern(pattern, None);
+                for (var, ty) in state.iter().zip(tys) {
+                    self.bind(var.clone(), ty);
+                }
+                let child =
+                    result.and_then(|result| Ok((result, self.elaborate(child, expected)?)));
+                self.locals.truncate(scope);
+                let (result, (child, child_ty)) = child?;
+
+                let mut patterns = vec![result];
+                patterns.extend(state.iter().cloned().map(ast::Pattern::Ident));
+                let expr = ast::Expr::LetPattern {
+                    pattern: Box::new(ast::Pattern::Tuple(patterns)),
+                    ty: None,
+                    value: Box::new(value),
+                    child: Box::new(child),
+                };
+                Ok((expr, child_ty))
+            }
+
+            ast::Expr::Match { scrutinee, cases } => {
+                let (scrutinee, scrutinee_ty) = self.infer(scrutinee)?;
+                let (mut elaborated, mut ty) =
+                    self.cases(cases, scrutinee_ty.as_ref(), expected)?;
+
+                // Once one arm's type is known, literals in the other arms must have it too.
+                if expected.is_none() {
+                    if let Some(first) = ty.iter().flatten().next().cloned() {
+                        (elaborated, ty) =
+                            self.cases(cases, scrutinee_ty.as_ref(), Some(&first))?;
+                    }
+                }
+
+                let expr = ast::Expr::Match {
+                    scrutinee: Box::new(scrutinee),
+                    cases: elaborated,
+                };
+                Ok((expr, ty.into_iter().flatten().next()))
+            }
+
+            ast::Expr::Try {
+                ident,
+                value,
+                child,
+                loc,
+            } => self
+                .bind_try(ident, value, child, expected)
+                .map_err(|err| err.at(*loc)),
+            ast::Expr::Bind {
+                ident,
+                value,
+                child,
+                ..
+            } => self.bind_try(ident, value, child, expected),
+
+            // The frontend has already moved everything after a `return` into the other branches.
+            ast::Expr::Return { loc, .. } => {
+                Err(TypeError::new("can't return early from the middle of an expression").at(*loc))
+            }
+
+            // A panic can have any type, since it's never reached.
+            ast::Expr::Panic => {
+                self.panics = true;
+                Ok((expr.clone(), expected.cloned()))
+            }
+
+            ast::Expr::Field { base, field, loc } => {
+                let (base, ty) = self.infer(base)?;
+                match ty {
+                    Some(Ty::Named(record)) => {
+                        let projection = ast::Record::projection(&record, field);
+                        let ty = self.field_ty(&projection);
+                        let expr = ast::Expr::Apply {
+                            func: projection,
+                            args: vec![base],
+                        };
+                        Ok((expr, ty))
+                    }
+                    Some(ty) => {
+                        Err(TypeError::new(format!("`{ty}` has no field `{field}`")).at(*loc))
+                    }
+                    None if self.partial => {
+                        let expr = ast::Expr::Apply {
+                            func: field.clone(),
+                            args: vec![base],
+                        };
+                        Ok((expr, None))
+                    }
+                    None => Err(TypeError::new(format!(
+                        "can't infer the type of the value whose field `{field}` is accessed; \
+                         annotate it with its type (e.g. `let x: T = ...;`)"
+                    ))
+                    .at(*loc)),
+                }
+            }
+
+            ast::Expr::SetField {
+                base,
+                field,
+                value,
+                loc,
+            } => {
+                let (base, ty) = self.infer(base)?;
+                if ty.is_none() && self.partial {
+                    let (value, _) = self.infer(value)?;
+                    let expr = ast::Expr::Apply {
+                        func: field.clone(),
+                        args: vec![base, value],
+                    };
+                    return Ok((expr, None));
+                }
+                let Some(Ty::Named(record)) = &ty else {
+                    return Err(TypeError::new(match ty {
+                        Some(ty) => format!("`{ty}` has no field `{field}`"),
+                        None => format!(
+                            "can't infer the type of the value whose field `{field}` is \
+                             assigned to; annotate it with its type (e.g. `let x: T = ...;`)"
+                        ),
+                    })
+                    .at(*loc));
+                };
+                let field_ty = self.field_ty(&ast::Record::projection(record, field));
+                let (value, _) = self.elaborate(value, field_ty.as_ref())?;
+                let expr = ast::Expr::Apply {
+                    func: ast::Record::setter(record, field),
+                    args: vec![base, value],
+                };
+                Ok((expr, ty))
+            }
+
+            ast::Expr::BinOp { op, lhs, rhs, loc } => self
+                .binary(*op, lhs, rhs, expected)
+                .map_err(|err| err.at(*loc)),
+
+            // Other types' methods of the same names needn't implement the operator.
+            ast::Expr::TraitOp {
+                op,
+                receiver,
+                arg,
+                loc,
+            } => match self.infer(receiver).map_err(|err| err.at(*loc))?.1 {
+                Some(Ty::Var(_)) => self
+                    .binary(*op, receiver, arg, expected)
+                    .map_err(|err| err.at(*loc)),
+                _ => Err(TypeError::new(format!(
+                    "the method for `{op}` is only supported on values of a type parameter"
+                ))
+                .at(*loc)),
+            },
+
+            ast::Expr::UnOp { op, operand, loc } => {
+                self.unary(*op, operand).map_err(|err| err.at(*loc))
+            }
+        }
+    }
+
+    /// Elaborate a call to a function with a known signature, applying it by its Coq name.
+    ///
+    /// A generic function's type parameters are given the types of the arguments passed for
+    /// them. If the function models unsigned integers differently, they're converted to its model
+    /// and back: an argument whose type is known is converted to the type the function expects,
+    /// and the result to the type expected of the call.
+    fn call(
+        &mut self,
+        signature: &Signature,
+        args: &[ast::Expr],
+        expected: Option<&Ty>,
+    ) -> Result<(ast::Expr, Option<Ty>), TypeError> {
+        // The type parameters are given the types expected of the call, and of the arguments.
+        let converted = signature.numbers != self.numbers;
+        let mut instances = vec![];
+        let unbound = |instances: &[(ast::Ident, Ty)]| {
+            let params = signature.params.iter();
+            params
+                .filter(|param| !instances.iter().any(|(bound, _)| bound == *param))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        if let Some(expected) = expected.filter(|_| !converted) {
+            unify(&signature.ret, expected, &signature.params, &mut instances);
+        }
+        let mut elaborated = vec![];
+        let mut pending = vec![];
+        for (i, (arg, ty)) in args.iter().zip(&signature.args).enumerate() {
+            let arg = if mentions_any(ty, &unbound(&instances)) {
+                let (arg, found) = self.infer(arg)?;
+                match found {
+                    Some(found) => unify(ty, &found, &signature.params, &mut instances),
+                    None => pending.push(i),
+                }
+                arg
+            } else {
+                let ty = instantiate(ty, &instances);
+                if converted {
+                    match self.infer(arg)? {
+                        (arg, Some(found)) => self.convert(arg, &found, &ty)?,
+                        (_, None) => self.check(arg, &ty)?,
+                    }
+                } else {
+                    self.check(arg, &ty)?
+                }
+            };
+            elaborated.push(arg);
+        }
+
+        // Arguments whose types weren't known, like literals, have the types the others gave
+        // their parameters.
+        for i in pending {
+            if !mentions_any(&signature.args[i], &unbound(&instances)) {
+                let ty = instantiate(&signature.args[i], &instances);
+                elaborated[i] = self.check(&args[i], &ty)?;
+            }
+        }
+
+        let call = ast::Expr::Apply {
+            func: signature.name.clone(),
+            args: elaborated,
+        };
+        if mentions_any(&signature.ret, &unbound(&instances)) {
+            return Ok((call, None));
+        }
+        let ret = instantiate(&signature.ret, &instances);
+        match expected {
+            Some(expected) if converted => {
+                Ok((self.convert(call, &ret, expected)?, Some(expected.clone())))
+            }
+            _ => Ok((call, Some(ret))),
+        }
+    }
+
+    /// Convert `expr` from one integer type to another, for a function whose unsigned integers
+    /// are modelled differently. Rust has already checked it's an unsigned integer.
+    fn convert(&mut self, expr: ast::Expr, from: &Ty, to: &Ty) -> Result<ast::Expr, TypeError> {
+        let func = match (from, to) {
+            _ if from == to => return Ok(expr),
+            (Ty::Nat, Ty::N) => "N.of_nat",
+            (Ty::N, Ty::Nat) => "N.to_nat",
+            (Ty::Nat, Ty::Z) => "Z.of_nat",
+            (Ty::Z, Ty::Nat) => "Z.to_nat",
+            (Ty::N, Ty::Z) => "Z.of_N",
+            (Ty::Z, Ty::N) => "Z.to_N",
+            _ => {
+                return Err(TypeError::new(format!(
+                    "mismatched types: expected `{to}`, found `{from}`"
+                )))
+            }
+        };
+        self.record(from);
+        self.record(to);
+        Ok(ast::Expr::Apply {
+            func: func.into(),
+            args: vec![expr],
+        })
+    }
+
+    /// Elaborate `Default::default()`, the method of the class `Default`, whose type comes from
+    /// its context. If that's a type parameter, it must be bounded by the class.
+    fn default_value(
+        &mut self,
+        expected: Option<&Ty>,
+    ) -> Result<(ast::Expr, Option<Ty>), TypeError> {
+        match expected {
+            Some(Ty::Var(param)) if !self.has_bound(param, "Default") => {
+                Err(TypeError::new(format!(
+                    "`Default::default()` isn't supported on `{param}`, without a bound \
+                     `{param}: Default`"
+                )))
+            }
+            _ => {
+                self.use_class("Default");
+                let expr = ast::Expr::Var(ast::Class::method("Default", "default"));
+                Ok((expr, expected.cloned()))
+            }
+        }
+    }
+
+    /// Elaborate `let ident = value?;` followed by `child`, as a `Bind` in the monad of the
+    /// type of `value`. Since returning early returns the `None` or error in `value`, `child`
+    /// must have a type in the same monad.
+    fn bind_try(
+        &mut self,
+        ident: &ast::Ident,
+        value: &ast::Expr,
+        child: &ast::Expr,
+        expected: Option<&Ty>,
+    ) -> Result<(ast::Expr, Option<Ty>), TypeError> {
+        let (value, value_ty) = self.infer(value)?;
+
+        // If the value's type isn't known, such as when it's returned by another function, it must
+        // be in the same monad as the expression its `None` or error is returned from.
+        let monad =
+            match (&value_ty, expected.and_then(monad)) {
+                (Some(ty), _) => monad(ty).ok_or_else(|| {
+                    TypeError::new(format!("the `?` operator can't be applied to `{ty}`"))
+                })?,
+                (None, Some(monad)) => monad,
+                (None, None) => return Err(TypeError::new(
+                    "can't infer the type of the value `?` is applied to; annotate it with its \
+                     type (e.g. `let x: T = ...;`)",
+                )),
+            };
+        let bound = match &value_ty {
+            Some(Ty::Apply { args, .. }) => Some(args[0].clone()),
+            _ => None,
+        };
+        let check_exit = |ty: &Ty| {
+            let exits = match (ty, &value_ty) {
+                (
+                    Ty::Apply { args, .. },
+                    Some(Ty::Apply {
+                        args: value_args, ..
+                    }),
+                ) if monad == ast::Monad::Sum => {
+                    self::monad(ty) == Some(monad) && args[1] == value_args[1]
+                }
+                _ => self::monad(ty) == Some(monad),
+            };
+            match (exits, &value_ty) {
+                (true, _) => Ok(()),
+                (false, Some(value_ty)) => Err(TypeError::new(format!(
+                    "the `?` operator on `{value_ty}` can't return early from a function \
+                     returning `{ty}`"
+                ))),
+                (false, None) => Err(TypeError::new(format!(
+                    "the `?` operator can't return early from a function returning `{ty}`"
+                ))),
+            }
+        };
+        if let Some(expected) = expected {
+            check_exit(expected)?;
+        }
+
+        self.bind(ident.clone(), bound);
+        let child = self.elaborate(child, expected);
+        self.locals.pop();
+        let (child, child_ty) = child?;
+        if let Some(child_ty) = &child_ty {
+            check_exit(child_ty)?;
+        }
+
+        // The notation is defined before anything else which might use it.
+        let notation = ast::Statement::BindNotation(monad);
+        if !self.auxiliary.contains(&notation) {
+            self.auxiliary.insert(0, notation);
+        }
+        let expr = ast::Expr::Bind {
+            monad,
+            ident: ident.clone(),
+            value: Box::new(value),
+            child: Box::new(child),
+        };
+        Ok((expr, child_ty))
+    }
+
+    /// Elaborate a value destructured by `pattern`, given the types of the variables it bound.
+    fn destructured(
+        &mut self,
+        value: &ast::Expr,
+        pattern: &ast::Pattern,
+        bound: &[(ast::Ident, Option<Ty>)],
+    ) -> Result<ast::Expr, TypeError> {
+        match (value, pattern) {
+            (ast::Expr::Tuple(elems), ast::Pattern::Tuple(patterns))
+                if elems.len() == patterns.len() =>
+            {
+                let elems = elems
+                    .iter()
+                    .zip(patterns)
+                    .map(|(elem, pattern)| self.destructured(elem, pattern, bound))
+                    .collect::<Result<_, _>>()?;
+                Ok(ast::Expr::Tuple(elems))
+            }
+            (_, ast::Pattern::Ident(name)) => {
+                match bound.iter().rev().find(|(local, _)| local == name) {
+                    Some((_, Some(ty))) => self.check(value, ty),
+                    _ => Ok(self.infer(value)?.0),
+                }
+            }
+            _ => Ok(self.infer(value)?.0),
+        }
+    }
+
+    /// Elaborate a function applied to arguments of the given types, returning its result type.
+    ///
+    /// A `Lambda`'s parameters are given the types of the arguments, unless they're annotated,
+    /// and are annotated with them in turn. Any other function is left as it is.
+    fn lambda(
+        &mut self,
+        func: &ast::Expr,
+        args: &[Option<Ty>],
+        expected: Option<&Ty>,
+    ) -> Result<(ast::Expr, Option<Ty>), TypeError> {
+        let ast::Expr::Lambda { params, body } = func else {
+            let (func, _) = self.infer(func)?;
+            return Ok((func, None));
+        };
+        if params.len() != args.len() {
+            return Err(TypeError::new(format!(
+                "expected a closure taking {} arguments, found one taking {}",
+                args.len(),
+                params.len()
+            )));
+        }
+
+        let scope = self.locals.len();
+        for ((param, ty), arg) in params.iter().zip(args) {
+            self.bind(param.clone(), ty.clone().or_else(|| arg.clone()));
+        }
+        let body = self.elaborate(body, expected);
+        let params = self.locals.split_off(scope);
+        let (body, ty) = body?;
+
+        let expr = ast::Expr::Lambda {
+            params,
+            body: Box::new(body),
+        };
+        Ok((expr, ty))
+    }
+
+    /// Replace a `for` loop over a range with a call to an auxiliary `Fixpoint` on the number of
+    /// iterations left:
+    ///
+    /// ```coq
+    /// Fixpoint f_loop'1 (fuel': nat) (i: nat) (x: nat) (acc: nat) {struct fuel'} : nat :=
+    ///   match fuel' with
+    ///   | O => acc
+    ///   | S fuel' => let acc := body in f_loop'1 fuel' (plus i 1) x acc
+    ///   end.
+    /// ```
+    ///
+    /// Since the `Fixpoint` is defined before the function containing the loop, the variables
+    /// the body captures are passed to it too. Each iteration is given by the lemma
+    /// `f_loop'1_equation`, unless the body may panic: then the `Fixpoint` is defined with
+    /// `Program`, and doesn't exist until its obligations are proved.
+    fn for_range(
+        &mut self,
+        func: &ast::Ident,
+        var: &ast::Ident,
+        start: &ast::Expr,
+        end: &ast::Expr,
+        state: &[ast::Ident],
+        body: &ast::Expr,
+    ) -> Result<(ast::Expr, Option<Ty>), TypeError> {
+        let func = &format!("{}{func}", self.prefix);
+
+        // The loop variable has the type of the bounds, which are unsigned integers if neither is
+        // known.
+        let (mut start_expr, start_ty) = self.infer(start)?;
+        let (mut end_expr, end_ty) = self.elaborate(end, start_ty.as_ref())?;
+        let ty = match (start_ty, end_ty) {
+            (Some(ty), _) => ty,
+            (None, Some(ty)) => {
+                start_expr = self.check(start, &ty)?;
+                ty
+            }
+            (None, None) => {
+                let ty = self.numbers.ty();
+                start_expr = self.check(start, &ty)?;
+                end_expr = self.check(end, &ty)?;
+                ty
+            }
+        };
+        let count = match ty {
+            Ty::Nat => ast::Expr::Apply {
+                func: "minus".into(),
+                args: vec![end_expr, start_expr.clone()],
+            },
+            Ty::N | Ty::Z => ast::Expr::Apply {
+                func: format!("{ty}.to_nat"),
+                args: vec![ast::Expr::Apply {
+                    func: format!("{ty}.sub"),
+                    args: vec![end_expr, start_expr.clone()],
+                }],
+            },
+            ty => return Err(TypeError::new(format!("can't loop over a range of `{ty}`"))),
+        };
+        let one = literal(&ty, 1);
+
+        // Panics in the body are obligations of the loop's `Fixpoint`, not the enclosing function.
+        let outer_panics = std::mem::take(&mut self.panics);
+        let fuel = ast::Ident::from("fuel'");
+        let mut args = vec![
+            ast::Binder {
+                name: fuel.clone(),
+                ty: Ty::Nat,
+                implicit: false,
+            },
+            ast::Binder {
+                name: var.clone(),
+                ty: ty.clone(),
+                implicit: false,
+            },
+        ];
+        args.extend(self.loop_args(&args[1..], state, &[body])?);
+        let state_ty = state_type(&args, state);
+
+        let scope = self.locals.len();
+        let outer_frame = std::mem::replace(&mut self.frame, scope);
+        for arg in &args {
+            self.bind(arg.name.clone(), Some(arg.ty.clone()));
+        }
+        let body = self.check(body, &state_ty);
+        self.locals.truncate(scope);
+        self.frame = outer_frame;
+        let body = body?;
+        let next = self.apply_operator(ast::BinOp::Add, &ty, ast::Expr::Var(var.clone()), one)?;
+
+        // Each iteration carries on with the next value of the loop variable, and the values the
+        // body leaves the state with.
+        let call = |fuel, i| {
+            let mut call_args = vec![fuel, i];
+            call_args.extend(args[2..].iter().map(|arg| ast::Expr::Var(arg.name.clone())));
+            ast::Expr::Apply {
+                func: func.clone(),
+                args: call_args,
+            }
+        };
+        let recurse = call(ast::Expr::Var(fuel.clone()), next);
+        let fixpoint_body = ast::Expr::Match {
+            scrutinee: Box::new(ast::Expr::Var(fuel.clone())),
+            cases: vec![
+                ast::MatchCase {
+                    pattern: ast::Pattern::Constructor {
+                        name: "O".into(),
+                        args: vec![],
+                    },
+                    body: state_value(state),
+                },
+                ast::MatchCase {
+                    pattern: ast::Pattern::Constructor {
+                        name: "S".into(),
+                        args: vec![ast::Pattern::Ident(fuel.clone())],
+                    },
+                    body: replace_tail(body, recurse),
+                },
+            ],
+        };
+
+        let lemma = ast::Lemma {
+            name: format!("{func}_equation"),
+            args: self.generic(args.clone()),
+            lhs: call(ast::Expr::Var(fuel.clone()), ast::Expr::Var(var.clone())),
+            rhs: fixpoint_body.clone(),
+            proof: format!("intros. destruct {fuel}; reflexivity."),
+        };
+        let expr = call(count, start_expr);
+        let program = std::mem::replace(&mut self.panics, outer_panics);
+        self.auxiliary.push(ast::Statement::Fixpoint(ast::Fixpoint {
+            name: func.clone(),
+            args: self.generic(args),
+            ret: state_ty.clone(),
+            body: fixpoint_body,
+            decreasing: fuel,
+            program,
+        }));
+        if !program {
+            self.auxiliary.push(ast::Statement::Lemma(lemma));
+        }
+
+        Ok((expr, Some(state_ty)))
+    }
+
+    /// Replace a `while` loop with a call to an auxiliary `Program Fixpoint`, which checks the
+    /// condition and calls itself again with the state left by the body:
+    ///
+    /// ```coq
+    /// Program Fixpoint f_loop'1 (n: nat) (i: nat) {measure (minus n i)} : nat :=
+    ///   match (Nat.ltb i n) with
+    ///   | true => let i := body in f_loop'1 n i
+    ///   | false => i
+    ///   end.
+    /// ```
+    ///
+    /// Coq generates an obligation that the measure decreases on each iteration, and one for each
+    /// panic in the loop, which are left to be proved with `Next Obligation`.
+    fn while_loop(
+        &mut self,
+        func: &ast::Ident,
+        cond: &ast::Expr,
+        measure: &ast::Expr,
+        state: &[ast::Ident],
+        body: &ast::Expr,
+    ) -> Result<(ast::Expr, Option<Ty>), TypeError> {
+        let func = &format!("{}{func}", self.prefix);
+        let outer_panics = std::mem::take(&mut self.panics);
+        let args = self.loop_args(&[], state, &[cond, measure, body])?;
+        let state_ty = state_type(&args, state);
+
+        let scope = self.locals.len();
+        let outer_frame = std::mem::replace(&mut self.frame, scope);
+        for arg in &args {
+            self.bind(arg.name.clone(), Some(arg.ty.clone()));
+        }
+        let elaborated = (|| {
+            let cond = self.check(cond, &Ty::Bool)?;
+            let measure = self.check_measure(measure)?;
+            let body = self.check(body, &state_ty)?;
+            Ok((cond, measure, body))
+        })();
+        self.locals.truncate(scope);
+        self.frame = outer_frame;
+        let (cond, measure, body) = elaborated?;
+
+        let call = ast::Expr::Apply {
+            func: func.clone(),
+            args: args
+                .iter()
+                .map(|arg| ast::Expr::Var(arg.name.clone()))
+                .collect(),
+        };
+        let fixpoint_body =
+            if_then_else(cond, replace_tail(body, call.clone()), state_value(state));
+        self.panics = outer_panics;
+        self.require("Program");
+        self.auxiliary
+            .push(ast::Statement::ProgramFixpoint(ast::ProgramFixpoint {
+                name: func.clone(),
+                args: self.generic(args),
+                ret: state_ty.clone(),
+                body: fixpoint_body,
+                measure,
+            }));
+
+        Ok((call, Some(state_ty)))
+    }
+
+    /// Precede the arguments of the auxiliary function of a loop with implicit arguments binding
+    /// the type parameters their types mention, since the function containing the loop may be
+    /// generic, and the instances of the classes they're bounded by.
+    fn generic(&self, args: Vec<ast::Binder>) -> Vec<ast::Binder> {
+        fn params(ty: &Ty, found: &mut Vec<ast::Ident>) {
+            match ty {
+                Ty::Var(name) if !found.contains(name) => found.push(name.clone()),
+                Ty::Apply { args, .. } | Ty::Arrow(args) => {
+                    for arg in args {
+                        params(arg, found);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut found = vec![];
+        for arg in &args {
+            params(&arg.ty, &mut found);
+        }
+        let mut generic = vec![];
+        for name in found {
+            generic.push(ast::Binder {
+                name: name.clone(),
+                ty: Ty::Type,
+                implicit: true,
+            });
+            for (param, class) in &self.bounds {
+                if *param == name {
+                    generic.push(ast::Binder::instance(class, Ty::Var(name.clone())));
+                }
+            }
+        }
+        generic.extend(args);
+        generic
+    }
+
+    /// The arguments of the auxiliary function a loop is translated to, besides those in `bound`:
+    /// each local variable which `exprs` use, followed by the `state`. Their types must all be
+    /// known by now.
+    fn loop_args(
+        &mut self,
+        bound: &[ast::Binder],
+        state: &[ast::Ident],
+        exprs: &[&ast::Expr],
+    ) -> Result<Vec<ast::Binder>, TypeError> {
+        let mut captured = vec![];
+        for expr in exprs {
+            mentioned(expr, &mut captured);
+        }
+        captured.retain(|name| !state.contains(name) && !bound.iter().any(|arg| arg.name == *name));
+
+        // Variables whose types aren't known yet, such as accumulators initialized with a literal,
+        // are given the types they're used with in the loop.
+        let unknown = captured
+            .iter()
+            .chain(state)
+            .filter(|name| {
+                let local = self.locals.iter().rev().find(|(local, _)| local == *name);
+                matches!(local, Some((_, None)))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        if !unknown.is_empty() {
+            let scope = self.locals.len();
+            let auxiliary = self.auxiliary.clone();
+            for arg in bound {
+                self.bind(arg.name.clone(), Some(arg.ty.clone()));
+            }
+            for name in &unknown {
+                self.bind(name.clone(), None);
+            }
+            let trial = exprs
+                .iter()
+                .try_for_each(|expr| self.infer(expr).map(|_| ()));
+            let resolved = self.locals.split_off(scope + bound.len());
+            self.locals.truncate(scope);
+            self.auxiliary = auxiliary;
+            trial?;
+            for (name, ty) in resolved {
+                if let Some(ty) = ty {
+                    self.check(&ast::Expr::Var(name), &ty)?;
+                }
+            }
+        }
+
+        let mut args = vec![];
+        for name in captured.iter().chain(state) {
+            let local = self.locals.iter().rev().find(|(local, _)| local == name);
+            match local {
+                Some((_, Some(ty))) => args.push(ast::Binder {
+                    name: name.clone(),
+                    ty: ty.clone(),
+                    implicit: false,
+                }),
+                Some((_, None)) => {
+                    return Err(TypeError::new(format!(
+                        "can't infer the type of `{name}`, which is used in a loop; annotate it \
+                         with its type (e.g. `let {name}: T = ...;`)"
+                    )))
+                }
+                // Anything else is a function or constructor, which is already defined.
+                None => {}
+            }
+        }
+        Ok(args)
+    }
+
+    /// Elaborate the arms of a `match`, returning the type of each arm's body.
+    fn cases(
+        &mut self,
+        cases: &[ast::MatchCase],
+        scrutinee_ty: Option<&Ty>,
+        expected: Option<&Ty>,
+    ) -> Result<(Vec<ast::MatchCase>, Vec<Option<Ty>>), TypeError> {
+        let mut elaborated = vec![];
+        let mut tys = vec![];
+        for case in cases {
+            let scope = self.locals.len();
+            let pattern = self.pattern(&case.pattern, scrutinee_ty);
+            let body =
+                pattern.and_then(|pattern| Ok((pattern, self.elaborate(&case.body, expected)?)));
+            self.locals.truncate(scope);
+
+            let (pattern, (body, ty)) = body?;
+            elaborated.push(ast::MatchCase { pattern, body });
+            tys.push(ty);
+        }
+        Ok((elaborated, tys))
+    }
+
+    /// Elaborate a pattern matching a value of type `ty`, binding the variables it introduces.
+    fn pattern(
+        &mut self,
+        pattern: &ast::Pattern,
+        ty: Option<&Ty>,
+    ) -> Result<ast::Pattern, TypeError> {
+        match pattern {
+            ast::Pattern::Expr(expr) => Ok(ast::Pattern::Expr(self.elaborate(expr, ty)?.0)),
+            ast::Pattern::Wildcard => Ok(ast::Pattern::Wildcard),
+            ast::Pattern::Ident(ident) => {
+                self.bind(ident.clone(), ty.cloned());
+                Ok(pattern.clone())
+            }
+            ast::Pattern::As { pattern, ident } => {
+                let pattern = self.pattern(pattern, ty)?;
+                self.bind(ident.clone(), ty.cloned());
+                Ok(ast::Pattern::As {
+                    pattern: Box::new(pattern),
+                    ident: ident.clone(),
+                })
+            }
+            ast::Pattern::Constructor { name, args } => {
+                // The only constructors whose fields we know the types of are those in Coq's
+                // prelude.
+                let arg_ty = constructor_arg(name, ty);
+                let args = args
+                    .iter()
+                    .map(|arg| self.pattern(arg, arg_ty))
+                    .collect::<Result<_, _>>()?;
+                Ok(ast::Pattern::Constructor {
+                    name: name.clone(),
+                    args,
+                })
+            }
+            ast::Pattern::Tuple(elems) => {
+                let components = ty.and_then(|ty| components(ty, elems.len()));
+                Ok(ast::Pattern::Tuple(
+                    elems
+                        .iter()
+                        .enumerate()
+                        .map(|(i, elem)| {
+                            let ty = components.as_ref().map(|components| &components[i]);
+                            self.pattern(elem, ty)
+                        })
+                        .collect::<Result<_, _>>()?,
+                ))
+            }
+            ast::Pattern::Record { fields } => Ok(ast::Pattern::Record {
+                fields: fields
+                    .iter()
+                    .map(|(field, pattern)| {
+                        let ty = self.field_ty(field);
+                        Ok((field.clone(), self.pattern(pattern, ty.as_ref())?))
+                    })
+                    .collect::<Result<_, _>>()?,
+            }),
+            ast::Pattern::Or(alternatives) => Ok(ast::Pattern::Or(
+                alternatives
+                    .iter()
+                    .map(|alternative| self.pattern(alternative, ty))
+                    .collect::<Result<_, _>>()?,
+            )),
+        }
+    }
+
+    fn binary(
+        &mut self,
+        op: ast::BinOp,
+        lhs: &ast::Expr,
+        rhs: &ast::Expr,
+        expected: Option<&Ty>,
+    ) -> Result<(ast::Expr, Option<Ty>), TypeError> {
+        let apply = |func: &str, lhs, rhs| ast::Expr::Apply {
+            func: func.into(),
+            args: vec![lhs, rhs],
+        };
+
+        // Boolean operators only have one meaning.
+        if let ast::BinOp::And | ast::BinOp::Or = op {
+            let func = if op == ast::BinOp::And { "andb" } else { "orb" };
+            let lhs = self.check(lhs, &Ty::Bool)?;
+            let rhs = self.check(rhs, &Ty::Bool)?;
+            return Ok((apply(func, lhs, rhs), Some(Ty::Bool)));
+        }
+
+        // Arithmetic produces the type of its operands, so the expected type carries through.
+        let comparison = matches!(
+            op,
+            ast::BinOp::Eq
+                | ast::BinOp::Ne
+                | ast::BinOp::Lt
+                | ast::BinOp::Le
+                | ast::BinOp::Gt
+                | ast::BinOp::Ge
+        );
+        let expected = if comparison { None } else { expected };
+
+        // Infer the type of the operands from whichever of them has a known type, falling back
+        // to unsigned integers if neither does (e.g. they're both literals). In that case,
+        // arithmetic is left without a type, so that it can still be given one by the context
+        // it's used in.
+        let (mut lhs_expr, lhs_ty) = self.elaborate(lhs, expected)?;
+        let (rhs_expr, rhs_ty) = self.elaborate(rhs, lhs_ty.as_ref())?;
+        let ty = match (lhs_ty, rhs_ty) {
+            (Some(ty), _) => ty,
+            (None, Some(ty)) => {
+                lhs_expr = self.check(lhs, &ty)?;
+                ty
+            }
+            (None, None) => {
+                return self.binary_default(op, lhs, rhs, comparison);
+            }
+        };
+
+        let expr = self.arithmetic(op, &ty, lhs_expr, rhs_expr)?;
+        let result = if comparison { Ty::Bool } else { ty };
+        Ok((expr, Some(result)))
+    }
+
+    /// Elaborate a binary operator whose operands have no known type, as unsigned integers.
+    fn binary_default(
+        &mut self,
+        op: ast::BinOp,
+        lhs: &ast::Expr,
+        rhs: &ast::Expr,
+        comparison: bool,
+    ) -> Result<(ast::Expr, Option<Ty>), TypeError> {
+        let ty = &self.numbers.ty();
+        let lhs = self.check(lhs, ty)?;
+        let rhs = self.check(rhs, ty)?;
+        let expr = self.arithmetic(op, ty, lhs, rhs)?;
+        Ok((expr, comparison.then_some(Ty::Bool)))
+    }
+
+    /// Apply the Coq function implementing a binary operator, like `apply_operator`. If the
+    /// arithmetic is `checked`, an operation on unsigned integers which Rust would panic on, or a
+    /// division of signed integers, instead panics unless a condition holds:
+    ///
+    /// ```coq
+    /// match (Nat.leb b a) with
+    /// | true => minus a b
+    /// | false => False_rect _ _
+    /// end
+    /// ```
+    ///
+    /// Operands other than variables and literals are bound with `let` first, so that checking
+    /// them doesn't evaluate them twice.
+    fn arithmetic(
+        &mut self,
+        op: ast::BinOp,
+        ty: &Ty,
+        lhs: ast::Expr,
+        rhs: ast::Expr,
+    ) -> Result<ast::Expr, TypeError> {
+        // Words wrap around instead of overflowing, but division by zero still panics, like
+        // the division of signed integers, which are unbounded otherwise.
+        let word = self.numbers == Numbers::Word && *ty == Ty::N;
+        let signed = *ty == Ty::Z;
+        let checked = match op {
+            ast::BinOp::Add
+            | ast::BinOp::Sub
+            | ast::BinOp::Mul
+            | ast::BinOp::Shl
+            | ast::BinOp::Shr => self.checked && !signed,
+            ast::BinOp::Div | ast::BinOp::Rem => {
+                (self.checked || word || signed && self.numbers == Numbers::Word)
+                    && !matches!(rhs, ast::Expr::Nat(n) | ast::Expr::N(n) if n != 0)
+                    && !matches!(rhs, ast::Expr::Z(n) if n != 0 && n != -1)
+            }
+            _ => false,
+        };
+        if word && !checked {
+            return self.wrapping(op, lhs, rhs);
+        }
+        if !checked || !matches!(ty, Ty::Nat | Ty::N | Ty::Z) {
+            return self.apply_operator(op, ty, lhs, rhs);
+        }
+
+        let mut bindings = vec![];
+        let mut share = |operand, name: &str| match operand {
+            ast::Expr::Var(_) | ast::Expr::Nat(_) | ast::Expr::N(_) | ast::Expr::Z(_) => operand,
+            operand => {
+                bindings.push((ast::Ident::from(name), operand));
+                ast::Expr::Var(name.into())
+            }
+        };
+        let lhs = share(lhs, "lhs'");
+        let rhs = share(rhs, "rhs'");
+        let value = self.apply_operator(op, ty, lhs.clone(), rhs.clone())?;
+        let expr = match op {
+            ast::BinOp::Sub => {
+                let cond = self.apply_operator(ast::BinOp::Le, ty, rhs, lhs)?;
+                self.panics = true;
+                if_then_else(cond, value, ast::Expr::Panic)
+            }
+            ast::BinOp::Div | ast::BinOp::Rem => {
+                // Only `-1` is left of the literals a signed integer can be divided by.
+                let cond = match rhs {
+                    ast::Expr::Z(_) => self.int_min_divided(lhs, rhs),
+                    _ if signed => ast::Expr::Apply {
+                        func: "andb".into(),
+                        args: vec![
+                            self.apply_operator(ast::BinOp::Ne, ty, rhs.clone(), literal(ty, 0))?,
+                            self.int_min_divided(lhs, rhs),
+                        ],
+                    },
+                    _ => self.apply_operator(ast::BinOp::Ne, ty, rhs, literal(ty, 0))?,
+                };
+                self.panics = true;
+                if_then_else(cond, value, ast::Expr::Panic)
+            }
+
+            // Shifting by 64 or more panics, but bits shifted out to the left are discarded.
+            ast::BinOp::Shl | ast::BinOp::Shr => {
+                let value = match op {
+                    ast::BinOp::Shl => self.wrap(ty, value),
+                    _ => value,
+                };
+                if matches!(rhs, ast::Expr::Nat(n) | ast::Expr::N(n) if n < 64) {
+                    value
+                } else {
+                    let cond = self.apply_operator(ast::BinOp::Lt, ty, rhs, literal(ty, 64))?;
+                    self.panics = true;
+                    if_then_else(cond, value, ast::Expr::Panic)
+                }
+            }
+            _ => {
+                let shared = bindings.iter().map(|(ident, _)| ident.clone()).collect();
+                self.bounded(ty, value, shared)
+            }
+        };
+        Ok(bindings
+            .into_iter()
+            .rev()
+            .fold(expr, |child, (ident, value)| ast::Expr::LetIn {
+                ident,
+                ty: None,
+                value: Box::new(value),
+                child: Box::new(child),
+            }))
+    }
+
+    /// Panic unless `value`, the result of checked arithmetic, fits in a `u64`:
+    ///
+    /// ```coq
+    /// match (fits_u64 (plus a b)) with
+    /// | true => plus a b
+    /// | false =>
+    ///     match (andb (fits_u64 a) (fits_u64 b)) with
+    ///     | true => False_rect _ _
+    ///     | false => plus a b
+    ///     end
+    /// end
+    /// ```
+    ///
+    /// Since the variables of type `ty` in scope, and those in `shared`, are all `u64`s in Rust,
+    /// the panic is only reached if they fit too, which its obligation can assume. For `N`s, the
+    /// function is called `fits_u64_N`.
+    fn bounded(&mut self, ty: &Ty, value: ast::Expr, shared: Vec<ast::Ident>) -> ast::Expr {
+        let name = match ty {
+            Ty::N => "fits_u64_N",
+            _ => "fits_u64",
+        };
+        let fits = |n| ast::Expr::Apply {
+            func: name.into(),
+            args: vec![n],
+        };
+
+        // The function is defined before anything else which might use it.
+        self.require("NArith");
+        let apply = |func: &str, args| ast::Expr::Apply {
+            func: func.into(),
+            args,
+        };
+        let bound = apply("N.pow", vec![ast::Expr::Nat(2), ast::Expr::Nat(64)]);
+        let n = match ty {
+            Ty::N => ast::Expr::Var("n".into()),
+            _ => apply("N.of_nat", vec![ast::Expr::Var("n".into())]),
+        };
+        let definition = ast::Statement::Definition(ast::Definition {
+            name: name.into(),
+            args: vec![ast::Binder {
+                name: "n".into(),
+                ty: ty.clone(),
+                implicit: false,
+            }],
+            ret: Ty::Bool,
+            body: apply("N.ltb", vec![n, bound]),
+            program: false,
+        });
+        if !self.auxiliary.contains(&definition) {
+            self.auxiliary.insert(0, definition);
+        }
+
+        // Only the innermost variable with each name is in scope. Those named by the translation,
+        // like the fuel of a loop, don't model a `u64`.
+        let mut names: Vec<&ast::Ident> = vec![];
+        let mut unsigned = vec![];
+        for (name, local) in self.locals[self.frame..].iter().rev() {
+            if !names.contains(&name) {
+                names.push(name);
+                if local.as_ref() == Some(ty) && !name.contains('\'') {
+                    unsigned.push(name.clone());
+                }
+            }
+        }
+        unsigned.reverse();
+        unsigned.extend(shared);
+
+        let overflow = unsigned
+            .into_iter()
+            .map(|name| fits(ast::Expr::Var(name)))
+            .reduce(|lhs, rhs| apply("andb", vec![lhs, rhs]))
+            .map_or(ast::Expr::Panic, |assumed| {
+                if_then_else(assumed, ast::Expr::Panic, value.clone())
+            });
+        self.panics = true;
+        if_then_else(fits(value.clone()), value, overflow)
+    }
+
+    /// Whether dividing the signed integer `lhs` by `rhs` doesn't overflow, which it does when it's
+    /// the minimum of its type and `rhs` is `-1`:
+    ///
+    /// ```coq
+    /// negb (andb (Z.eqb b (-1)%Z) (is_int_min a))
+    /// ```
+    ///
+    /// A `Z` doesn't record the width of the Rust integer it models, so `is_int_min` holds for the
+    /// minimum of any of `i8` through `i128`. This may leave an obligation which doesn't hold for
+    /// a wider type, but never misses an overflow.
+    fn int_min_divided(&mut self, lhs: ast::Expr, rhs: ast::Expr) -> ast::Expr {
+        let apply = |func: &str, args| ast::Expr::Apply {
+            func: func.into(),
+            args,
+        };
+
+        // The function is defined before anything else which might use it.
+        let n = ast::Expr::Var("n".into());
+        let body = [8, 16, 32, 64, 128]
+            .into_iter()
+            .map(|bits| {
+                apply(
+                    "Z.eqb",
+                    vec![n.clone(), ast::Expr::Z(i128::MIN >> (128 - bits))],
+                )
+            })
+            .reduce(|lhs, rhs| apply("orb", vec![lhs, rhs]))
+            .unwrap();
+        let definition = ast::Statement::Definition(ast::Definition {
+            name: "is_int_min".into(),
+            args: vec![ast::Binder {
+                name: "n".into(),
+                ty: Ty::Z,
+                implicit: false,
+            }],
+            ret: Ty::Bool,
+            body,
+            program: false,
+        });
+        if !self.auxiliary.contains(&definition) {
+            self.auxiliary.insert(0, definition);
+        }
+
+        let minus_one = apply("Z.eqb", vec![rhs, ast::Expr::Z(-1)]);
+        let min = apply("is_int_min", vec![lhs]);
+        apply("negb", vec![apply("andb", vec![minus_one, min])])
+    }
+
+    /// Apply the Coq function implementing a binary operator on `N`s modelling `u64`s which wrap
+    /// around, like `wrapping_add`. Results which may not fit are reduced by `wrap_u64`, and the
+    /// amount a value is shifted by is taken modulo 64, as Rust does without overflow checks.
+    fn wrapping(
+        &mut self,
+        op: ast::BinOp,
+        lhs: ast::Expr,
+        rhs: ast::Expr,
+    ) -> Result<ast::Expr, TypeError> {
+        let apply = |func: &str, args| ast::Expr::Apply {
+            func: func.into(),
+            args,
+        };
+        let bound = apply("N.pow", vec![ast::Expr::N(2), ast::Expr::N(64)]);
+        let value = match op {
+            ast::BinOp::Add => apply("N.add", vec![lhs, rhs]),
+            ast::BinOp::Mul => apply("N.mul", vec![lhs, rhs]),
+            ast::BinOp::Sub => apply("N.sub", vec![apply("N.add", vec![lhs, bound.clone()]), rhs]),
+            ast::BinOp::Shl => apply(
+                "N.shiftl",
+                vec![lhs, apply("N.modulo", vec![rhs, ast::Expr::N(64)])],
+            ),
+            ast::BinOp::Shr => {
+                let amount = apply("N.modulo", vec![rhs, ast::Expr::N(64)]);
+                return Ok(apply("N.shiftr", vec![lhs, amount]));
+            }
+            _ => return self.apply_operator(op, &Ty::N, lhs, rhs),
+        };
+        Ok(self.wrap(&Ty::N, value))
+    }
+
+    /// Reduce a value of type `ty` modulo `2^64`, with `wrap_u64` for an `N`, or `wrap_u64_nat`
+    /// for a `nat`, which is computed with `N`s.
+    fn wrap(&mut self, ty: &Ty, value: ast::Expr) -> ast::Expr {
+        let apply = |func: &str, args| ast::Expr::Apply {
+            func: func.into(),
+            args,
+        };
+
+        // The function is defined before anything else which might use it.
+        let bound = apply("N.pow", vec![ast::Expr::N(2), ast::Expr::N(64)]);
+        let n = ast::Expr::Var("n".into());
+        let (name, body) = match ty {
+            Ty::Nat => {
+                self.require("NArith");
+                let n = apply("N.of_nat", vec![n]);
+                let wrapped = apply("N.modulo", vec![n, bound]);
+                ("wrap_u64_nat", apply("N.to_nat", vec![wrapped]))
+            }
+            _ => ("wrap_u64", apply("N.modulo", vec![n, bound])),
+        };
+        let definition = ast::Statement::Definition(ast::Definition {
+            name: name.into(),
+            args: vec![ast::Binder {
+                name: "n".into(),
+                ty: ty.clone(),
+                implicit: false,
+            }],
+            ret: ty.clone(),
+            body,
+            program: false,
+        });
+        if !self.auxiliary.contains(&definition) {
+            self.auxiliary.insert(0, definition);
+        }
+        apply(name, vec![value])
+    }
+
+    /// Convert a `nat` computed by the standard library, such as the length of a list, to an
+    /// unsigned integer.
+    fn of_nat(&self, expr: ast::Expr) -> (ast::Expr, Ty) {
+        let ty = self.numbers.ty();
+        let expr = match ty {
+            Ty::Nat => expr,
+            _ => ast::Expr::Apply {
+                func: format!("{ty}.of_nat"),
+                args: vec![expr],
+            },
+        };
+        (expr, ty)
+    }
+
+    /// Elaborate an unsigned integer the standard library needs as a `nat`, such as an index.
+    fn check_nat(&mut self, expr: &ast::Expr) -> Result<ast::Expr, TypeError> {
+        let ty = self.numbers.ty();
+        let expr = self.check(expr, &ty)?;
+        Ok(match ty {
+            Ty::Nat => expr,
+            _ => ast::Expr::Apply {
+                func: format!("{ty}.to_nat"),
+                args: vec![expr],
+            },
+        })
+    }
+
+    fn list_op(
+        &mut self,
+        op: ast::ListOp,
+        args: &[ast::Expr],
+        expected: Option<&Ty>,
+    ) -> Result<(ast::Expr, Option<Ty>), TypeError> {
+        let apply = |func: &str, args| ast::Expr::Apply {
+            func: func.into(),
+            args,
+        };
+
+        if op == ast::ListOp::Repeat {
+            let (elem, ty) = self.elaborate(&args[0], expected.and_then(list_elem))?;
+            let count = self.check_nat(&args[1])?;
+            self.require("List");
+            return Ok((apply("repeat", vec![elem, count]), ty.map(Ty::list)));
+        }
+
+        // The type of the elements comes from the list, or else from what's expected of the
+        // result.
+        let expected_list = match op {
+            ast::ListOp::Push | ast::ListOp::Filter => expected.cloned(),
+            ast::ListOp::Sum => expected.cloned().map(Ty::list),
+            _ => None,
+        };
+        let (list, list_ty) = self.elaborate(&args[0], expected_list.as_ref())?;
+        let elem = list_ty.as_ref().and_then(list_elem).cloned();
+
+        match op {
+            ast::ListOp::Len => {
+                let (expr, ty) = self.of_nat(apply("length", vec![list]));
+                Ok((expr, Some(ty)))
+            }
+            ast::ListOp::IsEmpty => {
+                let len = apply("length", vec![list]);
+                let expr = apply("Nat.eqb", vec![len, ast::Expr::Nat(0)]);
+                Ok((expr, Some(Ty::Bool)))
+            }
+            ast::ListOp::First => {
+                self.require("List");
+                Ok((apply("hd_error", vec![list]), elem.map(Ty::option)))
+            }
+            ast::ListOp::Get => {
+                let index = self.check_nat(&args[1])?;
+                self.require("List");
+                let expr = apply("nth_error", vec![list, index]);
+                Ok((expr, elem.map(Ty::option)))
+            }
+            ast::ListOp::Contains => {
+                let (value, value_ty) = self.elaborate(&args[1], elem.as_ref())?;
+                let ty = elem.or(value_ty).unwrap_or_else(|| self.numbers.ty());
+                let eq = match &ty {
+                    Ty::Var(param) if self.has_bound(param, "PartialEq") => {
+                        Some(ast::Class::method("PartialEq", "eqb"))
+                    }
+                    ty => operator(ast::BinOp::Eq, ty).map(ast::Ident::from),
+                };
+                let eq = eq.ok_or_else(|| {
+                    TypeError::new(format!("`contains` isn't supported on a list of `{ty}`"))
+                })?;
+                if eq.starts_with("Bool.") {
+                    self.require("Bool");
+                }
+                self.require("List");
+                let expr = apply("existsb", vec![apply(&eq, vec![value]), list]);
+                Ok((expr, Some(Ty::Bool)))
+            }
+            ast::ListOp::Sum => {
+                let ty = elem
+                    .or(expected.cloned())
+                    .unwrap_or_else(|| self.numbers.ty());
+                self.require("List");
+                let sum = match ty {
+                    Ty::Nat => apply("list_sum", vec![list]),
+                    Ty::N | Ty::Z => {
+                        let add = ast::Expr::Var(format!("{ty}.add"));
+                        apply("fold_right", vec![add, literal(&ty, 0), list])
+                    }
+                    _ => {
+                        return Err(TypeError::new(format!(
+                            "`sum` isn't supported on a list of `{ty}`"
+                        )))
+                    }
+                };
+                let expr = match ty {
+                    Ty::N if self.numbers == Numbers::Word => self.wrap(&Ty::N, sum),
+                    Ty::Nat | Ty::N if self.checked => self.bounded(&ty, sum, vec![]),
+                    _ => sum,
+                };
+                Ok((expr, Some(ty)))
+            }
+            ast::ListOp::Map => {
+                let result = expected.and_then(list_elem);
+                let (func, ty) = self.lambda(&args[1], &[elem], result)?;
+                self.require("List");
+                let expr = apply("map", vec![func, list]);
+                Ok((expr, ty.or_else(|| result.cloned()).map(Ty::list)))
+            }
+            ast::ListOp::Filter | ast::ListOp::Any | ast::ListOp::All => {
+                let (func, _) = self.lambda(&args[1], &[elem], Some(&Ty::Bool))?;
+                self.require("List");
+                let (func_name, ty) = match op {
+                    ast::ListOp::Filter => ("filter", list_ty),
+                    ast::ListOp::Any => ("existsb", Some(Ty::Bool)),
+                    _ => ("forallb", Some(Ty::Bool)),
+                };
+                Ok((apply(func_name, vec![func, list]), ty))
+            }
+            ast::ListOp::Fold => {
+                let (mut init, mut acc) = self.elaborate(&args[1], expected)?;
+                let (mut func, ty) =
+                    self.lambda(&args[2], &[acc.clone(), elem.clone()], acc.as_ref())?;
+
+                // If the type of the accumulator is only known from the function's result, the
+                // initial value and the function's parameter need to have it too.
+                if let (None, Some(ty)) = (&acc, ty) {
+                    init = self.check(&args[1], &ty)?;
+                    func = self
+                        .lambda(&args[2], &[Some(ty.clone()), elem], Some(&ty))?
+                        .0;
+                    acc = Some(ty);
+                }
+                self.require("List");
+                Ok((apply("fold_left", vec![func, list, init]), acc))
+            }
+            ast::ListOp::Push => {
+                let (value, value_ty) = self.elaborate(&args[1], elem.as_ref())?;
+
+                // Otherwise, the type of the list comes from the element pushed to it.
+                let (list, list_ty) = match (list_ty, value_ty) {
+                    (None, Some(value_ty)) => {
+                        let ty = Ty::list(value_ty);
+                        (self.check(&args[0], &ty)?, Some(ty))
+                    }
+                    (list_ty, _) => (list, list_ty),
+                };
+                let expr = apply("app", vec![list, ast::Expr::List(vec![value])]);
+                Ok((expr, list_ty))
+            }
+            ast::ListOp::Repeat => unreachable!(),
+        }
+    }
+
+    fn unary(
+        &mut self,
+        op: ast::UnOp,
+        operand: &ast::Expr,
+    ) -> Result<(ast::Expr, Option<Ty>), TypeError> {
+        match op {
+            // Only signed integers can be negated, so an operand of unknown type must be one.
+            ast::UnOp::Neg => {
+                let operand = match self.infer(operand)? {
+                    (operand, Some(Ty::Z)) => operand,
+                    (_, None) => self.check(operand, &Ty::Z)?,
+                    (_, Some(ty)) => {
+                        return Err(TypeError::new(format!(
+                            "can't negate a value of type `{ty}`"
+                        )))
+                    }
+                };
+                let expr = ast::Expr::Apply {
+                    func: "Z.opp".into(),
+                    args: vec![operand],
+                };
+                Ok((expr, Some(Ty::Z)))
+            }
+
+            // Logical negation of a `bool`, or bitwise negation of a signed integer.
+            ast::UnOp::Not => {
+                let (operand, ty) = match self.infer(operand)? {
+                    (operand, Some(ty)) => (operand, ty),
+                    (_, None) => (self.check(operand, &Ty::Bool)?, Ty::Bool),
+                };
+                let func = match ty {
+                    Ty::Bool => "negb",
+                    Ty::Z => "Z.lnot",
+                    ty => {
+                        return Err(TypeError::new(format!(
+                            "operator `!` isn't supported on `{ty}`"
+                        )))
+                    }
+                };
+                let expr = ast::Expr::Apply {
+                    func: func.into(),
+                    args: vec![operand],
+                };
+                Ok((expr, Some(ty)))
+            }
+        }
+    }
+}
+
+/// The type of the state of a loop, given the arguments of its auxiliary function, which end
+/// with the state.
+fn state_type(args: &[ast::Binder], state: &[ast::Ident]) -> Ty {
+    args[args.len() - state.len()..]
+        .iter()
+        .map(|arg| arg.ty.clone())
+        .reduce(Ty::prod)
+        .expect("loop should have state")
+}
+
+/// The value of the state of a loop: a tuple of its variables, unless there's only one.
+fn state_value(state: &[ast::Ident]) -> ast::Expr {
+    match state {
+        [ident] => ast::Expr::Var(ident.clone()),
+        _ => ast::Expr::Tuple(state.iter().cloned().map(ast::Expr::Var).collect()),
+    }
+}
+
+/// Match on a `bool`, `cond`, evaluating `then` if it's true and `otherwise` if it's false.
+fn if_then_else(cond: ast::Expr, then: ast::Expr, otherwise: ast::Expr) -> ast::Expr {
+    ast::Expr::Match {
+        scrutinee: Box::new(cond),
+        cases: vec![
+            ast::MatchCase {
+                pattern: ast::Pattern::Expr(ast::Expr::Bool(true)),
+                body: then,
+            },
+            ast::MatchCase {
+                pattern: ast::Pattern::Expr(ast::Expr::Bool(false)),
+                body: otherwise,
+            },
+        ],
+    }
+}
+
+/// Replace the expression at the end of a chain of `let`s, such as the state at the end of a
+/// loop's body, with `tail`. Where the body branches because it may panic, the state at the end
+/// of each branch which doesn't is replaced.
+fn replace_tail(expr: ast::Expr, tail: ast::Expr) -> ast::Expr {
+    match expr {
+        ast::Expr::Match { scrutinee, cases } => ast::Expr::Match {
+            scrutinee,
+            cases: cases
+                .into_iter()
+                .map(|case| ast::MatchCase {
+                    pattern: case.pattern,
+                    body: replace_tail(case.body, tail.clone()),
+                })
+                .collect(),
+        },
+        ast::Expr::LetIn {
+            ident,
+            ty,
+            value,
+            child,
+        } => ast::Expr::LetIn {
+            ident,
+            ty,
+            value,
+            child: Box::new(replace_tail(*child, tail)),
+        },
+        ast::Expr::LetPattern {
+            pattern,
+            ty,
+            value,
+            child,
+        } => ast::Expr::LetPattern {
+            pattern,
+            ty,
+            value,
+            child: Box::new(replace_tail(*child, tail)),
+        },
+        ast::Expr::LetState {
+            pattern,
+            state,
+            value,
+            child,
+        } => ast::Expr::LetState {
+            pattern,
+            state,
+            value,
+            child: Box::new(replace_tail(*child, tail)),
+        },
+        ast::Expr::Panic => expr,
+        _ => tail,
+    }
+}
+
+/// Add the variables and functions which `expr` refers to to `names`, in order of first use.
+fn mentioned(expr: &ast::Expr, names: &mut Vec<ast::Ident>) {
+    let mut mention = |name: &ast::Ident| {
+        if !names.contains(name) {
+            names.push(name.clone());
+        }
+    };
+    match expr {
+        ast::Expr::Var(name) => mention(name),
+        ast::Expr::Apply { func, args } => {
+            mention(func);
+            for arg in args {
+                mentioned(arg, names);
+            }
+        }
+        ast::Expr::Match { scrutinee, cases } => {
+            mentioned(scrutinee, names);
+            for case in cases {
+                mentioned(&case.body, names);
+            }
+        }
+        ast::Expr::LetIn { value, child, .. }
+        | ast::Expr::LetPattern { value, child, .. }
+        | ast::Expr::LetState { value, child, .. }
+        | ast::Expr::Try { value, child, .. }
+        | ast::Expr::Bind { value, child, .. } => {
+            mentioned(value, names);
+            mentioned(child, names);
+        }
+        ast::Expr::BinOp { lhs, rhs, .. }
+        | ast::Expr::TraitOp {
+            receiver: lhs,
+            arg: rhs,
+            ..
+        } => {
+            mentioned(lhs, names);
+            mentioned(rhs, names);
+        }
+        ast::Expr::UnOp { operand, .. } | ast::Expr::Return { value: operand, .. } => {
+            mentioned(operand, names)
+        }
+        ast::Expr::Tuple(elems)
+        | ast::Expr::List(elems)
+        | ast::Expr::ListOp { args: elems, .. } => {
+            for elem in elems {
+                mentioned(elem, names);
+            }
+        }
+        ast::Expr::Lambda { body, .. } => mentioned(body, names),
+        ast::Expr::ForRange {
+            start, end, body, ..
+        } => {
+            mentioned(start, names);
+            mentioned(end, names);
+            mentioned(body, names);
+        }
+        ast::Expr::While {
+            cond,
+            measure,
+            body,
+            ..
+        } => {
+            mentioned(cond, names);
+            mentioned(measure, names);
+            mentioned(body, names);
+        }
+        ast::Expr::Record { fields, .. } => {
+            for (_, value) in fields {
+                mentioned(value, names);
+            }
+        }
+        ast::Expr::Field { base, .. } => mentioned(base, names),
+        ast::Expr::SetField { base, value, .. } => {
+            mentioned(base, names);
+            mentioned(value, names);
+        }
+        ast::Expr::Nat(_)
+        | ast::Expr::N(_)
+        | ast::Expr::Z(_)
+        | ast::Expr::Bool(_)
+        | ast::Expr::Tt
+        | ast::Expr::Panic => {}
+    }
+}
+
+/// Whether `ty` mentions any of the type parameters `params`.
+fn mentions_any(ty: &Ty, params: &[ast::Ident]) -> bool {
+    match ty {
+        Ty::Var(name) => params.contains(name),
+        Ty::Apply { args: tys, .. } | Ty::Arrow(tys) => {
+            tys.iter().any(|ty| mentions_any(ty, params))
+        }
+        Ty::Nat | Ty::N | Ty::Z | Ty::Bool | Ty::Named(_) | Ty::Type => false,
+    }
+}
+
+/// Give the type parameters `params` which `ty` mentions the corresponding parts of `found`, as
+/// `(param, ty)`, unless they've already been given one.
+fn unify(ty: &Ty, found: &Ty, params: &[ast::Ident], instances: &mut Vec<(ast::Ident, Ty)>) {
+    match (ty, found) {
+        (Ty::Var(name), _)
+            if params.contains(name) && !instances.iter().any(|(param, _)| param == name) =>
+        {
+            instances.push((name.clone(), found.clone()));
+        }
+        (
+            Ty::Apply { func, args },
+            Ty::Apply {
+                func: found_func,
+                args: found_args,
+            },
+        ) if func == found_func => {
+            for (ty, found) in args.iter().zip(found_args) {
+                unify(ty, found, params, instances);
+            }
+        }
+        (Ty::Arrow(tys), Ty::Arrow(found)) => {
+            for (ty, found) in tys.iter().zip(found) {
+                unify(ty, found, params, instances);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Replace each type parameter in `ty` which has been given a type, as `(param, ty)`, with it.
+fn instantiate(ty: &Ty, instances: &[(ast::Ident, Ty)]) -> Ty {
+    match ty {
+        Ty::Var(name) => instances
+            .iter()
+            .find(|(param, _)| param == name)
+            .map_or_else(|| ty.clone(), |(_, instance)| instance.clone()),
+        Ty::Apply { func, args } => Ty::Apply {
+            func: func.clone(),
+            args: args.iter().map(|arg| instantiate(arg, instances)).collect(),
+        },
+        Ty::Arrow(tys) => Ty::Arrow(tys.iter().map(|ty| instantiate(ty, instances)).collect()),
+        Ty::Nat | Ty::N | Ty::Z | Ty::Bool | Ty::Named(_) | Ty::Type => ty.clone(),
+    }
+}
+
+/// An integer literal of type `ty`, which must be an integer type.
+fn literal(ty: &Ty, n: u64) -> ast::Expr {
+    match ty {
+        Ty::N => ast::Expr::N(n),
+        Ty::Z => ast::Expr::Z(n.into()),
+        _ => ast::Expr::Nat(n),
+    }
+}
+
+/// The monad `?` binds the value in for a value of type `ty`, if it can be applied to one.
+fn monad(ty: &Ty) -> Option<ast::Monad> {
+    match ty {
+        Ty::Apply { func, args } if func == "option" && args.len() == 1 => Some(ast::Monad::Option),
+        Ty::Apply { func, args } if func == "sum" && args.len() == 2 => Some(ast::Monad::Sum),
+        _ => None,
+    }
+}
+
+/// The types of the elements of a tuple of `n` elements and type `ty`, if it's a product.
+fn components(ty: &Ty, n: usize) -> Option<Vec<Ty>> {
+    match ty {
+        _ if n == 0 => None,
+        _ if n == 1 => Some(vec![ty.clone()]),
+        Ty::Apply { func, args } if func == "prod" && args.len() == 2 => {
+            let mut components = components(&args[0], n - 1)?;
+            components.push(args[1].clone());
+            Some(components)
+        }
+        _ => None,
+    }
+}
+
+/// The type of the elements of a list of type `ty`.
+fn list_elem(ty: &Ty) -> Option<&Ty> {
+    match ty {
+        Ty::Apply { func, args } if func == "list" => args.first(),
+        _ => None,
+    }
+}
+
+/// The type of the argument of the prelude constructor `name`, if it builds a value of type `ty`.
+fn constructor_arg<'a>(name: &str, ty: Option<&'a Ty>) -> Option<&'a Ty> {
+    match (name, ty?) {
+        ("S", Ty::Nat) => Some(&Ty::Nat),
+        ("Some", Ty::Apply { func, args }) if func == "option" => args.first(),
+        ("inl", Ty::Apply { func, args }) if func == "sum" => args.first(),
+        ("inr", Ty::Apply { func, args }) if func == "sum" => args.get(1),
+        _ => None,
+    }
+}
+
+/// The Coq function implementing a binary operator on operands of type `ty`.
+pub(crate) fn operator(op: ast::BinOp, ty: &Ty) -> Option<&'static str> {
+    let func = match (ty, op) {
+        (Ty::Nat, ast::BinOp::Add) => "plus",
+        (Ty::Nat, ast::BinOp::Sub) => "minus",
+        (Ty::Nat, ast::BinOp::Mul) => "mult",
+        (Ty::Nat, ast::BinOp::Div) => "Nat.div",
+        (Ty::Nat, ast::BinOp::Rem) => "Nat.modulo",
+        (Ty::Nat, ast::BinOp::Shl) => "Nat.shiftl",
+        (Ty::Nat, ast::BinOp::Shr) => "Nat.shiftr",
+        (Ty::Nat, ast::BinOp::BitAnd) => "Nat.land",
+        (Ty::Nat, ast::BinOp::BitOr) => "Nat.lor",
+        (Ty::Nat, ast::BinOp::BitXor) => "Nat.lxor",
+        (Ty::Nat, ast::BinOp::Eq) => "Nat.eqb",
+        (Ty::Nat, ast::BinOp::Lt) => "Nat.ltb",
+        (Ty::Nat, ast::BinOp::Le) => "Nat.leb",
+
+        (Ty::N, ast::BinOp::Add) => "N.add",
+        (Ty::N, ast::BinOp::Sub) => "N.sub",
+        (Ty::N, ast::BinOp::Mul) => "N.mul",
+        (Ty::N, ast::BinOp::Div) => "N.div",
+        (Ty::N, ast::BinOp::Rem) => "N.modulo",
+        (Ty::N, ast::BinOp::Shl) => "N.shiftl",
+        (Ty::N, ast::BinOp::Shr) => "N.shiftr",
+        (Ty::N, ast::BinOp::BitAnd) => "N.land",
+        (Ty::N, ast::BinOp::BitOr) => "N.lor",
+        (Ty::N, ast::BinOp::BitXor) => "N.lxor",
+        (Ty::N, ast::BinOp::Eq) => "N.eqb",
+        (Ty::N, ast::BinOp::Lt) => "N.ltb",
+        (Ty::N, ast::BinOp::Le) => "N.leb",
+
+        // Rust's division and remainder round towards zero, like `Z.quot` and `Z.rem`.
+        (Ty::Z, ast::BinOp::Add) => "Z.add",
+        (Ty::Z, ast::BinOp::Sub) => "Z.sub",
+        (Ty::Z, ast::BinOp::Mul) => "Z.mul",
+        (Ty::Z, ast::BinOp::Div) => "Z.quot",
+        (Ty::Z, ast::BinOp::Rem) => "Z.rem",
+        (Ty::Z, ast::BinOp::Shl) => "Z.shiftl",
+        (Ty::Z, ast::BinOp::Shr) => "Z.shiftr",
+        (Ty::Z, ast::BinOp::BitAnd) => "Z.land",
+        (Ty::Z, ast::BinOp::BitOr) => "Z.lor",
+        (Ty::Z, ast::BinOp::BitXor) => "Z.lxor",
+        (Ty::Z, ast::BinOp::Eq) => "Z.eqb",
+        (Ty::Z, ast::BinOp::Lt) => "Z.ltb",
+        (Ty::Z, ast::BinOp::Le) => "Z.leb",
+
+        (Ty::Bool, ast::BinOp::Eq) => "Bool.eqb",
+
+        // Bitwise operators on `bool`s don't short-circuit, but otherwise act like the logical
+        // ones.
+        (Ty::Bool, ast::BinOp::BitAnd) => "andb",
+        (Ty::Bool, ast::BinOp::BitOr) => "orb",
+        (Ty::Bool, ast::BinOp::BitXor) => "xorb",
+
+        _ => return None,
+    };
+    Some(func)
+}
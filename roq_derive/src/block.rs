@@ -1,34 +1,183 @@
+use std::borrow::Cow;
+
+use proc_macro2::Span;
 use roq_core::ast;
 use syn::spanned::Spanned;
+use syn::visit::Visit;
 
-use crate::expr::expr_as_ast;
+use crate::context::Context;
+use crate::expr::{compound_assign_op, expr_as_ast, if_as_ast, is_panic};
+use crate::pat::{bound_vars, is_irrefutable, mutable_vars, pat_as_ast};
+use crate::recursion;
+use crate::ty::use_type;
 
-pub fn block_as_ast(block: &syn::Block) -> syn::Result<ast::Expr> {
-    // Make sure the function consists of supported statements.
-    let stmts = block.stmts.as_slice();
+pub fn block_as_ast(cx: &mut Context, block: &syn::Block) -> syn::Result<ast::Expr> {
+    // Only variables declared in this block can be reassigned within it.
+    let outer = std::mem::take(&mut cx.assignable);
+    let expr = stmts_as_ast(cx, block);
+    cx.assignable = outer;
+    expr
+}
 
-    // Empty blocks evaluate to the unit type.
-    if stmts.is_empty() {
-        return Ok(ast::Expr::Tt);
+/// A statement of a block, which binds variables for the rest of it.
+enum Binding {
+    /// `let pattern := value in`, optionally annotated with the type of the value.
+    Let(ast::Pattern, Option<ast::Ty>, ast::Expr),
+
+    /// Bind a variable to what's in a value `?` is applied to, or return early.
+    Try(ast::Ident, ast::Expr, ast::Loc),
+
+    /// `let '(pattern, state...) := value in`, for a call which is passed the variables in
+    /// `state` by `&mut`.
+    State(ast::Pattern, Vec<ast::Ident>, ast::Expr),
+}
+
+/// Translate the statements of a block, which may reassign any of `cx.assignable`.
+pub fn stmts_as_ast(cx: &mut Context, block: &syn::Block) -> syn::Result<ast::Expr> {
+    // The values `?` is applied to in the enclosing statement are bound outside the block, and
+    // the guard clauses in the block only hold within it.
+    let outer_tries = std::mem::take(&mut cx.tries);
+    let outer_depth = cx.recursion.as_ref().map(|recursion| recursion.depth);
+    let translated = bindings_as_ast(cx, block);
+    cx.tries = outer_tries;
+    if let (Some(recursion), Some(depth)) = (cx.recursion.as_mut(), outer_depth) {
+        recursion.depth = depth;
     }
+    let (bindings, mut seq_stmt) = translated?;
 
-    // Match the last statement, the implicit return value.
-    let last_stmt = stmts.last().unwrap();
-    let mut seq_stmt = match last_stmt {
-        syn::Stmt::Expr(expr, _) => expr_as_ast(expr)?,
-        _ => {
-            return Err(syn::Error::new(
-                last_stmt.span(),
-                "Expected function to end with an expr",
-            ))
-        }
+    // Iterate backwards through the bindings, building up the AST.
+    for binding in bindings.into_iter().rev() {
+        seq_stmt = match binding {
+            Binding::Try(ident, value, loc) => ast::Expr::Try {
+                ident,
+                value: Box::new(value),
+                child: Box::new(seq_stmt),
+                loc,
+            },
+
+            // If the value might return early or panic, the rest of the block follows each of its
+            // branches which doesn't.
+            Binding::Let(pattern, ty, value) if exits(&value) => {
+                map_tails(value, &mut |tail| match tail {
+                    ast::Expr::Return { .. } | ast::Expr::Panic => tail,
+                    tail => let_as_ast(pattern.clone(), ty.clone(), tail, seq_stmt.clone()),
+                })
+            }
+            Binding::Let(pattern, ty, value) => let_as_ast(pattern, ty, value, seq_stmt),
+            Binding::State(pattern, state, value) => ast::Expr::LetState {
+                pattern: Box::new(pattern),
+                state,
+                value: Box::new(value),
+                child: Box::new(seq_stmt),
+            },
+        };
+    }
+
+    Ok(seq_stmt)
+}
+
+/// Translate the statements of a block to the variables each binds, and the expression at the
+/// end of the block.
+fn bindings_as_ast(cx: &mut Context, block: &syn::Block) -> syn::Result<(Vec<Binding>, ast::Expr)> {
+    // Statements after a `return` or a panic are never evaluated.
+    let stmts = match block.stmts.iter().position(diverges) {
+        Some(i) => &block.stmts[..=i],
+        None => block.stmts.as_slice(),
     };
 
-    // Iterate backwards through the statements, building up the AST.
-    for stmt in stmts.iter().rev() {
-        match stmt {
-            // Ignore useless expressions.
-            syn::Stmt::Expr(_expr, _) => {}
+    // Empty blocks evaluate to the unit type.
+    let Some((last_stmt, init_stmts)) = stmts.split_last() else {
+        return Ok((vec![], ast::Expr::Tt));
+    };
+
+    // Translate each local variable declaration in order.
+    let mut bindings = vec![];
+    for stmt in init_stmts {
+        let stmt = &*macro_as_expr(stmt);
+        let binding = match stmt {
+            // Convert assignments to `LetIn` expressions which shadow the old value.
+            syn::Stmt::Expr(syn::Expr::Assign(assign), _) => {
+                let ident = assigned_var(cx, &assign.left)?;
+                let value = expr_as_ast(cx, &assign.right)?;
+                let value = assigned_value(cx, &assign.left, value)?;
+                Some(Binding::Let(ast::Pattern::Ident(ident), None, value))
+            }
+            syn::Stmt::Expr(
+                syn::Expr::Binary(syn::ExprBinary {
+                    left, op, right, ..
+                }),
+                _,
+            ) if compound_assign_op(op).is_some() => {
+                let ident = assigned_var(cx, left)?;
+                let value = ast::Expr::BinOp {
+                    op: compound_assign_op(op).unwrap(),
+                    lhs: Box::new(expr_as_ast(cx, left)?),
+                    rhs: Box::new(expr_as_ast(cx, right)?),
+                    loc: cx.loc(op.span()),
+                };
+                let value = assigned_value(cx, left, value)?;
+                Some(Binding::Let(ast::Pattern::Ident(ident), None, value))
+            }
+
+            // Pushing to a list rebinds it to a new list, with the element appended.
+            syn::Stmt::Expr(syn::Expr::MethodCall(call), _)
+                if call.method == "push" && call.args.len() == 1 =>
+            {
+                let ident = assigned_var(cx, &call.receiver)?;
+                let value = ast::Expr::ListOp {
+                    op: ast::ListOp::Push,
+                    args: vec![
+                        expr_as_ast(cx, &call.receiver)?,
+                        expr_as_ast(cx, &call.args[0])?,
+                    ],
+                    loc: cx.loc(call.span()),
+                };
+                let value = assigned_value(cx, &call.receiver, value)?;
+                Some(Binding::Let(ast::Pattern::Ident(ident), None, value))
+            }
+
+            // A call which is passed `&mut` variables rebinds them to their new values, which it
+            // returns along with its result.
+            syn::Stmt::Expr(expr, _) if !mut_args(expr).is_empty() => {
+                let (state, value) = state_call_as_ast(cx, expr)?;
+                Some(Binding::State(ast::Pattern::Wildcard, state, value))
+            }
+
+            // Loops rebind the variables they assign to their values after the last iteration.
+            syn::Stmt::Expr(expr @ (syn::Expr::ForLoop(_) | syn::Expr::While(_)), _) => {
+                let (state, value) = match expr {
+                    syn::Expr::ForLoop(for_loop) => for_loop_as_ast(cx, for_loop)?,
+                    syn::Expr::While(while_loop) => while_as_ast(cx, while_loop)?,
+                    _ => unreachable!(),
+                };
+                Some(Binding::Let(state_pattern(state), None, value))
+            }
+
+            // Conditionals rebind the variables their branches assign, to their values after
+            // whichever branch is taken.
+            syn::Stmt::Expr(syn::Expr::If(if_ex), _)
+                if !if_reassigned_vars(cx, if_ex).is_empty() =>
+            {
+                let state = if_reassigned_vars(cx, if_ex);
+                let value = if_stmt_as_ast(cx, if_ex, Some(&state))?;
+                Some(Binding::Let(state_pattern(state), None, value))
+            }
+
+            // Statements like `if n == 0 { return 1; }`, `check(n)?;` or `assert!(n > 0);` are
+            // evaluated for whether they return early or panic. Any other statement would only be
+            // evaluated for its value, which is discarded, and dropping it could hide a difference
+            // between the Rust and Coq functions.
+            syn::Stmt::Expr(expr, _) => {
+                let value = expr_as_ast(cx, expr)?;
+                if !exits(&value) && cx.tries.is_empty() {
+                    return Err(syn::Error::new(
+                        expr.span(),
+                        "expected a statement which assigns to a variable, since the value of \
+                         this one is discarded",
+                    ));
+                }
+                Some(Binding::Let(ast::Pattern::Wildcard, None, value))
+            }
 
             // Convert local variable declarations to `LetIn` expressions.
             syn::Stmt::Local(local) => {
@@ -38,27 +187,714 @@ pub fn block_as_ast(block: &syn::Block) -> syn::Result<ast::Expr> {
                         "expected local variable to be initialized",
                     ));
                 };
-                let local_init_expr = expr_as_ast(&local_init.expr)?;
-
-                let ident = match &local.pat {
-                    syn::Pat::Ident(ident) => ident.ident.to_string(),
-                    _ => {
-                        return Err(syn::Error::new(
-                            local.pat.span(),
-                            "expected a single identifier, not a pattern, in local variable",
-                        ))
+
+                // Keep the type annotation if there is one.
+                let (pat, ty) = match &local.pat {
+                    syn::Pat::Type(syn::PatType { pat, ty, .. }) => {
+                        (&**pat, Some(use_type(&mut cx.types, &cx.scope, ty)?))
                     }
+                    pat => (pat, None),
                 };
 
-                seq_stmt = roq_core::ast::Expr::LetIn {
-                    ident,
-                    value: Box::new(local_init_expr),
-                    child: Box::new(seq_stmt),
+                // Bind the variable directly to what's in a value `?` is applied to, and rebind the
+                // `&mut` variables passed to a call along with its result.
+                let mut state = vec![];
+                let local_init_expr = match (&*local_init.expr, pat, &ty) {
+                    (syn::Expr::Try(try_ex), syn::Pat::Ident(ident), None) => {
+                        let value = expr_as_ast(cx, &try_ex.expr)?;
+                        let loc = cx.loc(try_ex.span());
+                        cx.tries.push((ident.ident.to_string(), value, loc));
+                        None
+                    }
+                    (expr, _, _) if !mut_args(expr).is_empty() => {
+                        let (vars, value) = state_call_as_ast(cx, expr)?;
+                        state = vars;
+                        Some(value)
+                    }
+                    _ => Some(expr_as_ast(cx, &local_init.expr)?),
                 };
+
+                // Destructuring a value must always succeed.
+                let pattern = pat_as_ast(pat)?;
+                if !is_irrefutable(&pattern) {
+                    return Err(syn::Error::new(
+                        pat.span(),
+                        "expected an irrefutable pattern in local variable",
+                    ));
+                }
+
+                // Only `mut` variables can be reassigned, including those which shadow another.
+                let bound = bound_vars(&pattern);
+                cx.assignable.retain(|ident| !bound.contains(ident));
+                cx.assignable.extend(mutable_vars(pat));
+
+                // A discarded value is still bound, to `_`, since evaluating it might return early
+                // or panic.
+                match local_init_expr {
+                    Some(value) if !state.is_empty() => Some(Binding::State(pattern, state, value)),
+                    Some(value) if pattern == ast::Pattern::Wildcard => {
+                        Some(Binding::Let(ast::Pattern::Ident("_".into()), ty, value))
+                    }
+                    Some(value) => Some(Binding::Let(pattern, ty, value)),
+                    None => None,
+                }
+            }
+            _ => return Err(syn::Error::new(stmt.span(), "unsupported statement")),
+        };
+
+        // The values `?` was applied to in the statement are bound before it.
+        bindings.extend(
+            cx.tries
+                .drain(..)
+                .map(|(ident, value, loc)| Binding::Try(ident, value, loc)),
+        );
+        bindings.extend(binding);
+
+        // After a guard clause like `if n == 0 { return 1; }`, the rest of the block is only
+        // evaluated where the decreasing argument is at least 1, so its predecessor is bound.
+        if let syn::Stmt::Expr(syn::Expr::If(if_ex), _) = stmt {
+            if let Some(k) = guard_clause(cx, if_ex) {
+                let recursion = cx.recursion.as_mut().unwrap();
+                recursion.depth = recursion.depth.max(k);
             }
-            _ => {}
         }
     }
 
-    Ok(seq_stmt)
+    // Match the last statement, the implicit return value.
+    let last_stmt = &*macro_as_expr(last_stmt);
+    let tail = match last_stmt {
+        // A conditional at the end of the block can reassign variables in its branches, since
+        // they aren't used afterwards.
+        syn::Stmt::Expr(syn::Expr::If(if_ex), None)
+            if !if_reassigned_vars(cx, if_ex).is_empty() =>
+        {
+            if_stmt_as_ast(cx, if_ex, None)?
+        }
+        // A call which is passed `&mut` variables at the end of the block evaluates to its result,
+        // after rebinding them.
+        syn::Stmt::Expr(expr, None) if !mut_args(expr).is_empty() => {
+            let (state, value) = state_call_as_ast(cx, expr)?;
+            let result = cx.fresh("result");
+            let pattern = ast::Pattern::Ident(result.clone());
+            bindings.push(Binding::State(pattern, state, value));
+            ast::Expr::Var(result)
+        }
+        syn::Stmt::Expr(expr, _) => expr_as_ast(cx, expr)?,
+        _ => {
+            return Err(syn::Error::new(
+                last_stmt.span(),
+                "Expected function to end with an expr",
+            ))
+        }
+    };
+
+    bindings.extend(
+        cx.tries
+            .drain(..)
+            .map(|(ident, value, loc)| Binding::Try(ident, value, loc)),
+    );
+    Ok((bindings, tail))
+}
+
+/// Bind `pattern` to `value` in `child`.
+fn let_as_ast(
+    pattern: ast::Pattern,
+    ty: Option<ast::Ty>,
+    value: ast::Expr,
+    child: ast::Expr,
+) -> ast::Expr {
+    // Discarding a value, or rebinding variables to themselves, doesn't bind anything. These come
+    // from the branches of statements which might return early, where they don't.
+    if pattern == ast::Pattern::Wildcard
+        || ty.is_none()
+            && pattern == state_pattern(bound_vars(&pattern))
+            && value == state_expr(&bound_vars(&pattern))
+    {
+        return child;
+    }
+    let value = Box::new(value);
+    let child = Box::new(child);
+    match pattern {
+        ast::Pattern::Ident(ident) => ast::Expr::LetIn {
+            ident,
+            ty,
+            value,
+            child,
+        },
+        pattern => ast::Expr::LetPattern {
+            pattern: Box::new(pattern),
+            ty,
+            value,
+            child,
+        },
+    }
+}
+
+/// Convert a macro invoked as a statement, like `assert!(n > 0);`, to an expression statement, so
+/// that it's translated like any other macro.
+fn macro_as_expr(stmt: &syn::Stmt) -> Cow<'_, syn::Stmt> {
+    match stmt {
+        syn::Stmt::Macro(stmt_mac) => Cow::Owned(syn::Stmt::Expr(
+            syn::Expr::Macro(syn::ExprMacro {
+                attrs: stmt_mac.attrs.clone(),
+                mac: stmt_mac.mac.clone(),
+            }),
+            stmt_mac.semi_token,
+        )),
+        stmt => Cow::Borrowed(stmt),
+    }
+}
+
+/// Whether a statement is a `return`, or a macro which always panics.
+fn diverges(stmt: &syn::Stmt) -> bool {
+    match stmt {
+        syn::Stmt::Expr(syn::Expr::Return(_), _) => true,
+        syn::Stmt::Expr(syn::Expr::Macro(syn::ExprMacro { mac, .. }), _)
+        | syn::Stmt::Macro(syn::StmtMacro { mac, .. }) => is_panic(mac),
+        _ => false,
+    }
+}
+
+/// Whether `expr` might return early or panic, because one of its branches ends with a `Return`,
+/// a `Try` or a `Panic`.
+fn exits(expr: &ast::Expr) -> bool {
+    match expr {
+        ast::Expr::Return { .. } | ast::Expr::Try { .. } | ast::Expr::Panic => true,
+        ast::Expr::Match { cases, .. } => cases.iter().any(|case| exits(&case.body)),
+        ast::Expr::LetIn { child, .. }
+        | ast::Expr::LetPattern { child, .. }
+        | ast::Expr::LetState { child, .. } => exits(child),
+        _ => false,
+    }
+}
+
+/// Replace the expression at the end of each branch of `expr` with `f` of it.
+fn map_tails<F>(expr: ast::Expr, f: &mut F) -> ast::Expr
+where
+    F: FnMut(ast::Expr) -> ast::Expr,
+{
+    match expr {
+        ast::Expr::Match { scrutinee, cases } => ast::Expr::Match {
+            scrutinee,
+            cases: cases
+                .into_iter()
+                .map(|case| ast::MatchCase {
+                    pattern: case.pattern,
+                    body: map_tails(case.body, f),
+                })
+                .collect(),
+        },
+        ast::Expr::LetIn {
+            ident,
+            ty,
+            value,
+            child,
+        } => ast::Expr::LetIn {
+            ident,
+            ty,
+            value,
+            child: Box::new(map_tails(*child, f)),
+        },
+        ast::Expr::LetPattern {
+            pattern,
+            ty,
+            value,
+            child,
+        } => ast::Expr::LetPattern {
+            pattern,
+            ty,
+            value,
+            child: Box::new(map_tails(*child, f)),
+        },
+        ast::Expr::LetState {
+            pattern,
+            state,
+            value,
+            child,
+        } => ast::Expr::LetState {
+            pattern,
+            state,
+            value,
+            child: Box::new(map_tails(*child, f)),
+        },
+        ast::Expr::Try {
+            ident,
+            value,
+            child,
+            loc,
+        } => ast::Expr::Try {
+            ident,
+            value,
+            child: Box::new(map_tails(*child, f)),
+            loc,
+        },
+        expr => f(expr),
+    }
+}
+
+/// Replace each `Return` at the end of a branch of the body of a function or closure with the
+/// value it returns, since everything after it has been moved into the other branches.
+pub fn strip_returns(body: ast::Expr) -> ast::Expr {
+    map_tails(body, &mut |tail| match tail {
+        ast::Expr::Return { value, .. } => *value,
+        tail => tail,
+    })
+}
+
+/// Pair the value at the end of each branch of the body of a function with the values of its
+/// `&mut` arguments, `state`, there, which is how their new values are returned to the caller.
+pub fn return_state(body: ast::Expr, state: &[ast::Ident]) -> ast::Expr {
+    if state.is_empty() {
+        return body;
+    }
+    map_tails(body, &mut |tail| match tail {
+        ast::Expr::Panic => tail,
+        tail => {
+            let mut values = vec![tail];
+            values.extend(state.iter().cloned().map(ast::Expr::Var));
+            ast::Expr::Tuple(values)
+        }
+    })
+}
+
+/// If `if_ex` is a guard clause, which returns early or panics unless the decreasing argument of
+/// the function is at least `k`, return `k`.
+fn guard_clause(cx: &Context, if_ex: &syn::ExprIf) -> Option<usize> {
+    let recursion = cx.recursion.as_ref()?;
+    if if_ex.else_branch.is_some()
+        || !if_ex.then_branch.stmts.iter().any(diverges)
+        || !if_reassigned_vars(cx, if_ex).is_empty()
+    {
+        return None;
+    }
+    match recursion::guard_threshold(&if_ex.cond, &recursion.arg)? {
+        (k, false) => Some(k),
+        (_, true) => None,
+    }
+}
+
+/// Translate a `for` loop over a range, returning the variables it reassigns along with the loop.
+fn for_loop_as_ast(
+    cx: &mut Context,
+    for_loop: &syn::ExprForLoop,
+) -> syn::Result<(Vec<ast::Ident>, ast::Expr)> {
+    let syn::Expr::Range(syn::ExprRange {
+        start: Some(start),
+        limits,
+        end: Some(end),
+        ..
+    }) = &*for_loop.expr
+    else {
+        return Err(syn::Error::new(
+            for_loop.expr.span(),
+            "expected a `for` loop over a range (e.g. `for i in 0..n`)",
+        ));
+    };
+    let var = match pat_as_ast(&for_loop.pat)? {
+        ast::Pattern::Ident(name) => name,
+        ast::Pattern::Wildcard => cx.fresh("i"),
+        _ => {
+            return Err(syn::Error::new(
+                for_loop.pat.span(),
+                "expected a variable in `for` loop",
+            ))
+        }
+    };
+    let start = expr_as_ast(cx, start)?;
+    let mut end = expr_as_ast(cx, end)?;
+    if let syn::RangeLimits::Closed(_) = limits {
+        end = ast::Expr::BinOp {
+            op: ast::BinOp::Add,
+            lhs: Box::new(end),
+            rhs: Box::new(ast::Expr::Nat(1)),
+            loc: cx.loc(limits.span()),
+        };
+    }
+
+    let (state, body) = loop_body_as_ast(cx, &for_loop.body, for_loop.span())?;
+    let helper = cx.fresh("loop");
+    let expr = ast::Expr::ForRange {
+        func: format!("{}_{helper}", cx.func),
+        var,
+        start: Box::new(start),
+        end: Box::new(end),
+        state: state.clone(),
+        body: Box::new(body),
+        loc: cx.loc(for_loop.span()),
+    };
+    Ok((state, expr))
+}
+
+/// Translate a `while` loop, returning the variables it reassigns along with the loop.
+///
+/// The loop must be annotated with a measure which decreases on each iteration, since the
+/// `Fixpoint` it's translated to must terminate.
+fn while_as_ast(
+    cx: &mut Context,
+    while_loop: &syn::ExprWhile,
+) -> syn::Result<(Vec<ast::Ident>, ast::Expr)> {
+    let Some(measure) = recursion::measure(&while_loop.attrs)? else {
+        return Err(syn::Error::new(
+            while_loop.while_token.span(),
+            "can't prove that this loop terminates; annotate it with a measure which decreases \
+             on each iteration (e.g. `#[roq::decreasing(n - i)]`)",
+        ));
+    };
+    reject_jumps(|jumps| jumps.visit_expr(&while_loop.cond))?;
+    let cond = expr_as_ast(cx, &while_loop.cond)?;
+    let measure = expr_as_ast(cx, &measure)?;
+
+    let (state, body) = loop_body_as_ast(cx, &while_loop.body, while_loop.span())?;
+    let helper = cx.fresh("loop");
+    let expr = ast::Expr::While {
+        func: format!("{}_{helper}", cx.func),
+        cond: Box::new(cond),
+        measure: Box::new(measure),
+        state: state.clone(),
+        body: Box::new(body),
+        loc: cx.loc(while_loop.span()),
+    };
+    Ok((state, expr))
+}
+
+/// Translate the body of a loop, returning the variables declared outside it which it reassigns.
+///
+/// The body is translated as a block ending with a tuple of those variables, so that it evaluates
+/// to their values at the end of each iteration.
+fn loop_body_as_ast(
+    cx: &mut Context,
+    block: &syn::Block,
+    span: Span,
+) -> syn::Result<(Vec<ast::Ident>, ast::Expr)> {
+    reject_jumps(|jumps| jumps.visit_block(block))?;
+
+    let state = reassigned_vars(cx, block);
+    if state.is_empty() {
+        return Err(syn::Error::new(
+            span,
+            "expected the loop to assign to a variable declared outside it",
+        ));
+    }
+    let body = stateful_block_as_ast(cx, block, &state)?;
+    Ok((state, body))
+}
+
+/// Translate an `if` statement whose branches reassign variables declared outside it.
+///
+/// Given the `state` of variables the branches reassign, each branch evaluates to their values at
+/// its end, as does a missing `else` branch. Without `state`, the `if` ends the enclosing block,
+/// so each branch can reassign its variables before evaluating to its own value.
+fn if_stmt_as_ast(
+    cx: &mut Context,
+    if_ex: &syn::ExprIf,
+    state: Option<&[ast::Ident]>,
+) -> syn::Result<ast::Expr> {
+    let branch_as_ast = |cx: &mut Context, block: &syn::Block| match state {
+        Some(state) => stateful_block_as_ast(cx, block, state),
+        None => {
+            let outer = cx.assignable.clone();
+            let expr = stmts_as_ast(cx, block);
+            cx.assignable = outer;
+            expr
+        }
+    };
+    if_as_ast(cx, if_ex, |cx| {
+        let then_branch = branch_as_ast(cx, &if_ex.then_branch)?;
+        let else_branch = match if_ex.else_branch.as_ref().map(|(_else, expr)| &**expr) {
+            None => state.map_or(ast::Expr::Tt, state_expr),
+            Some(syn::Expr::If(else_if)) => if_stmt_as_ast(cx, else_if, state)?,
+            Some(syn::Expr::Block(block)) => branch_as_ast(cx, &block.block)?,
+            Some(expr) => return Err(syn::Error::new(expr.span(), "unsupported `else` branch")),
+        };
+        Ok((then_branch, else_branch))
+    })
+}
+
+/// Translate a block which reassigns the variables in `state`, as a block ending with a tuple of
+/// them, so that it evaluates to their values at its end.
+fn stateful_block_as_ast(
+    cx: &mut Context,
+    block: &syn::Block,
+    state: &[ast::Ident],
+) -> syn::Result<ast::Expr> {
+    let idents = state.iter().map(|name| syn::Ident::new(name, block.span()));
+    let mut body = block.clone();
+    body.stmts
+        .push(syn::Stmt::Expr(syn::parse_quote!((#(#idents),*)), None));
+
+    // Only the state can be reassigned in the block, besides the variables it declares.
+    let outer = std::mem::replace(&mut cx.assignable, state.to_vec());
+    let body = stmts_as_ast(cx, &body);
+    cx.assignable = outer;
+    body
+}
+
+/// The value of the variables in `state`: the variable itself, or a tuple of them.
+fn state_expr(state: &[ast::Ident]) -> ast::Expr {
+    match state {
+        [ident] => ast::Expr::Var(ident.clone()),
+        _ => ast::Expr::Tuple(state.iter().cloned().map(ast::Expr::Var).collect()),
+    }
+}
+
+/// The pattern which rebinds the variables in `state` to the value given by `state_expr`.
+fn state_pattern(state: Vec<ast::Ident>) -> ast::Pattern {
+    match state.as_slice() {
+        [ident] => ast::Pattern::Ident(ident.clone()),
+        _ => ast::Pattern::Tuple(state.into_iter().map(ast::Pattern::Ident).collect()),
+    }
+}
+
+/// The variables which the statements of `block` reassign, other than those it declares itself,
+/// in order of first assignment. These are the variables a loop with this body needs to thread
+/// through its iterations.
+fn reassigned_vars(cx: &Context, block: &syn::Block) -> Vec<ast::Ident> {
+    let mut declared = vec![];
+    let mut assigned = vec![];
+    for stmt in &block.stmts {
+        let targets = match stmt {
+            syn::Stmt::Local(local) => {
+                let pat = match &local.pat {
+                    syn::Pat::Type(syn::PatType { pat, .. }) => pat,
+                    pat => pat,
+                };
+                let targets = match &local.init {
+                    Some(init) => mut_args(&init.expr)
+                        .into_iter()
+                        .map(assigned_ident)
+                        .collect(),
+                    None => vec![],
+                };
+                if let Ok(pattern) = pat_as_ast(pat) {
+                    declared.extend(bound_vars(&pattern));
+                }
+                targets
+            }
+            syn::Stmt::Expr(syn::Expr::Assign(assign), _) => vec![assigned_ident(&assign.left)],
+            syn::Stmt::Expr(syn::Expr::Binary(syn::ExprBinary { left, op, .. }), _)
+                if compound_assign_op(op).is_some() =>
+            {
+                vec![assigned_ident(left)]
+            }
+            syn::Stmt::Expr(syn::Expr::MethodCall(call), _)
+                if call.method == "push" && call.args.len() == 1 =>
+            {
+                vec![assigned_ident(&call.receiver)]
+            }
+            syn::Stmt::Expr(syn::Expr::ForLoop(syn::ExprForLoop { body, .. }), _)
+            | syn::Stmt::Expr(syn::Expr::While(syn::ExprWhile { body, .. }), _) => {
+                reassigned_vars(cx, body).into_iter().map(Some).collect()
+            }
+            syn::Stmt::Expr(syn::Expr::If(if_ex), _) => if_reassigned_vars(cx, if_ex)
+                .into_iter()
+                .map(Some)
+                .collect(),
+            syn::Stmt::Expr(expr, _) => mut_args(expr).into_iter().map(assigned_ident).collect(),
+            _ => continue,
+        };
+        for ident in targets.into_iter().flatten() {
+            if cx.assignable.contains(&ident)
+                && !declared.contains(&ident)
+                && !assigned.contains(&ident)
+            {
+                assigned.push(ident);
+            }
+        }
+    }
+    assigned
+}
+
+/// The variables which any branch of an `if` reassigns, other than those they declare themselves,
+/// in order of first assignment.
+fn if_reassigned_vars(cx: &Context, if_ex: &syn::ExprIf) -> Vec<ast::Ident> {
+    let mut assigned = reassigned_vars(cx, &if_ex.then_branch);
+    let else_vars = match if_ex.else_branch.as_ref().map(|(_else, expr)| &**expr) {
+        Some(syn::Expr::If(else_if)) => if_reassigned_vars(cx, else_if),
+        Some(syn::Expr::Block(block)) => reassigned_vars(cx, &block.block),
+        _ => vec![],
+    };
+    for ident in else_vars {
+        if !assigned.contains(&ident) {
+            assigned.push(ident);
+        }
+    }
+    assigned
+}
+
+/// Check that a loop doesn't jump out of an iteration, with `break`, `continue`, `return` or `?`,
+/// within the parts of it which `visit` visits.
+fn reject_jumps(visit: impl FnOnce(&mut Jumps)) -> syn::Result<()> {
+    let mut jumps = Jumps(None);
+    visit(&mut jumps);
+    match jumps.0 {
+        Some(span) => Err(syn::Error::new(
+            span,
+            "`break`, `continue`, `return` and `?` aren't supported in loops",
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Finds the first `break`, `continue`, `return` or `?` in a loop.
+struct Jumps(Option<Span>);
+
+impl<'ast> Visit<'ast> for Jumps {
+    fn visit_expr_break(&mut self, node: &'ast syn::ExprBreak) {
+        self.0.get_or_insert(node.span());
+    }
+
+    fn visit_expr_continue(&mut self, node: &'ast syn::ExprContinue) {
+        self.0.get_or_insert(node.span());
+    }
+
+    fn visit_expr_return(&mut self, node: &'ast syn::ExprReturn) {
+        self.0.get_or_insert(node.span());
+    }
+
+    fn visit_expr_try(&mut self, node: &'ast syn::ExprTry) {
+        self.0.get_or_insert(node.span());
+    }
+
+    // Closures can't jump out of the loop.
+    fn visit_expr_closure(&mut self, _node: &'ast syn::ExprClosure) {}
+}
+
+/// The variable assigned to by `target`, if it's a single identifier, a field of one (or of a
+/// field of one, and so on), or what a `&mut` reference in one refers to.
+fn assigned_ident(target: &syn::Expr) -> Option<ast::Ident> {
+    match target {
+        syn::Expr::Path(path) => path.path.get_ident().map(|ident| ident.to_string()),
+        syn::Expr::Field(syn::ExprField { base: expr, .. })
+        | syn::Expr::Paren(syn::ExprParen { expr, .. })
+        | syn::Expr::Unary(syn::ExprUnary {
+            op: syn::UnOp::Deref(_),
+            expr,
+            ..
+        }) => assigned_ident(expr),
+        _ => None,
+    }
+}
+
+/// The new value of the variable assigned to by `target`, given the `value` assigned. Assigning
+/// to a field updates the record it's in, which is itself assigned to its `target`.
+fn assigned_value(
+    cx: &mut Context,
+    target: &syn::Expr,
+    value: ast::Expr,
+) -> syn::Result<ast::Expr> {
+    match target {
+        syn::Expr::Field(syn::ExprField {
+            base,
+            member: syn::Member::Named(field),
+            ..
+        }) => {
+            let value = ast::Expr::SetField {
+                base: Box::new(expr_as_ast(cx, base)?),
+                field: field.to_string(),
+                value: Box::new(value),
+                loc: cx.loc(target.span()),
+            };
+            assigned_value(cx, base, value)
+        }
+        syn::Expr::Field(field) => Err(syn::Error::new(
+            field.member.span(),
+            "expected a named field on the left of an assignment",
+        )),
+        syn::Expr::Paren(syn::ExprParen { expr, .. })
+        | syn::Expr::Unary(syn::ExprUnary {
+            op: syn::UnOp::Deref(_),
+            expr,
+            ..
+        }) => assigned_value(cx, expr, value),
+        _ => Ok(value),
+    }
+}
+
+/// The variables passed by `&mut` to a call, if `expr` is one.
+fn mut_args(expr: &syn::Expr) -> Vec<&syn::Expr> {
+    let syn::Expr::Call(call) = expr else {
+        return vec![];
+    };
+    call.args
+        .iter()
+        .filter_map(|arg| match arg {
+            syn::Expr::Reference(syn::ExprReference {
+                mutability: Some(_),
+                expr,
+                ..
+            }) => Some(&**expr),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Translate a call which is passed `&mut` variables, returning them along with the call. The
+/// function called takes their values, and returns its result followed by their new values.
+fn state_call_as_ast(
+    cx: &mut Context,
+    expr: &syn::Expr,
+) -> syn::Result<(Vec<ast::Ident>, ast::Expr)> {
+    let syn::Expr::Call(call) = expr else {
+        unreachable!("only calls have `&mut` arguments");
+    };
+    let mut state = vec![];
+    for arg in mut_args(expr) {
+        // An `&mut` argument is passed on by reborrowing it, as `&mut *x`.
+        let var = match arg {
+            syn::Expr::Unary(syn::ExprUnary {
+                op: syn::UnOp::Deref(_),
+                expr,
+                ..
+            }) => expr,
+            arg => arg,
+        };
+        let ident = match var {
+            syn::Expr::Path(path) => path.path.get_ident().map(|ident| ident.to_string()),
+            _ => None,
+        };
+        let Some(ident) = ident else {
+            return Err(syn::Error::new(
+                arg.span(),
+                "expected a variable to be passed by `&mut`",
+            ));
+        };
+        if state.contains(&ident) {
+            return Err(syn::Error::new(
+                arg.span(),
+                "can't pass the same variable by `&mut` twice",
+            ));
+        }
+        state.push(assigned_var(cx, arg)?);
+    }
+
+    // The call is translated as if each variable was passed by value.
+    let mut call = call.clone();
+    for arg in &mut call.args {
+        if let syn::Expr::Reference(syn::ExprReference {
+            mutability: Some(_),
+            expr,
+            ..
+        }) = arg
+        {
+            *arg = (**expr).clone();
+        }
+    }
+    Ok((state, expr_as_ast(cx, &syn::Expr::Call(call))?))
+}
+
+/// Check that the target of an assignment is a variable which can be rebound, and return it.
+fn assigned_var(cx: &Context, target: &syn::Expr) -> syn::Result<ast::Ident> {
+    let Some(ident) = assigned_ident(target) else {
+        return Err(syn::Error::new(
+            target.span(),
+            "expected a variable on the left of an assignment",
+        ));
+    };
+    if !cx.assignable.contains(&ident) {
+        return Err(syn::Error::new(
+            target.span(),
+            "can't assign to a variable unless it's declared `mut` in the current block",
+        ));
+    }
+    Ok(ident)
 }
@@ -0,0 +1,319 @@
+use roq_core::ast;
+use roq_core::typing::Typing;
+use syn::spanned::Spanned;
+
+use crate::func::{self, method_as_ast, method_item, Options};
+use crate::ty::{check_type_names, default_numbers, use_type, TypeScope};
+
+/// A translated trait or `impl` block, along with the other items it refers to.
+pub struct Translation {
+    pub statements: Vec<ast::Statement>,
+
+    /// The functions an `impl` block's methods are translated to, which are defined before its
+    /// statements once they're elaborated.
+    pub functions: Vec<func::Translation>,
+
+    pub callees: Vec<syn::Path>,
+    pub types: Vec<syn::Path>,
+    pub requires: Vec<ast::Ident>,
+}
+
+/// Convert a trait to a Coq `Class` over the type implementing it, `Self`, with a method for each
+/// of the trait's.
+pub fn class_as_ast(source: &syn::ItemTrait) -> syn::Result<Translation> {
+    if !source.generics.params.is_empty() || source.generics.where_clause.is_some() {
+        return Err(syn::Error::new(
+            source.generics.span(),
+            "can't generate Coq `Class` for a generic trait",
+        ));
+    }
+    if !source.supertraits.is_empty() {
+        return Err(syn::Error::new(
+            source.supertraits.span(),
+            "can't generate Coq `Class` for a trait with supertraits",
+        ));
+    }
+
+    let name = source.ident.to_string();
+    let scope = TypeScope {
+        numbers: default_numbers()?,
+        self_ty: Some(ast::Ty::Var("Self".into())),
+        ..TypeScope::default()
+    };
+    let mut types = vec![];
+    let mut typing = Typing::default();
+    let mut methods = vec![];
+    for item in &source.items {
+        let syn::TraitItem::Fn(method) = item else {
+            return Err(syn::Error::new(
+                item.span(),
+                "expected a method, other trait items aren't supported",
+            ));
+        };
+        if let Some(default) = &method.default {
+            return Err(syn::Error::new(
+                default.span(),
+                "default methods aren't supported",
+            ));
+        }
+        let sig = &method.sig;
+        if !sig.generics.params.is_empty() {
+            return Err(syn::Error::new(
+                sig.generics.span(),
+                "can't generate Coq `Class` for a trait with generic methods",
+            ));
+        }
+
+        // A method becomes a function taking `self`, if it has a receiver, then its arguments.
+        let mut args = vec![];
+        for arg in &sig.inputs {
+            let ty = match arg {
+                syn::FnArg::Receiver(receiver) => &receiver.ty,
+                syn::FnArg::Typed(typed) => &typed.ty,
+            };
+            args.push(use_type(&mut types, &scope, ty)?);
+        }
+        let ret = match &sig.output {
+            syn::ReturnType::Default => {
+                return Err(syn::Error::new(
+                    sig.span(),
+                    "can't generate Coq `Class` for a method without a return type",
+                ))
+            }
+            syn::ReturnType::Type(_, ty) => use_type(&mut types, &scope, ty)?,
+        };
+        let ty = ast::Ty::arrow(args, ret);
+        typing.record(&ty);
+        methods.push(ast::Binder {
+            name: ast::Class::method(&name, &sig.ident.to_string()),
+            ty,
+            implicit: false,
+        });
+    }
+
+    let class = ast::Class {
+        name,
+        args: vec![ast::Binder {
+            name: "Self".into(),
+            ty: ast::Ty::Type,
+            implicit: false,
+        }],
+        methods,
+    };
+    check_type_names(&types)?;
+    Ok(Translation {
+        statements: vec![ast::Statement::Class(class)],
+        functions: vec![],
+        callees: vec![],
+        types,
+        requires: typing.requires,
+    })
+}
+
+/// Convert an implementation of a `#[class]` trait to a Coq `Instance` of its class, and a
+/// function for each of its methods, `Trait_Type_method`, which takes `self` as its first
+/// argument.
+pub fn instance_as_ast(source: &syn::ItemImpl, options: &Options) -> syn::Result<Translation> {
+    let Some((None, trait_path, _)) = &source.trait_ else {
+        return Err(syn::Error::new(
+            source.self_ty.span(),
+            "expected an implementation of a trait, like `impl Trait for Type`",
+        ));
+    };
+    if !source.generics.params.is_empty() || source.generics.where_clause.is_some() {
+        return Err(syn::Error::new(
+            source.generics.span(),
+            "can't generate Coq `Instance` for a generic `impl` block",
+        ));
+    }
+    let syn::Type::Path(self_path) = &*source.self_ty else {
+        return Err(syn::Error::new(
+            source.self_ty.span(),
+            "expected a user-defined type, like a `#[record]` struct",
+        ));
+    };
+
+    let class = trait_path.segments.last().unwrap().ident.to_string();
+    let ty_name = self_path.path.segments.last().unwrap().ident.to_string();
+    let name = ast::Instance::name(&class, &ty_name);
+
+    let scope = TypeScope {
+        numbers: options.numbers,
+        ..TypeScope::default()
+    };
+    let mut types = vec![];
+    let ty = use_type(&mut types, &scope, &source.self_ty)?;
+
+    let mut functions = vec![];
+    let mut callees = vec![];
+    let mut methods = vec![];
+    for item in &source.items {
+        let syn::ImplItem::Fn(method) = item else {
+            return Err(syn::Error::new(
+                item.span(),
+                "expected a method, other items aren't supported",
+            ));
+        };
+        let func = method_item(method);
+        let method_name = method.sig.ident.to_string();
+        let definition = ast::Class::method(&name, &method_name);
+        let translation = method_as_ast(&func, &source.self_ty, definition.clone(), options)?;
+        callees.extend(translation.callees.iter().cloned());
+        types.extend(translation.types.iter().cloned());
+        functions.push(translation);
+        methods.push((
+            ast::Class::method(&class, &method_name),
+            ast::Expr::Var(definition),
+        ));
+    }
+
+    let instance = ast::Statement::Instance(ast::Instance {
+        name,
+        args: vec![],
+        class: ast::Ty::Apply {
+            func: class,
+            args: vec![ty],
+        },
+        methods,
+    });
+    check_type_names(&types)?;
+    Ok(Translation {
+        statements: vec![instance],
+        functions,
+        callees,
+        types,
+        requires: vec![],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_snapshot;
+
+    fn test_class(input: &str) -> syn::Result<ast::Vernacular> {
+        let translation = class_as_ast(&syn::parse_str(input).unwrap())?;
+        Ok(ast::Vernacular {
+            statements: translation.statements,
+        })
+    }
+
+    fn test_instance(input: &str) -> syn::Result<ast::Vernacular> {
+        let translation = instance_as_ast(&syn::parse_str(input).unwrap(), &Options::default())?;
+        let mut vernacular = ast::Vernacular::default();
+        for function in translation.functions {
+            let mut typing = Typing::default();
+            let statement = function.function.elaborate(&mut typing).unwrap();
+            vernacular.extend(typing.auxiliary);
+            vernacular.push(statement);
+        }
+        vernacular.extend(translation.statements);
+        Ok(vernacular)
+    }
+
+    #[test]
+    fn test_class_methods() {
+        let class = test_class(
+            "trait Fee {
+                fn fee(&self, amount: u64) -> u64;
+                fn waived(self) -> bool;
+                fn zero() -> Self;
+            }",
+        )
+        .unwrap();
+        assert_snapshot!(class, @r###"
+        Class Fee (Self: Type) := {
+        	Fee_fee: Self -> nat -> nat;
+        	Fee_waived: Self -> bool;
+        	Fee_zero: Self
+        }.
+        "###);
+    }
+
+    #[test]
+    fn test_class_errors() {
+        let errors = [
+            "trait Fee<T> { fn fee(&self) -> T; }",
+            "trait Fee: Clone { fn fee(&self) -> u64; }",
+            "trait Fee { const RATE: u64; }",
+            "trait Fee { fn fee(&self) -> u64 { 0 } }",
+            "trait Fee { fn fee<T>(&self, x: T) -> u64; }",
+            "trait Fee { fn charge(&self); }",
+        ]
+        .map(|input| test_class(input).err().unwrap().to_string());
+        assert_snapshot!(errors.join("\n"), @r###"
+        can't generate Coq `Class` for a generic trait
+        can't generate Coq `Class` for a trait with supertraits
+        expected a method, other trait items aren't supported
+        default methods aren't supported
+        can't generate Coq `Class` for a trait with generic methods
+        can't generate Coq `Class` for a method without a return type
+        "###);
+    }
+
+    #[test]
+    fn test_instance_methods() {
+        let instance = test_instance(
+            "impl Fee for Flat {
+                fn fee(&self, amount: u64) -> u64 {
+                    if amount == 0 { 0 } else { self.fee }
+                }
+                fn waived(self) -> bool {
+                    self.fee == 0
+                }
+                fn zero() -> Self {
+                    Flat { fee: 0 }
+                }
+            }",
+        )
+        .unwrap();
+        assert_snapshot!(instance, @r###"
+        Definition Fee_Flat_fee (self: Flat) (amount: nat) : nat :=
+        	match (Nat.eqb amount 0)
+        	 with
+        	| true =>
+        		0
+        	| false =>
+        		(Flat_fee self)
+        	end
+        .
+
+
+        Definition Fee_Flat_waived (self: Flat) : bool :=
+        	(Nat.eqb (Flat_fee self)
+        	 0)
+        .
+
+
+        Definition Fee_Flat_zero : Flat :=
+        	{| Flat_fee := 0 |}
+        .
+
+
+        Instance Fee_Flat : Fee Flat := {
+        	Fee_fee := Fee_Flat_fee;
+        	Fee_waived := Fee_Flat_waived;
+        	Fee_zero := Fee_Flat_zero
+        }.
+        "###);
+    }
+
+    #[test]
+    fn test_instance_errors() {
+        let errors = [
+            "impl Flat { fn fee(&self) -> u64 { 0 } }",
+            "impl<T> Fee for Wrapper<T> { fn fee(&self) -> u64 { 0 } }",
+            "impl Fee for (u64, u64) { fn fee(&self) -> u64 { 0 } }",
+            "impl Fee for Flat { const RATE: u64 = 0; }",
+            "impl Fee for Flat { fn fee(&mut self) -> u64 { 0 } }",
+        ]
+        .map(|input| test_instance(input).err().unwrap().to_string());
+        assert_snapshot!(errors.join("\n"), @r###"
+        expected an implementation of a trait, like `impl Trait for Type`
+        can't generate Coq `Instance` for a generic `impl` block
+        expected a user-defined type, like a `#[record]` struct
+        expected a method, other items aren't supported
+        mutable references aren't supported
+        "###);
+    }
+}
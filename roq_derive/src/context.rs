@@ -0,0 +1,125 @@
+use proc_macro2::Span;
+use roq_core::ast;
+use roq_core::typing::TypeError;
+
+use crate::ty::TypeScope;
+
+/// State threaded through the translation of a single Rust function.
+#[derive(Debug, Default)]
+pub struct Context {
+    /// The name of the function being translated, used to recognise recursive calls.
+    pub func: ast::Ident,
+
+    /// If the function is recursive, the argument it is structurally decreasing on.
+    pub recursion: Option<Recursion>,
+
+    /// The model of unsigned integers, and the function's type parameters.
+    pub scope: TypeScope,
+
+    /// The name of the type whose inherent method is being translated, if it is one, so that its
+    /// other methods can be called on `self` or `Self`.
+    pub methods_of: Option<ast::Ident>,
+
+    /// Paths to the other `#[definition]` functions called by this one, in order of first use.
+    pub callees: Vec<syn::Path>,
+
+    /// Paths to the user-defined types used by this function, in order of first use.
+    pub types: Vec<syn::Path>,
+
+    /// Variables which can be reassigned: the `mut` variables declared in the innermost block, or
+    /// the function's `mut` arguments at the top level.
+    pub assignable: Vec<ast::Ident>,
+
+    /// The values `?` was applied to in the statement being translated, along with the fresh
+    /// variables standing for what's in them, which are bound before the statement.
+    pub tries: Vec<(ast::Ident, ast::Expr, ast::Loc)>,
+
+    /// The source spans referred to by each `ast::Loc`, for reporting type errors.
+    pub spans: Vec<Span>,
+
+    /// Counter used to generate fresh variable names.
+    pub fresh: usize,
+}
+
+impl Context {
+    pub fn new(func: ast::Ident) -> Context {
+        Context {
+            func,
+            recursion: None,
+            scope: TypeScope::default(),
+            methods_of: None,
+            callees: vec![],
+            types: vec![],
+            assignable: vec![],
+            tries: vec![],
+            spans: vec![],
+            fresh: 0,
+        }
+    }
+
+    /// Generate a variable name which can't clash with any Rust identifier.
+    pub fn fresh(&mut self, base: &str) -> ast::Ident {
+        self.fresh += 1;
+        format!("{base}'{}", self.fresh)
+    }
+
+    /// Refer to a span from the AST, so that type errors can be reported there.
+    pub fn loc(&mut self, span: Span) -> ast::Loc {
+        self.spans.push(span);
+        ast::Loc(self.spans.len() as u32 - 1)
+    }
+
+    /// Convert a type error to a compile error, at `fallback` if it doesn't have a location.
+    pub fn type_error(&self, err: TypeError, fallback: Span) -> syn::Error {
+        let span = err.loc.map_or(fallback, |loc| self.spans[loc.0 as usize]);
+        syn::Error::new(span, err)
+    }
+
+    /// Record a call to another `#[definition]` function, returning the name it's applied by until
+    /// it's elaborated (see `callee_name`).
+    pub fn add_callee(&mut self, path: &syn::Path) -> ast::Ident {
+        if !self.callees.contains(path) {
+            self.callees.push(path.clone());
+        }
+        callee_name(path)
+    }
+
+    /// Record a use of a user-defined type.
+    pub fn add_type(&mut self, path: &syn::Path) {
+        if !self.types.contains(path) {
+            self.types.push(path.clone());
+        }
+    }
+}
+
+/// The name a call to the function at `path` applies it by until it's elaborated, when it's
+/// replaced with the function's Coq name: the path itself, like `util::max`, so that functions of
+/// the same name in different modules aren't confused.
+pub fn callee_name(path: &syn::Path) -> ast::Ident {
+    let segments = path
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string());
+    segments.collect::<Vec<_>>().join("::")
+}
+
+/// The decreasing argument of a recursive function, and how much of it is in scope.
+#[derive(Debug)]
+pub struct Recursion {
+    /// Position of the decreasing argument in the function's argument list.
+    pub index: usize,
+
+    /// Name of the decreasing argument.
+    pub arg: ast::Ident,
+
+    /// How many successors have been peeled off the argument by enclosing guards. Within a
+    /// guard checking `arg >= k`, the predecessors `arg - 1` through `arg - k` are bound.
+    pub depth: usize,
+}
+
+impl Recursion {
+    /// The name bound to `arg - n` within a guard, for `1 <= n <= depth`.
+    pub fn predecessor(&self, n: usize) -> ast::Ident {
+        format!("{}{}", self.arg, "'".repeat(n))
+    }
+}
@@ -1,21 +1,88 @@
 use roq_core::ast;
 use syn::spanned::Spanned;
 
-use crate::block::block_as_ast;
+use crate::block::{block_as_ast, strip_returns};
+use crate::context::Context;
+use crate::func::method_name;
+use crate::inductive::{constructor_of, prelude_constructor};
+use crate::pat::{is_irrefutable, mutable_vars, pat_as_ast};
+use crate::recursion;
+use crate::ty::{is_signed_int, use_type};
 
-pub fn expr_as_ast(source: &syn::Expr) -> syn::Result<ast::Expr> {
+pub fn expr_as_ast(cx: &mut Context, source: &syn::Expr) -> syn::Result<ast::Expr> {
     match source {
         // Traverse parenthesized expressions.
-        syn::Expr::Paren(syn::ExprParen { expr, .. }) => expr_as_ast(expr),
+        syn::Expr::Paren(syn::ExprParen { expr, .. }) => expr_as_ast(cx, expr),
 
         // Traverse block expressions.
-        syn::Expr::Block(syn::ExprBlock { block, .. }) => block_as_ast(block),
+        syn::Expr::Block(syn::ExprBlock { block, .. }) => block_as_ast(cx, block),
 
-        // Match integer literals.
+        // Match integer literals, which are `nat`s unless they have a signed suffix.
         syn::Expr::Lit(syn::ExprLit {
             lit: syn::Lit::Int(int),
             ..
-        }) => Ok(ast::Expr::Nat(int.base10_parse().unwrap())),
+        }) => {
+            if is_signed_int(int.suffix()) {
+                Ok(ast::Expr::Z(int.base10_parse()?))
+            } else {
+                Ok(ast::Expr::Nat(int.base10_parse()?))
+            }
+        }
+
+        // Match negation.
+        syn::Expr::Unary(syn::ExprUnary {
+            op: syn::UnOp::Neg(_),
+            expr,
+            ..
+        }) => {
+            // Negative literals are written directly.
+            if let syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Int(int),
+                ..
+            }) = &**expr
+            {
+                let value = format!("-{}", int.base10_digits())
+                    .parse()
+                    .map_err(|err| syn::Error::new(int.span(), err))?;
+                return Ok(ast::Expr::Z(value));
+            }
+
+            Ok(ast::Expr::UnOp {
+                op: ast::UnOp::Neg,
+                operand: Box::new(expr_as_ast(cx, expr)?),
+                loc: cx.loc(source.span()),
+            })
+        }
+
+        // References are erased, so dereferencing does nothing.
+        syn::Expr::Unary(syn::ExprUnary {
+            op: syn::UnOp::Deref(_),
+            expr,
+            ..
+        })
+        | syn::Expr::Reference(syn::ExprReference {
+            mutability: None,
+            expr,
+            ..
+        }) => expr_as_ast(cx, expr),
+
+        // Calls which are passed `&mut` variables rebind them, so they must be statements.
+        syn::Expr::Reference(reference) => Err(syn::Error::new(
+            reference.span(),
+            "`&mut` is only supported on the arguments of a call which is a statement, or the \
+             value of a `let`",
+        )),
+
+        // Match logical (or bitwise) negation.
+        syn::Expr::Unary(syn::ExprUnary {
+            op: syn::UnOp::Not(_),
+            expr,
+            ..
+        }) => Ok(ast::Expr::UnOp {
+            op: ast::UnOp::Not,
+            operand: Box::new(expr_as_ast(cx, expr)?),
+            loc: cx.loc(source.span()),
+        }),
 
         // Match boolean literals.
         syn::Expr::Lit(syn::ExprLit {
@@ -23,6 +90,22 @@ pub fn expr_as_ast(source: &syn::Expr) -> syn::Result<ast::Expr> {
             ..
         }) => Ok(ast::Expr::Bool(boolean.value)),
 
+        // Match `None`, however it's qualified.
+        syn::Expr::Path(syn::ExprPath {
+            qself: None, path, ..
+        }) if prelude_constructor(path).is_some() => {
+            Ok(ast::Expr::Var(prelude_constructor(path).unwrap().into()))
+        }
+
+        // Match unit variants of enums, like `Shape::Empty`.
+        syn::Expr::Path(syn::ExprPath {
+            qself: None, path, ..
+        }) if constructor_of(path).is_some() => {
+            let (inductive, constructor) = constructor_of(path).unwrap();
+            cx.add_type(&inductive);
+            Ok(ast::Expr::Var(constructor))
+        }
+
         // Match variables.
         syn::Expr::Path(syn::ExprPath {
             path: syn::Path { segments, .. },
@@ -38,65 +121,336 @@ pub fn expr_as_ast(source: &syn::Expr) -> syn::Result<ast::Expr> {
             )),
         },
 
+        // Match recursive calls.
+        syn::Expr::Call(call) if recursion::is_path_to(&call.func, &cx.func) => {
+            let args = call
+                .args
+                .iter()
+                .map(|arg| expr_as_ast(cx, arg))
+                .collect::<syn::Result<Vec<_>>>()?;
+
+            // The decreasing argument must be one of the predecessors bound by an enclosing guard,
+            // unless the function was given a measure instead.
+            let Some(recursion) = cx.recursion.as_ref() else {
+                return Ok(ast::Expr::Apply {
+                    func: cx.func.clone(),
+                    args,
+                });
+            };
+            let Some(arg) = args.get(recursion.index) else {
+                return Err(syn::Error::new(
+                    call.span(),
+                    format!(
+                        "expected the decreasing argument `{}` in this recursive call",
+                        recursion.arg
+                    ),
+                ));
+            };
+            let guarded =
+                (1..=recursion.depth).any(|n| *arg == ast::Expr::Var(recursion.predecessor(n)));
+            if !guarded {
+                return Err(syn::Error::new(
+                    call.args[recursion.index].span(),
+                    format!(
+                        "recursive call on `{arg} - k` must be guarded by a check that `{arg} >= k`",
+                        arg = recursion.arg
+                    ),
+                ));
+            }
+
+            Ok(ast::Expr::Apply {
+                func: cx.func.clone(),
+                args,
+            })
+        }
+
+        // Match `Default::default()`, the method of the class `Default`.
+        syn::Expr::Call(call) if call.args.is_empty() && is_default(cx, &call.func) => {
+            Ok(ast::Expr::Var(ast::Class::method("Default", "default")))
+        }
+
+        // Match calls to other `#[definition]` functions.
+        syn::Expr::Call(call) => {
+            let syn::Expr::Path(syn::ExprPath {
+                qself: None, path, ..
+            }) = &*call.func
+            else {
+                return Err(syn::Error::new(
+                    call.func.span(),
+                    "expected the name of a `#[definition]` function",
+                ));
+            };
+            // Tuple variants of enums are applied like functions, but are defined by their type.
+            let func = match (prelude_constructor(path), constructor_of(path)) {
+                (Some(constructor), _) => constructor.into(),
+                (None, Some((inductive, constructor))) => {
+                    cx.add_type(&inductive);
+                    constructor
+                }
+                (None, None) => {
+                    let method = method_path(cx, path);
+                    let path = method.as_ref().unwrap_or(path);
+                    if method.is_some() && path.segments.last().unwrap().ident == cx.func {
+                        return Err(syn::Error::new(
+                            call.func.span(),
+                            "methods can't call themselves",
+                        ));
+                    }
+                    cx.add_callee(path)
+                }
+            };
+
+            let args = call
+                .args
+                .iter()
+                .map(|arg| expr_as_ast(cx, arg))
+                .collect::<syn::Result<Vec<_>>>()?;
+            Ok(ast::Expr::Apply { func, args })
+        }
+
+        // Within a guard, refer to the predecessors of the decreasing argument by name.
+        syn::Expr::Binary(_)
+            if cx.recursion.as_ref().is_some_and(|recursion| {
+                recursion::predecessor_of(source, &recursion.arg)
+                    .is_some_and(|n| n <= recursion.depth)
+            }) =>
+        {
+            let recursion = cx.recursion.as_ref().unwrap();
+            let n = recursion::predecessor_of(source, &recursion.arg).unwrap();
+            Ok(ast::Expr::Var(recursion.predecessor(n)))
+        }
+
         // Match binary arithmetic.
         syn::Expr::Binary(syn::ExprBinary {
             left, right, op, ..
         }) => {
-            let lhs = expr_as_ast(left)?;
-            let rhs = expr_as_ast(right)?;
-
-            // Convert the name of the Rust operator to the name of a Coq function.
-            // TODO: Make this work with non-nat types.
-            let op_fn_name = match op {
-                syn::BinOp::Add(_) => "plus",
-                syn::BinOp::Sub(_) => "minus",
-                syn::BinOp::Mul(_) => "mult",
-                syn::BinOp::Div(_) => "Nat.div", // TODO: breaks with non-nat
-                syn::BinOp::Rem(_) => "Nat.modulo", // TODO: breaks with non-nat
-                syn::BinOp::And(_) => "andb",
-                syn::BinOp::Or(_) => "orb",
-                syn::BinOp::Shl(_) => "Nat.shiftl", // TODO: breaks with non-nat
-                syn::BinOp::Shr(_) => "Nat.shiftr",
-                syn::BinOp::Eq(_) => "Nat.eqb",
-                syn::BinOp::Lt(_) => "Nat.ltb",
-                syn::BinOp::Le(_) => "Nat.leb",
-
-                // TODO: Find a function for Ne, Gt, Ge
+            // The typing pass picks the Coq function for the type of the operands.
+            let op = match op {
+                syn::BinOp::Add(_) => ast::BinOp::Add,
+                syn::BinOp::Sub(_) => ast::BinOp::Sub,
+                syn::BinOp::Mul(_) => ast::BinOp::Mul,
+                syn::BinOp::Div(_) => ast::BinOp::Div,
+                syn::BinOp::Rem(_) => ast::BinOp::Rem,
+                syn::BinOp::And(_) => ast::BinOp::And,
+                syn::BinOp::Or(_) => ast::BinOp::Or,
+                syn::BinOp::Shl(_) => ast::BinOp::Shl,
+                syn::BinOp::Shr(_) => ast::BinOp::Shr,
+                syn::BinOp::BitAnd(_) => ast::BinOp::BitAnd,
+                syn::BinOp::BitOr(_) => ast::BinOp::BitOr,
+                syn::BinOp::BitXor(_) => ast::BinOp::BitXor,
+                syn::BinOp::Eq(_) => ast::BinOp::Eq,
+                syn::BinOp::Ne(_) => ast::BinOp::Ne,
+                syn::BinOp::Lt(_) => ast::BinOp::Lt,
+                syn::BinOp::Le(_) => ast::BinOp::Le,
+                syn::BinOp::Gt(_) => ast::BinOp::Gt,
+                syn::BinOp::Ge(_) => ast::BinOp::Ge,
                 _ => return Err(syn::Error::new(op.span(), "Unsupported binary operator")),
             };
 
-            Ok(ast::Expr::Apply {
-                func: op_fn_name.into(),
-                args: vec![lhs, rhs],
+            // The right operand of `&&` and `||` isn't always evaluated.
+            let lhs = expr_as_ast(cx, left)?;
+            let rhs = match op {
+                ast::BinOp::And | ast::BinOp::Or => scoped_as_ast(cx, right)?,
+                _ => expr_as_ast(cx, right)?,
+            };
+            Ok(ast::Expr::BinOp {
+                op,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+                loc: cx.loc(source.span()),
             })
         }
 
-        // Match an if statement.
-        syn::Expr::If(if_ex) => {
-            let scrutinee = Box::new(expr_as_ast(&if_ex.cond)?);
-            let mut cases = Vec::with_capacity(2);
+        // Match an if statement which guards a recursive call.
+        syn::Expr::If(if_ex)
+            if cx.recursion.as_ref().is_some_and(|recursion| {
+                recursion::guard_threshold(&if_ex.cond, &recursion.arg).is_some()
+            }) =>
+        {
+            guard_as_ast(cx, if_ex)
+        }
 
-            // Push the `then` branch
-            cases.push(ast::MatchCase {
-                pattern: ast::Pattern::Expr(ast::Expr::Bool(true)),
-                body: block_as_ast(&if_ex.then_branch)?,
-            });
+        // Match an if statement, or an `if let`.
+        syn::Expr::If(if_ex) => if_as_ast(cx, if_ex, |cx| {
+            let then_branch = block_as_ast(cx, &if_ex.then_branch)?;
 
-            // Optionally push the else branch
-            if let Some((_tok, else_expr)) = &if_ex.else_branch {
-                cases.push(ast::MatchCase {
-                    pattern: ast::Pattern::Expr(ast::Expr::Bool(false)),
-                    body: expr_as_ast(else_expr)?,
-                })
-            } else {
-                // Push unit
-                cases.push(ast::MatchCase {
-                    pattern: ast::Pattern::Expr(ast::Expr::Bool(false)),
-                    body: ast::Expr::Tt,
-                })
+            // Without an else branch, the `if` evaluates to unit.
+            let else_branch = match &if_ex.else_branch {
+                Some((_tok, else_expr)) => expr_as_ast(cx, else_expr)?,
+                None => ast::Expr::Tt,
+            };
+            Ok((then_branch, else_branch))
+        }),
+
+        // Match tuples, which are pairs (nested, if there are more than two elements), or `tt` if
+        // they're empty.
+        syn::Expr::Tuple(syn::ExprTuple { elems, .. }) => {
+            let mut elems = elems
+                .iter()
+                .map(|elem| expr_as_ast(cx, elem))
+                .collect::<syn::Result<Vec<_>>>()?;
+            match elems.len() {
+                0 => Ok(ast::Expr::Tt),
+                1 => Ok(elems.pop().unwrap()),
+                _ => Ok(ast::Expr::Tuple(elems)),
+            }
+        }
+
+        // Match lists: `vec![..]` and array literals.
+        syn::Expr::Macro(syn::ExprMacro { mac, .. }) if mac.path.is_ident("vec") => {
+            let repeat = |input: syn::parse::ParseStream| {
+                let elem: syn::Expr = input.parse()?;
+                input.parse::<syn::Token![;]>()?;
+                let count: syn::Expr = input.parse()?;
+                Ok((elem, count))
+            };
+            if let Ok((elem, count)) = mac.parse_body_with(repeat) {
+                return repeat_as_ast(cx, source, &elem, &count);
+            }
+            let elems = mac.parse_body_with(
+                syn::punctuated::Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated,
+            )?;
+            Ok(ast::Expr::List(
+                elems
+                    .iter()
+                    .map(|elem| expr_as_ast(cx, elem))
+                    .collect::<syn::Result<_>>()?,
+            ))
+        }
+
+        // Match macros which may panic, whether or not they're invoked as statements.
+        syn::Expr::Macro(syn::ExprMacro { mac, .. }) => macro_as_ast(cx, mac),
+
+        syn::Expr::Array(syn::ExprArray { elems, .. }) => Ok(ast::Expr::List(
+            elems
+                .iter()
+                .map(|elem| expr_as_ast(cx, elem))
+                .collect::<syn::Result<_>>()?,
+        )),
+        syn::Expr::Repeat(syn::ExprRepeat { expr, len, .. }) => {
+            repeat_as_ast(cx, source, expr, len)
+        }
+
+        // Match the methods of lists.
+        syn::Expr::MethodCall(call) => method_as_ast(cx, call),
+
+        // Match closures, which become anonymous functions.
+        syn::Expr::Closure(closure) => closure_as_ast(cx, closure),
+
+        // Match struct literals, including those which update the fields of another struct.
+        syn::Expr::Struct(syn::ExprStruct {
+            qself: None,
+            path,
+            fields,
+            rest,
+            ..
+        }) => {
+            // A struct variant of an enum is its constructor applied to a record of its fields.
+            let variant = constructor_of(path);
+            let name = match &variant {
+                Some((inductive, constructor)) => {
+                    cx.add_type(inductive);
+                    ast::Inductive::fields(constructor)
+                }
+                None => {
+                    cx.add_type(path);
+                    path.segments.last().unwrap().ident.to_string()
+                }
+            };
+
+            let mut values = vec![];
+            for field in fields {
+                let syn::Member::Named(ident) = &field.member else {
+                    return Err(syn::Error::new(
+                        field.member.span(),
+                        "expected a named field",
+                    ));
+                };
+                values.push((ident.to_string(), expr_as_ast(cx, &field.expr)?));
+            }
+
+            // Replace each given field of the base struct in turn.
+            if let Some((_, constructor)) = variant {
+                let fields = values
+                    .into_iter()
+                    .map(|(field, value)| (ast::Record::projection(&constructor, &field), value))
+                    .collect();
+                return Ok(ast::Expr::Apply {
+                    func: constructor,
+                    args: vec![ast::Expr::Record { name, fields }],
+                });
+            }
+            let Some(base) = rest else {
+                let fields = values
+                    .into_iter()
+                    .map(|(field, value)| (ast::Record::projection(&name, &field), value))
+                    .collect();
+                return Ok(ast::Expr::Record { name, fields });
+            };
+            let mut expr = expr_as_ast(cx, base)?;
+            for (field, value) in values {
+                expr = ast::Expr::SetField {
+                    base: Box::new(expr),
+                    field,
+                    value: Box::new(value),
+                    loc: cx.loc(source.span()),
+                };
             }
+            Ok(expr)
+        }
+
+        // Match field access on structs.
+        syn::Expr::Field(syn::ExprField {
+            base,
+            member: syn::Member::Named(field),
+            ..
+        }) => Ok(ast::Expr::Field {
+            base: Box::new(expr_as_ast(cx, base)?),
+            field: field.to_string(),
+            loc: cx.loc(source.span()),
+        }),
+
+        // Match a match statement.
+        syn::Expr::Match(match_ex) => {
+            let scrutinee = expr_as_ast(cx, &match_ex.expr)?;
+            let guarded = match_ex.arms.iter().any(|arm| arm.guard.is_some());
+
+            // Guards are desugared by matching the scrutinee again, so bind it to a variable first
+            // rather than evaluating it repeatedly.
+            match scrutinee {
+                ast::Expr::Var(_) => arms_as_ast(cx, &scrutinee, &match_ex.arms),
+                _ if !guarded => arms_as_ast(cx, &scrutinee, &match_ex.arms),
+                _ => {
+                    let ident = cx.fresh("scrutinee");
+                    let var = ast::Expr::Var(ident.clone());
+                    Ok(ast::Expr::LetIn {
+                        ident,
+                        ty: None,
+                        value: Box::new(scrutinee),
+                        child: Box::new(arms_as_ast(cx, &var, &match_ex.arms)?),
+                    })
+                }
+            }
+        }
+
+        // Match an early return, which the enclosing blocks move everything after into the other
+        // branches.
+        syn::Expr::Return(syn::ExprReturn { expr, .. }) => Ok(ast::Expr::Return {
+            value: Box::new(match expr {
+                Some(expr) => expr_as_ast(cx, expr)?,
+                None => ast::Expr::Tt,
+            }),
+            loc: cx.loc(source.span()),
+        }),
 
-            Ok(ast::Expr::Match { cases, scrutinee })
+        // Match the `?` operator, binding what's in the value to a fresh variable before the
+        // statement it's in.
+        syn::Expr::Try(syn::ExprTry { expr, .. }) => {
+            let value = expr_as_ast(cx, expr)?;
+            let ident = cx.fresh("try");
+            let loc = cx.loc(source.span());
+            cx.tries.push((ident.clone(), value, loc));
+            Ok(ast::Expr::Var(ident))
         }
 
         _ => Err(syn::Error::new(
@@ -106,6 +460,502 @@ pub fn expr_as_ast(source: &syn::Expr) -> syn::Result<ast::Expr> {
     }
 }
 
+/// Translate an expression which isn't always evaluated when the statement it's in is. Any
+/// values `?` is applied to within it are bound there, rather than before the statement.
+pub fn scoped_as_ast(cx: &mut Context, source: &syn::Expr) -> syn::Result<ast::Expr> {
+    let outer = std::mem::take(&mut cx.tries);
+    let expr = expr_as_ast(cx, source);
+    let tries = std::mem::replace(&mut cx.tries, outer);
+    let mut expr = expr?;
+    for (ident, value, loc) in tries.into_iter().rev() {
+        expr = ast::Expr::Try {
+            ident,
+            value: Box::new(value),
+            child: Box::new(expr),
+            loc,
+        };
+    }
+    Ok(expr)
+}
+
+/// Translate a macro which may panic. `panic!`, `unreachable!`, `todo!` and `unimplemented!`
+/// always do, while an assertion becomes
+/// `match cond with true => tt | false => False_rect _ _ end`. Debug assertions are translated
+/// like any other, since they're checked in debug builds.
+fn macro_as_ast(cx: &mut Context, mac: &syn::Macro) -> syn::Result<ast::Expr> {
+    let name = mac
+        .path
+        .get_ident()
+        .map(|ident| ident.to_string())
+        .unwrap_or_default();
+    if is_panic(mac) {
+        return Ok(ast::Expr::Panic);
+    }
+    let op = match name.as_str() {
+        "assert" | "debug_assert" => None,
+        "assert_eq" | "debug_assert_eq" => Some(ast::BinOp::Eq),
+        "assert_ne" | "debug_assert_ne" => Some(ast::BinOp::Ne),
+        _ => {
+            return Err(syn::Error::new(
+                mac.path.span(),
+                "unsupported macro, expected `vec!`, `panic!`, `unreachable!` or an assertion",
+            ))
+        }
+    };
+
+    // The message, and any arguments it's formatted with, are ignored.
+    let args = mac.parse_body_with(
+        syn::punctuated::Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated,
+    )?;
+    let cond = match (op, args.first(), args.get(1)) {
+        (None, Some(cond), _) => expr_as_ast(cx, cond)?,
+        (Some(op), Some(lhs), Some(rhs)) => ast::Expr::BinOp {
+            op,
+            lhs: Box::new(expr_as_ast(cx, lhs)?),
+            rhs: Box::new(expr_as_ast(cx, rhs)?),
+            loc: cx.loc(mac.span()),
+        },
+        _ => {
+            return Err(syn::Error::new(
+                mac.span(),
+                "expected the condition of the assertion",
+            ))
+        }
+    };
+    Ok(if_as_match(cond, ast::Expr::Tt, ast::Expr::Panic))
+}
+
+/// Whether a macro always panics.
+pub fn is_panic(mac: &syn::Macro) -> bool {
+    ["panic", "unreachable", "todo", "unimplemented"]
+        .iter()
+        .any(|name| mac.path.is_ident(name))
+}
+
+/// Translate a list of `count` copies of `elem`, from `vec![elem; count]` or `[elem; count]`.
+fn repeat_as_ast(
+    cx: &mut Context,
+    source: &syn::Expr,
+    elem: &syn::Expr,
+    count: &syn::Expr,
+) -> syn::Result<ast::Expr> {
+    Ok(ast::Expr::ListOp {
+        op: ast::ListOp::Repeat,
+        args: vec![expr_as_ast(cx, elem)?, expr_as_ast(cx, count)?],
+        loc: cx.loc(source.span()),
+    })
+}
+
+/// Translate a closure to a `Lambda`. Parameters which are destructured by a pattern are given a
+/// fresh name, and destructured at the start of the body, like a function's arguments.
+fn closure_as_ast(cx: &mut Context, closure: &syn::ExprClosure) -> syn::Result<ast::Expr> {
+    let mut params = vec![];
+    let mut destructured = vec![];
+    let mut assignable = vec![];
+    for input in &closure.inputs {
+        let (pat, ty) = match input {
+            syn::Pat::Type(syn::PatType { pat, ty, .. }) => {
+                (&**pat, Some(use_type(&mut cx.types, &cx.scope, ty)?))
+            }
+            pat => (pat, None),
+        };
+        let name = match pat_as_ast(pat)? {
+            ast::Pattern::Ident(name) => name,
+            ast::Pattern::Wildcard => cx.fresh("arg"),
+            pattern if is_irrefutable(&pattern) => {
+                let name = cx.fresh("arg");
+                destructured.push((pattern, name.clone()));
+                name
+            }
+            _ => {
+                return Err(syn::Error::new(
+                    pat.span(),
+                    "expected an irrefutable pattern in closure argument",
+                ))
+            }
+        };
+        params.push((name, ty));
+        assignable.extend(mutable_vars(pat));
+    }
+
+    // Only the closure's own parameters can be reassigned within it: the variables it captures
+    // are shared with the enclosing function.
+    let outer = std::mem::replace(&mut cx.assignable, assignable);
+    let body = scoped_as_ast(cx, &closure.body);
+    cx.assignable = outer;
+
+    // A `return` in the closure returns from the closure.
+    let mut body = strip_returns(body?);
+    for (pattern, name) in destructured.into_iter().rev() {
+        body = ast::Expr::LetPattern {
+            pattern: Box::new(pattern),
+            ty: None,
+            value: Box::new(ast::Expr::Var(name)),
+            child: Box::new(body),
+        };
+    }
+    Ok(ast::Expr::Lambda {
+        params,
+        body: Box::new(body),
+    })
+}
+
+/// Translate a method call on a list, or an iterator over one, to the equivalent `ListOp`, or a
+/// method of a standard trait to the operator it implements.
+fn method_as_ast(cx: &mut Context, call: &syn::ExprMethodCall) -> syn::Result<ast::Expr> {
+    let method = call.method.to_string();
+
+    // Within an inherent `impl` block, a method called on `self` is another of the type's, except
+    // for `clone`.
+    if let Some(ty) = cx.methods_of.clone().filter(|_| method != "clone") {
+        if matches!(&*call.receiver, syn::Expr::Path(path) if path.path.is_ident("self")) {
+            let func = method_name(&ty, &method);
+            if func == cx.func {
+                return Err(syn::Error::new(
+                    call.method.span(),
+                    "methods can't call themselves",
+                ));
+            }
+            let func = cx.add_callee(&syn::Ident::new(&func, call.method.span()).into());
+            let mut args = vec![expr_as_ast(cx, &call.receiver)?];
+            for arg in &call.args {
+                args.push(expr_as_ast(cx, arg)?);
+            }
+            return Ok(ast::Expr::Apply { func, args });
+        }
+    }
+
+    if let Some(op) = trait_method(&method).filter(|_| call.args.len() == 1) {
+        return Ok(ast::Expr::TraitOp {
+            op,
+            receiver: Box::new(expr_as_ast(cx, &call.receiver)?),
+            arg: Box::new(expr_as_ast(cx, &call.args[0])?),
+            loc: cx.loc(call.span()),
+        });
+    }
+    let (op, arity) = match method.as_str() {
+        // Values can be used more than once in Coq, so cloning one is the same as the value itself.
+        "clone" if call.args.is_empty() => return expr_as_ast(cx, &call.receiver),
+
+        // Iterating over a list is the same as the list itself, so adapters which don't change
+        // its elements are erased, as is collecting them into a list again.
+        "iter" | "into_iter" | "copied" | "cloned" | "collect" if call.args.is_empty() => {
+            return expr_as_ast(cx, &call.receiver);
+        }
+
+        "len" | "count" => (ast::ListOp::Len, 0),
+        "is_empty" => (ast::ListOp::IsEmpty, 0),
+        "first" => (ast::ListOp::First, 0),
+        "get" => (ast::ListOp::Get, 1),
+        "contains" => (ast::ListOp::Contains, 1),
+        "sum" => (ast::ListOp::Sum, 0),
+        "map" => (ast::ListOp::Map, 1),
+        "filter" => (ast::ListOp::Filter, 1),
+        "fold" => (ast::ListOp::Fold, 2),
+        "any" => (ast::ListOp::Any, 1),
+        "all" => (ast::ListOp::All, 1),
+        _ => {
+            return Err(syn::Error::new(
+                call.method.span(),
+                format!("unsupported method `{method}`"),
+            ))
+        }
+    };
+    if call.args.len() != arity {
+        let expected = match arity {
+            0 => "no arguments".to_string(),
+            1 => "1 argument".to_string(),
+            n => format!("{n} arguments"),
+        };
+        return Err(syn::Error::new(
+            call.span(),
+            format!("`{method}` takes {expected}"),
+        ));
+    }
+
+    let mut args = vec![expr_as_ast(cx, &call.receiver)?];
+    for arg in &call.args {
+        args.push(expr_as_ast(cx, arg)?);
+    }
+    Ok(ast::Expr::ListOp {
+        op,
+        args,
+        loc: cx.loc(call.span()),
+    })
+}
+
+/// The operator implemented by a method of `PartialEq`, `PartialOrd` or `Add`, e.g. `Lt` for
+/// `lt`.
+fn trait_method(method: &str) -> Option<ast::BinOp> {
+    let op = match method {
+        "eq" => ast::BinOp::Eq,
+        "ne" => ast::BinOp::Ne,
+        "lt" => ast::BinOp::Lt,
+        "le" => ast::BinOp::Le,
+        "gt" => ast::BinOp::Gt,
+        "ge" => ast::BinOp::Ge,
+        "add" => ast::BinOp::Add,
+        _ => return None,
+    };
+    Some(op)
+}
+
+/// Whether `func` is `Default::default`, either called on the trait or on one of the function's
+/// type parameters, like `T::default` or `<T as Default>::default`.
+fn is_default(cx: &Context, func: &syn::Expr) -> bool {
+    let syn::Expr::Path(syn::ExprPath { qself, path, .. }) = func else {
+        return false;
+    };
+    let segments = path
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect::<Vec<_>>();
+    let segments = segments.iter().map(String::as_str).collect::<Vec<_>>();
+    match (qself, &segments[..]) {
+        (_, [.., "Default", "default"]) => true,
+        (None, [param, "default"]) => cx.scope.params.iter().any(|p| p == param),
+        _ => false,
+    }
+}
+
+/// If `path` names an inherent method, like `Rect::area`, or `Self::area` within an `impl` block,
+/// the path to the definition generated for it, `Rect_area`.
+///
+/// Like the variants of enums, types are recognised by being capitalized, and methods by not
+/// being.
+fn method_path(cx: &Context, path: &syn::Path) -> Option<syn::Path> {
+    let mut path = path.clone();
+    let method = path.segments.pop()?.into_value();
+    let ty = path.segments.pop()?.into_value();
+    let ty_name = ty.ident.to_string();
+    if method.ident.to_string().starts_with(char::is_uppercase)
+        || !ty_name.starts_with(char::is_uppercase)
+        || !ty.arguments.is_empty()
+    {
+        return None;
+    }
+    let ty_name = match ty_name.as_str() {
+        "Self" => cx.methods_of.clone()?,
+        _ => ty_name,
+    };
+    let func = method_name(&ty_name, &method.ident.to_string());
+    path.segments
+        .push(syn::Ident::new(&func, method.ident.span()).into());
+    Some(path)
+}
+
+/// The operator applied by a compound assignment, e.g. `Add` for `+=`.
+pub fn compound_assign_op(op: &syn::BinOp) -> Option<ast::BinOp> {
+    let op = match op {
+        syn::BinOp::AddAssign(_) => ast::BinOp::Add,
+        syn::BinOp::SubAssign(_) => ast::BinOp::Sub,
+        syn::BinOp::MulAssign(_) => ast::BinOp::Mul,
+        syn::BinOp::DivAssign(_) => ast::BinOp::Div,
+        syn::BinOp::RemAssign(_) => ast::BinOp::Rem,
+        syn::BinOp::BitXorAssign(_) => ast::BinOp::BitXor,
+        syn::BinOp::BitAndAssign(_) => ast::BinOp::BitAnd,
+        syn::BinOp::BitOrAssign(_) => ast::BinOp::BitOr,
+        syn::BinOp::ShlAssign(_) => ast::BinOp::Shl,
+        syn::BinOp::ShrAssign(_) => ast::BinOp::Shr,
+        _ => return None,
+    };
+    Some(op)
+}
+
+/// Translate an `if` to a `match` on its condition, or an `if let` to a `match` on its scrutinee
+/// with a wildcard for the `else` branch. The branches are translated by `branches`, once the
+/// condition has been.
+pub fn if_as_ast(
+    cx: &mut Context,
+    if_ex: &syn::ExprIf,
+    branches: impl FnOnce(&mut Context) -> syn::Result<(ast::Expr, ast::Expr)>,
+) -> syn::Result<ast::Expr> {
+    let syn::Expr::Let(let_ex) = &*if_ex.cond else {
+        let cond = expr_as_ast(cx, &if_ex.cond)?;
+        let (then_branch, else_branch) = branches(cx)?;
+        return Ok(if_as_match(cond, then_branch, else_branch));
+    };
+    let scrutinee = expr_as_ast(cx, &let_ex.expr)?;
+    let pattern = pat_as_ast(&let_ex.pat)?;
+    let (then_branch, else_branch) = branches(cx)?;
+
+    // Coq rejects redundant cases, so there's no `else` branch if the pattern always matches.
+    let irrefutable = is_irrefutable(&pattern);
+    let mut cases = vec![ast::MatchCase {
+        pattern,
+        body: then_branch,
+    }];
+    if !irrefutable {
+        cases.push(ast::MatchCase {
+            pattern: ast::Pattern::Wildcard,
+            body: else_branch,
+        });
+    }
+    Ok(ast::Expr::Match {
+        scrutinee: Box::new(scrutinee),
+        cases,
+    })
+}
+
+/// Build a `match` on a boolean condition, which is how Coq spells `if`.
+fn if_as_match(cond: ast::Expr, then_branch: ast::Expr, else_branch: ast::Expr) -> ast::Expr {
+    ast::Expr::Match {
+        scrutinee: Box::new(cond),
+        cases: vec![
+            ast::MatchCase {
+                pattern: ast::Pattern::Expr(ast::Expr::Bool(true)),
+                body: then_branch,
+            },
+            ast::MatchCase {
+                pattern: ast::Pattern::Expr(ast::Expr::Bool(false)),
+                body: else_branch,
+            },
+        ],
+    }
+}
+
+/// Convert the arms of a Rust `match` on `scrutinee` to a Coq `match`.
+///
+/// Coq has no guards, and a guard is only evaluated once the arms before it haven't matched, so
+/// the first guarded arm `p if g => e` is matched on its own, when the arms before it don't match:
+///
+/// ```coq
+/// match scrutinee with
+/// | earlier pattern => ...
+/// | _ => match scrutinee with
+///        | p => if g then e else rest
+///        | _ => rest
+///        end
+/// end
+/// ```
+///
+/// where `rest` matches the other arms, converted the same way, to fall through to when `g` is
+/// false. Since Rust requires the unguarded arms to be exhaustive, so is each Coq `match`.
+fn arms_as_ast(
+    cx: &mut Context,
+    scrutinee: &ast::Expr,
+    arms: &[syn::Arm],
+) -> syn::Result<ast::Expr> {
+    let mut cases = vec![];
+    for (i, arm) in arms.iter().enumerate() {
+        let pattern = pat_as_ast(&arm.pat)?;
+        let guard =
+            match &arm.guard {
+                Some(_) if i + 1 == arms.len() => return Err(syn::Error::new(
+                    arm.span(),
+                    "expected another arm after a guarded arm, to match when the guard is false",
+                )),
+                Some((_if, guard)) => Some(scoped_as_ast(cx, guard)?),
+                None => None,
+            };
+        let body = scoped_as_ast(cx, &arm.body)?;
+        cases.push((pattern, guard, body));
+    }
+    Ok(guarded_match(scrutinee, &cases))
+}
+
+/// Build a `match` on `scrutinee` of the arms `cases`, each a pattern, an optional guard and a
+/// body (see `arms_as_ast`).
+fn guarded_match(
+    scrutinee: &ast::Expr,
+    cases: &[(ast::Pattern, Option<ast::Expr>, ast::Expr)],
+) -> ast::Expr {
+    let unguarded = |(pattern, _, body): &(ast::Pattern, _, ast::Expr)| ast::MatchCase {
+        pattern: pattern.clone(),
+        body: body.clone(),
+    };
+    let Some(i) = cases.iter().position(|(_, guard, _)| guard.is_some()) else {
+        return ast::Expr::Match {
+            scrutinee: Box::new(scrutinee.clone()),
+            cases: cases.iter().map(unguarded).collect(),
+        };
+    };
+
+    // The arms before the guarded one are kept in the rest, where they don't match, so that it
+    // stays exhaustive.
+    let (pattern, guard, body) = &cases[i];
+    let others = cases[..i]
+        .iter()
+        .chain(&cases[i + 1..])
+        .cloned()
+        .collect::<Vec<_>>();
+    let rest = guarded_match(scrutinee, &others);
+    let guard = guard.clone().unwrap();
+    let mut guarded_cases = vec![ast::MatchCase {
+        pattern: pattern.clone(),
+        body: if_as_match(guard, body.clone(), rest.clone()),
+    }];
+    if !is_irrefutable(pattern) {
+        guarded_cases.push(ast::MatchCase {
+            pattern: ast::Pattern::Wildcard,
+            body: rest,
+        });
+    }
+    let guarded = ast::Expr::Match {
+        scrutinee: Box::new(scrutinee.clone()),
+        cases: guarded_cases,
+    };
+    if i == 0 {
+        return guarded;
+    }
+    let mut cases = cases[..i].iter().map(unguarded).collect::<Vec<_>>();
+    cases.push(ast::MatchCase {
+        pattern: ast::Pattern::Wildcard,
+        body: guarded,
+    });
+    ast::Expr::Match {
+        scrutinee: Box::new(scrutinee.clone()),
+        cases,
+    }
+}
+
+/// Convert an `if` which checks that the decreasing argument of a recursive function is at least
+/// `k` into a `match` which binds its predecessors, so that recursive calls are structural.
+fn guard_as_ast(cx: &mut Context, if_ex: &syn::ExprIf) -> syn::Result<ast::Expr> {
+    let recursion = cx.recursion.as_ref().unwrap();
+    let (k, then_is_successor) = recursion::guard_threshold(&if_ex.cond, &recursion.arg).unwrap();
+    let scrutinee = Box::new(ast::Expr::Var(recursion.arg.clone()));
+    let pattern = recursion::successor_pattern(recursion, k);
+
+    let then_branch = |cx: &mut Context| block_as_ast(cx, &if_ex.then_branch);
+    let else_branch = |cx: &mut Context| match &if_ex.else_branch {
+        Some((_tok, else_expr)) => expr_as_ast(cx, else_expr),
+        None => Ok(ast::Expr::Tt),
+    };
+
+    // Translate the successor branch with the predecessors in scope.
+    let outer_depth = recursion.depth;
+    let successor_depth = outer_depth.max(k);
+    cx.recursion.as_mut().unwrap().depth = successor_depth;
+    let successor = if then_is_successor {
+        then_branch(cx)
+    } else {
+        else_branch(cx)
+    };
+    cx.recursion.as_mut().unwrap().depth = outer_depth;
+
+    let otherwise = if then_is_successor {
+        else_branch(cx)?
+    } else {
+        then_branch(cx)?
+    };
+
+    Ok(ast::Expr::Match {
+        scrutinee,
+        cases: vec![
+            ast::MatchCase {
+                pattern,
+                body: successor?,
+            },
+            ast::MatchCase {
+                pattern: ast::Pattern::Wildcard,
+                body: otherwise,
+            },
+        ],
+    })
+}
+
 /// Give the name of an Expr.
 fn expr_name(expr: &syn::Expr) -> &'static str {
     // This is literally the only way to do it.
@@ -157,13 +1007,29 @@ fn expr_name(expr: &syn::Expr) -> &'static str {
 mod tests {
     use super::*;
     use insta::assert_snapshot;
+    use proc_macro2::Span;
+    use roq_core::typing::{Numbers, Signature, Typing};
 
     fn parse(input: &str) -> syn::Expr {
         syn::parse_str(input).expect("Failed to parse source code")
     }
 
     fn expr(input: &str) -> ast::Expr {
-        expr_as_ast(&parse(input)).expect("Failed to convert to expr")
+        typed_expr(&[], input).expect("Failed to convert to expr")
+    }
+
+    /// Convert an expression and infer its types, with the given variables in scope.
+    fn typed_expr(locals: &[(&str, ast::Ty)], input: &str) -> syn::Result<ast::Expr> {
+        let mut cx = Context::default();
+        let expr = expr_as_ast(&mut cx, &parse(input))?;
+        let mut typing = Typing::default();
+        for (name, ty) in locals {
+            typing.bind(name.to_string(), Some(ty.clone()));
+        }
+        let (expr, _ty) = typing
+            .infer(&expr)
+            .map_err(|err| cx.type_error(err, Span::call_site()))?;
+        Ok(expr)
     }
 
     #[test]
@@ -198,6 +1064,55 @@ mod tests {
         assert_snapshot!(expr("a == b"), @r###"
         (Nat.eqb a b)
         "###);
+        assert_snapshot!(expr("a != b"), @r###"
+        (negb (Nat.eqb a b)
+        )
+        "###);
+        assert_snapshot!(expr("a < b"), @"(Nat.ltb a b)");
+        assert_snapshot!(expr("a <= b"), @"(Nat.leb a b)");
+        assert_snapshot!(expr("a > b"), @"(Nat.ltb b a)");
+        assert_snapshot!(expr("a >= b"), @"(Nat.leb b a)");
+    }
+
+    #[test]
+    fn test_bitwise_ops() {
+        let locals = [
+            ("p", ast::Ty::Bool),
+            ("q", ast::Ty::Bool),
+            ("z", ast::Ty::Z),
+        ];
+        let expr = |input| typed_expr(&locals, input).unwrap();
+        assert_snapshot!(expr("a & b"), @"(Nat.land a b)");
+        assert_snapshot!(expr("a | b"), @"(Nat.lor a b)");
+        assert_snapshot!(expr("a ^ 0xff"), @"(Nat.lxor a 255)");
+        assert_snapshot!(expr("z & 1"), @"(Z.land z 1%Z)");
+        assert_snapshot!(expr("z | -1"), @"(Z.lor z (-1)%Z)");
+        assert_snapshot!(expr("z ^ z"), @"(Z.lxor z z)");
+        assert_snapshot!(expr("p & q"), @"(andb p q)");
+        assert_snapshot!(expr("p | q"), @"(orb p q)");
+        assert_snapshot!(expr("p ^ q"), @"(xorb p q)");
+    }
+
+    #[test]
+    fn test_unary_ops() {
+        let locals = [("p", ast::Ty::Bool), ("z", ast::Ty::Z)];
+        let expr = |input| typed_expr(&locals, input).unwrap();
+        assert_snapshot!(expr("!p"), @"(negb p)");
+        assert_snapshot!(expr("!(z > 0)"), @r###"
+        (negb (Z.ltb 0%Z z)
+        )
+        "###);
+        assert_snapshot!(expr("!z"), @"(Z.lnot z)");
+        assert_snapshot!(expr("-z"), @"(Z.opp z)");
+        assert_snapshot!(expr("-(z - 1)"), @r###"
+        (Z.opp (Z.sub z 1%Z)
+        )
+        "###);
+        assert_snapshot!(expr("p != (z >= -1)"), @r###"
+        (negb (Bool.eqb p (Z.leb (-1)%Z z)
+        )
+        )
+        "###);
     }
 
     #[test]
@@ -228,6 +1143,477 @@ mod tests {
         "###);
     }
 
+    #[test]
+    fn test_call() {
+        assert_snapshot!(expr("double(a + 1)"), @r###"
+        (double (plus a 1)
+        )
+        "###);
+        assert_snapshot!(expr("util::max(a, b)"), @"(util::max a b)");
+    }
+
+    #[test]
+    fn test_call_signatures() {
+        let call = |numbers: Numbers, ty: ast::Ty, input: &str| {
+            let mut cx = Context::default();
+            let expr = expr_as_ast(&mut cx, &parse(input)).unwrap();
+            let mut typing = Typing::default();
+            typing.bind("a".into(), Some(ast::Ty::Nat));
+            typing.bind("b".into(), Some(ast::Ty::Nat));
+            let max = Signature {
+                name: "util_max".into(),
+                params: vec![],
+                args: vec![ty.clone(), ty.clone()],
+                ret: ty,
+                numbers,
+            };
+            typing.declare("util::max".into(), max);
+            typing.check(&expr, &ast::Ty::Nat).unwrap()
+        };
+        assert_snapshot!(call(Numbers::Nat, ast::Ty::Nat, "util::max(a, b)"), @"(util_max a b)");
+        assert_snapshot!(call(Numbers::N, ast::Ty::N, "util::max(a, b)"), @r###"
+        (N.to_nat (util_max (N.of_nat a)
+         (N.of_nat b)
+        )
+        )
+        "###);
+        assert_snapshot!(call(Numbers::N, ast::Ty::N, "util::max(a, b) + 1"), @r###"
+        (plus (N.to_nat (util_max (N.of_nat a)
+         (N.of_nat b)
+        )
+        )
+         1)
+        "###);
+    }
+
+    #[test]
+    fn test_call_callees() {
+        let mut cx = Context::default();
+        expr_as_ast(&mut cx, &parse("f(g(a), f(b), util::h(c))")).unwrap();
+        let callees = cx
+            .callees
+            .iter()
+            .map(|path| quote::quote!(#path).to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(callees, ["f", "g", "util :: h"]);
+    }
+
+    #[test]
+    fn test_record_fields() {
+        let elaborate = |input: &str| {
+            let mut cx = Context::default();
+            let expr = expr_as_ast(&mut cx, &parse(input)).unwrap();
+            let mut typing = Typing::default();
+            typing.bind("p".into(), Some(ast::Ty::Named("Point".into())));
+            typing.define(&ast::Record {
+                name: "Point".into(),
+                fields: vec![ast::Binder {
+                    name: "Point_x".into(),
+                    ty: ast::Ty::Z,
+                    implicit: false,
+                }],
+            });
+            typing.infer(&expr).unwrap().0
+        };
+        assert_snapshot!(elaborate("p.x > 0"), @r###"
+        (Z.ltb 0%Z (Point_x p)
+        )
+        "###);
+        assert_snapshot!(elaborate("Point { x: 1 }"), @"{| Point_x := 1%Z |}");
+        assert_snapshot!(elaborate("Point { x: 1, ..p }"), @"(Point_set_x p 1%Z)");
+    }
+
+    #[test]
+    fn test_variants() {
+        assert_snapshot!(expr("Shape::Empty"), @"Shape_Empty");
+        assert_snapshot!(expr("Shape::Circle(r + 1)"), @r###"
+        (Shape_Circle (plus r 1)
+        )
+        "###);
+        assert_snapshot!(expr("Command::Move { dx: 1, dy }"), @"(Command_Move {| Command_Move_dx := 1; Command_Move_dy := dy |})");
+    }
+
+    #[test]
+    fn test_variant_types() {
+        let mut cx = Context::default();
+        let source = "(Shape::Empty, shapes::Shape::Circle(f(r)), Command::Move { dx, dy })";
+        expr_as_ast(&mut cx, &parse(source)).unwrap();
+        let paths = |paths: &[syn::Path]| {
+            paths
+                .iter()
+                .map(|path| quote::quote!(#path).to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(paths(&cx.types), ["Shape", "shapes :: Shape", "Command"]);
+        assert_eq!(paths(&cx.callees), ["f"]);
+    }
+
+    #[test]
+    fn test_match_variant() {
+        assert_snapshot!(
+            expr("match c { Command::Stop => 0, Command::Move { dx, .. } => dx }"),
+            @r###"
+        match c with
+        | Command_Stop =>
+        	0
+        | Command_Move {| Command_Move_dx := dx |} =>
+        	dx
+        end
+        "###);
+    }
+
+    #[test]
+    fn test_option_result() {
+        assert_snapshot!(expr("Some(a + 1)"), @r###"
+        (Some (plus a 1)
+        )
+        "###);
+        assert_snapshot!(expr("None"), @"None");
+        assert_snapshot!(expr("Option::None"), @"None");
+        assert_snapshot!(expr("if b { Ok(x) } else { Result::Err(false) }"), @r###"
+        match b with
+        | true =>
+        	(inl x)
+        | false =>
+        	(inr false)
+        end
+        "###);
+    }
+
+    #[test]
+    fn test_if_let() {
+        assert_snapshot!(expr("if let Some(x) = o { x } else { 0 }"), @r###"
+        match o with
+        | Some x =>
+        	x
+        | _ =>
+        	0
+        end
+        "###);
+        assert_snapshot!(expr("if let Ok(x) = r { x } else if let Err(Some(e)) = r { e } else { 0 }"), @r###"
+        match r with
+        | inl x =>
+        	x
+        | _ =>
+        	match r with
+        	| inr (Some e) =>
+        		e
+        	| _ =>
+        		0
+        	end
+        end
+        "###);
+        assert_snapshot!(expr("if let (x, _) = p { x } else { 0 }"), @r###"
+        match p with
+        | (x, _) =>
+        	x
+        end
+        "###);
+    }
+
+    #[test]
+    fn test_option_typing() {
+        let option_z = ast::Ty::option(ast::Ty::Z);
+        assert_snapshot!(
+            typed_expr(&[("o", option_z.clone())], "match o { Some(x) => Some(x + 1), None => None }").unwrap(),
+            @r###"
+        match o with
+        | Some x =>
+        	(Some (Z.add x 1%Z)
+        	)
+        | None =>
+        	None
+        end
+        "###);
+        assert_snapshot!(
+            typed_expr(&[("o", option_z)], "if let Some(x) = o { x } else { 0 }").unwrap(),
+            @r###"
+        match o with
+        | Some x =>
+        	x
+        | _ =>
+        	0%Z
+        end
+        "###);
+        assert_snapshot!(
+            typed_expr(&[("a", ast::Ty::Z)], "match Some(a) { Some(x) => x, None => 1 }").unwrap(),
+            @r###"
+        match (Some a)
+         with
+        | Some x =>
+        	x
+        | None =>
+        	1%Z
+        end
+        "###);
+    }
+
+    #[test]
+    fn test_match() {
+        assert_snapshot!(
+            expr("match n { 0 => a, 1 | 2 => b, _ => c }"),
+            @r###"
+        match n with
+        | 0 =>
+        	a
+        | 1 | 2 =>
+        	b
+        | _ =>
+        	c
+        end
+        "###);
+    }
+
+    #[test]
+    fn test_tuple() {
+        assert_snapshot!(expr("()"), @"tt");
+        assert_snapshot!(expr("(a,)"), @"a");
+        assert_snapshot!(expr("(a, b, c)"), @"(a, b, c)");
+        assert_snapshot!(
+            typed_expr(&[("a", ast::Ty::Z), ("b", ast::Ty::Bool)], "match (a, b) { (x, true) => x + 1, (x, false) => x }").unwrap(),
+            @r###"
+        match (a, b) with
+        | (x, true) =>
+        	(Z.add x 1%Z)
+        | (x, false) =>
+        	x
+        end
+        "###);
+    }
+
+    #[test]
+    fn test_list() {
+        assert_snapshot!(expr("vec![]"), @"nil");
+        assert_snapshot!(expr("vec![a, b + 1]"), @r###"
+        (a :: (plus b 1)
+         :: nil)
+        "###);
+        assert_snapshot!(expr("[1, 2, 3]"), @"(1 :: 2 :: 3 :: nil)");
+        assert_snapshot!(expr("vec![false; n]"), @"(repeat false n)");
+        assert_snapshot!(expr("[0; 4]"), @"(repeat 0 4)");
+        assert_snapshot!(expr("&v"), @"v");
+    }
+
+    #[test]
+    fn test_list_methods() {
+        let list_z = ast::Ty::list(ast::Ty::Z);
+        let typed =
+            |input| typed_expr(&[("v", list_z.clone()), ("i", ast::Ty::Nat)], input).unwrap();
+        assert_snapshot!(typed("v.len()"), @"(length v)");
+        assert_snapshot!(typed("v.is_empty()"), @r###"
+        (Nat.eqb (length v)
+         0)
+        "###);
+        assert_snapshot!(typed("v.first()"), @"(hd_error v)");
+        assert_snapshot!(typed("v.get(i + 1)"), @r###"
+        (nth_error v (plus i 1)
+        )
+        "###);
+        assert_snapshot!(typed("v.contains(&3)"), @r###"
+        (existsb (Z.eqb 3%Z)
+         v)
+        "###);
+        assert_snapshot!(typed("v.iter().sum()"), @"(fold_right Z.add 0%Z v)");
+        assert_snapshot!(typed("vec![1, 2].into_iter().sum()"), @"(list_sum (1 :: 2 :: nil))");
+        assert_snapshot!(typed("[v.len(); 2]"), @r###"
+        (repeat (length v)
+         2)
+        "###);
+    }
+
+    #[test]
+    fn test_closure() {
+        assert_snapshot!(expr("|x| x"), @"(fun x => x)");
+        assert_snapshot!(expr("|x: i64, _| x + 1"), @r###"
+        (fun (x : Z) arg'1 => (Z.add x 1%Z)
+        )
+        "###);
+        assert_snapshot!(expr("|(a, b)| a && b"), @r###"
+        (fun arg'1 => let '(a, b) := arg'1 in
+        (andb a b)
+        )
+        "###);
+    }
+
+    #[test]
+    fn test_iterators() {
+        let (list_nat, list_z) = (ast::Ty::list(ast::Ty::Nat), ast::Ty::list(ast::Ty::Z));
+        let typed =
+            |input| typed_expr(&[("v", list_nat.clone()), ("w", list_z.clone())], input).unwrap();
+        assert_snapshot!(typed("v.iter().map(|x| x * 2).collect::<Vec<_>>()"), @r###"
+        (map (fun (x : nat) => (mult x 2)
+        ) v)
+        "###);
+        assert_snapshot!(typed("v.iter().filter(|&&x| x > 3).count()"), @r###"
+        (length (filter (fun (x : nat) => (Nat.ltb 3 x)
+        ) v)
+        )
+        "###);
+        assert_snapshot!(typed("v.iter().fold(0, |a, b| a + b)"), @r###"
+        (fold_left (fun (a : nat) (b : nat) => (plus a b)
+        ) v 0)
+        "###);
+        assert_snapshot!(typed("w.iter().fold(0, |a, b| a - b)"), @r###"
+        (fold_left (fun (a : Z) (b : Z) => (Z.sub a b)
+        ) w 0%Z)
+        "###);
+        assert_snapshot!(
+            typed("v.iter().map(|x| x * 2).filter(|x| *x > 3).fold(0, |a, b| a + b)"),
+            @r###"
+        (fold_left (fun (a : nat) (b : nat) => (plus a b)
+        ) (filter (fun (x : nat) => (Nat.ltb 3 x)
+        ) (map (fun (x : nat) => (mult x 2)
+        ) v)
+        )
+         0)
+        "###
+        );
+        assert_snapshot!(typed("v.iter().any(|x| *x == 0) || v.iter().all(|x| x % 2 == 1)"), @r###"
+        (orb (existsb (fun (x : nat) => (Nat.eqb x 0)
+        ) v)
+         (forallb (fun (x : nat) => (Nat.eqb (Nat.modulo x 2)
+         1)
+        ) v)
+        )
+        "###);
+        assert_snapshot!(typed("v.iter().map(|x| x + 1).sum::<u64>()"), @r###"
+        (list_sum (map (fun (x : nat) => (plus x 1)
+        ) v)
+        )
+        "###);
+    }
+
+    #[test]
+    fn test_list_errors() {
+        let list_bool = ast::Ty::list(ast::Ty::Bool);
+        let err = |input| {
+            typed_expr(&[("v", list_bool.clone())], input)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_snapshot!(err("v.sum()"), @"`sum` isn't supported on a list of `bool`");
+        assert_snapshot!(err("v.len(1)"), @"`len` takes no arguments");
+        assert_snapshot!(err("v.iter().rev()"), @"unsupported method `rev`");
+        assert_snapshot!(err("v.iter().fold(0)"), @"`fold` takes 2 arguments");
+        assert_snapshot!(err("v.contains(&0)"), @"mismatched types: expected `bool`, found an integer");
+    }
+
+    #[test]
+    fn test_match_tuple() {
+        assert_snapshot!(
+            expr("match (a, b) { (true, _) => 1, (false, x) => x }"),
+            @r###"
+        match (a, b) with
+        | (true, _) =>
+        	1
+        | (false, x) =>
+        	x
+        end
+        "###);
+    }
+
+    #[test]
+    fn test_match_constructor() {
+        assert_snapshot!(
+            expr("match o { Some(x) => x, None => 0 }"),
+            @r###"
+        match o with
+        | Some x =>
+        	x
+        | None =>
+        	0
+        end
+        "###);
+    }
+
+    #[test]
+    fn test_match_guard() {
+        assert_snapshot!(
+            expr("match o { None => 0, Some(x) if x < 10 => x, Some(_) => 10 }"),
+            @r###"
+        match o with
+        | None =>
+        	0
+        | _ =>
+        	match o with
+        	| Some x =>
+        		match (Nat.ltb x 10)
+        		 with
+        		| true =>
+        			x
+        		| false =>
+        			match o with
+        			| None =>
+        				0
+        			| Some _ =>
+        				10
+        			end
+        		end
+        	| _ =>
+        		match o with
+        		| None =>
+        			0
+        		| Some _ =>
+        			10
+        		end
+        	end
+        end
+        "###);
+    }
+
+    #[test]
+    fn test_match_guard_binding() {
+        assert_snapshot!(
+            expr("match f(n) { x if x < 10 => x, _ => 10 }"),
+            @r###"
+        let scrutinee'1 := (f n)
+         in
+        match scrutinee'1 with
+        | x =>
+        	match (Nat.ltb x 10)
+        	 with
+        	| true =>
+        		x
+        	| false =>
+        		match scrutinee'1 with
+        		| _ =>
+        			10
+        		end
+        	end
+        end
+        "###);
+    }
+
+    #[test]
+    fn test_match_guard_after_arm() {
+        // The guard divides by `n` only once the first arm hasn't matched.
+        assert_snapshot!(
+            expr("match x { 0 => 0, n if 10 / n > 1 => n, _ => 1 }"),
+            @r###"
+        match x with
+        | 0 =>
+        	0
+        | _ =>
+        	match x with
+        	| n =>
+        		match (Nat.ltb 1 (Nat.div 10 n)
+        		)
+        		 with
+        		| true =>
+        			n
+        		| false =>
+        			match x with
+        			| 0 =>
+        				0
+        			| _ =>
+        				1
+        			end
+        		end
+        	end
+        end
+        "###);
+    }
+
     #[test]
     fn test_ternary_add() {
         assert_snapshot!(
@@ -237,4 +1623,50 @@ mod tests {
          3)
         "###);
     }
+
+    #[test]
+    fn test_signed_literals() {
+        assert_snapshot!(expr("5i64"), @"5%Z");
+        assert_snapshot!(expr("-5"), @"(-5)%Z");
+        assert_snapshot!(expr("-5 + a"), @"(Z.add (-5)%Z a)");
+    }
+
+    #[test]
+    fn test_signed_binary_ops() {
+        let locals = [("a", ast::Ty::Z), ("b", ast::Ty::Z)];
+        let expr = |input| typed_expr(&locals, input).unwrap();
+        assert_snapshot!(expr("a + b"), @"(Z.add a b)");
+        assert_snapshot!(expr("a - 1"), @"(Z.sub a 1%Z)");
+        assert_snapshot!(expr("1 * a"), @"(Z.mul 1%Z a)");
+        assert_snapshot!(expr("a / b"), @"(Z.quot a b)");
+        assert_snapshot!(expr("a % b"), @"(Z.rem a b)");
+        assert_snapshot!(expr("a == 0"), @"(Z.eqb a 0%Z)");
+        assert_snapshot!(expr("a < b"), @"(Z.ltb a b)");
+        assert_snapshot!(expr("a <= b"), @"(Z.leb a b)");
+        assert_snapshot!(expr("-a"), @"(Z.opp a)");
+    }
+
+    #[test]
+    fn test_bool_eq() {
+        let locals = [("p", ast::Ty::Bool), ("q", ast::Ty::Bool)];
+        assert_snapshot!(typed_expr(&locals, "p == q").unwrap(), @"(Bool.eqb p q)");
+        assert_snapshot!(typed_expr(&locals, "p == (1 < 2)").unwrap(), @r###"
+        (Bool.eqb p (Nat.ltb 1 2)
+        )
+        "###);
+    }
+
+    #[test]
+    fn test_type_errors() {
+        let locals = [("a", ast::Ty::Nat), ("b", ast::Ty::Bool), ("z", ast::Ty::Z)];
+        let err = |input| typed_expr(&locals, input).unwrap_err().to_string();
+        assert_snapshot!(err("-a"), @"can't negate a value of type `nat`");
+        assert_snapshot!(err("a + z"), @"mismatched types: expected `nat`, found `Z`");
+        assert_snapshot!(err("b + b"), @"operator `+` isn't supported on `bool`");
+        assert_snapshot!(err("b < b"), @"operator `<` isn't supported on `bool`");
+        assert_snapshot!(err("a && b"), @"mismatched types: expected `bool`, found `nat`");
+        assert_snapshot!(err("!a"), @"operator `!` isn't supported on `nat`");
+        assert_snapshot!(err("b > b"), @"operator `>` isn't supported on `bool`");
+        assert_snapshot!(err("b == 1"), @"mismatched types: expected `bool`, found an integer");
+    }
 }
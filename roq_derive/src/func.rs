@@ -1,26 +1,289 @@
+use proc_macro2::Span;
 use roq_core::ast;
+use roq_core::function::Function;
+use roq_core::typing::{Numbers, Typing};
 use syn::spanned::Spanned;
 
-use crate::{block::block_as_ast, ty::type_as_ast};
+use crate::block::{return_state, stmts_as_ast, strip_returns};
+use crate::context::Context;
+use crate::expr::expr_as_ast;
+use crate::pat::{is_irrefutable, mutable_vars, pat_as_ast};
+use crate::recursion::{self, find_decreasing_arg};
+use crate::ty::{check_type_names, default_numbers, type_params, use_type, TypeScope};
 
-pub fn func_as_ast(source: &syn::ItemFn) -> syn::Result<ast::Definition> {
-    let name = source.sig.ident.to_string();
+/// A translated function, along with the other definitions it calls.
+pub struct Translation {
+    pub function: Function,
+    pub callees: Vec<syn::Path>,
+    pub types: Vec<syn::Path>,
 
-    // Map the return type, which is mandatory.
+    /// The span of the function's body, where errors elaborating it are reported if they aren't
+    /// at any `ast::Loc`.
+    pub span: Span,
+
+    /// The spans each `ast::Loc` in the function refers to.
+    pub spans: Vec<Span>,
+}
+
+/// The options given to `#[definition(...)]`.
+#[derive(Debug)]
+pub struct Options {
+    /// Whether arithmetic on `u64`s panics where Rust's would: when it overflows or underflows,
+    /// or divides by zero. This is the default for `nat`s and `N`s, unless `unchecked` is given.
+    pub checked: bool,
+
+    /// The Coq type unsigned integers are modelled with, given by `numbers = "..."`.
+    pub numbers: Numbers,
+}
+
+/// The options of a function without any given: checked arithmetic on `nat`s.
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            checked: true,
+            numbers: Numbers::default(),
+        }
+    }
+}
+
+impl Options {
+    pub fn parse(attr: proc_macro2::TokenStream) -> syn::Result<Options> {
+        let mut options = Options {
+            numbers: default_numbers()?,
+            ..Options::default()
+        };
+        let mut checked = None;
+        let mut unchecked = None;
+        let parser = syn::meta::parser(|meta| {
+            if meta.path.is_ident("checked") {
+                checked = Some(meta.path.span());
+                Ok(())
+            } else if meta.path.is_ident("unchecked") {
+                unchecked = Some(meta.path.span());
+                Ok(())
+            } else if meta.path.is_ident("numbers") {
+                let numbers: syn::LitStr = meta.value()?.parse()?;
+                options.numbers = numbers
+                    .value()
+                    .parse()
+                    .map_err(|err: String| syn::Error::new(numbers.span(), err))?;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported option, expected `checked`, `unchecked` or `numbers`"))
+            }
+        });
+        syn::parse::Parser::parse2(parser, attr)?;
+        if let (Some(_), Some(span)) = (checked, unchecked) {
+            return Err(syn::Error::new(
+                span,
+                "a function can't be both `checked` and `unchecked`",
+            ));
+        }
+
+        // Words already wrap around rather than overflowing, and a `Z` could be a signed integer,
+        // which isn't checked.
+        if let (Some(span), Numbers::Z | Numbers::Word) = (checked, options.numbers) {
+            return Err(syn::Error::new(
+                span,
+                "`checked` is only supported with `numbers = \"nat\"` or `numbers = \"N\"`",
+            ));
+        }
+        options.checked =
+            unchecked.is_none() && matches!(options.numbers, Numbers::Nat | Numbers::N);
+        Ok(options)
+    }
+}
+
+pub fn func_as_ast(source: &syn::ItemFn, options: &Options) -> syn::Result<Translation> {
+    translate(source, source.sig.ident.to_string(), None, None, options)
+}
+
+/// Whether a function takes any `&mut` arguments, including `&mut self`.
+fn takes_mut(source: &syn::ItemFn) -> bool {
+    source.sig.inputs.iter().any(|arg| match arg {
+        syn::FnArg::Receiver(receiver) => {
+            receiver.reference.is_some() && receiver.mutability.is_some()
+        }
+        syn::FnArg::Typed(pat) => is_mut_ref(&pat.ty),
+    })
+}
+
+/// Whether `ty` is a `&mut` reference.
+fn is_mut_ref(ty: &syn::Type) -> bool {
+    matches!(ty, syn::Type::Reference(reference) if reference.mutability.is_some())
+}
+
+/// The Coq name of the inherent method `method` of the type named `ty`.
+pub fn method_name(ty: &str, method: &str) -> ast::Ident {
+    format!("{ty}_{method}")
+}
+
+/// Convert each method of an inherent `impl` block to a function named `Type_method`, which takes
+/// `self` as its first argument, like `method_as_ast`, and return them along with their names.
+/// Calls of the type's other methods, like `self.area()` or `Self::new(...)`, call the functions
+/// they're translated to.
+pub fn impl_block_as_ast(
+    source: &syn::ItemImpl,
+    options: &Options,
+) -> syn::Result<Vec<(ast::Ident, Translation)>> {
+    if let Some((_, trait_path, _)) = &source.trait_ {
+        return Err(syn::Error::new(
+            trait_path.span(),
+            "expected an inherent `impl` block, use `#[instance]` for an implementation of a trait",
+        ));
+    }
+    if !source.generics.params.is_empty() || source.generics.where_clause.is_some() {
+        return Err(syn::Error::new(
+            source.generics.span(),
+            "can't generate Coq `Definition` for the methods of a generic `impl` block",
+        ));
+    }
+    let syn::Type::Path(self_path) = &*source.self_ty else {
+        return Err(syn::Error::new(
+            source.self_ty.span(),
+            "expected a user-defined type, like a `#[record]` struct",
+        ));
+    };
+    let ty = self_path.path.segments.last().unwrap().ident.to_string();
+
+    let mut translations = vec![];
+    for item in &source.items {
+        let syn::ImplItem::Fn(method) = item else {
+            return Err(syn::Error::new(
+                item.span(),
+                "expected a method, other items aren't supported",
+            ));
+        };
+        let name = method_name(&ty, &method.sig.ident.to_string());
+        let translation = translate(
+            &method_item(method),
+            name.clone(),
+            Some(&source.self_ty),
+            Some(ty.clone()),
+            options,
+        )?;
+        translations.push((name, translation));
+    }
+    Ok(translations)
+}
+
+/// A method of an `impl` block, as a free function with the same signature and body.
+pub fn method_item(method: &syn::ImplItemFn) -> syn::ItemFn {
+    syn::ItemFn {
+        attrs: method.attrs.clone(),
+        vis: method.vis.clone(),
+        sig: method.sig.clone(),
+        block: Box::new(method.block.clone()),
+    }
+}
+
+/// Convert a method of an `impl` block for `self_ty` to a function named `name`, which takes the
+/// value the method is called on, `self`, as its first argument. Methods are never recursive,
+/// since a call to a function of the same name isn't a call to the method.
+pub fn method_as_ast(
+    source: &syn::ItemFn,
+    self_ty: &syn::Type,
+    name: ast::Ident,
+    options: &Options,
+) -> syn::Result<Translation> {
+    translate(source, name, Some(self_ty), None, options)
+}
+
+/// Convert a function, or a method of `self_ty` if that's given. The methods of an inherent `impl`
+/// block are given the name of the type they're methods of, `methods_of`, too.
+fn translate(
+    source: &syn::ItemFn,
+    name: ast::Ident,
+    self_ty: Option<&syn::Type>,
+    methods_of: Option<ast::Ident>,
+    options: &Options,
+) -> syn::Result<Translation> {
+    // Functions which call themselves become a `Fixpoint`, which needs a decreasing argument,
+    // unless they're given a measure, in which case they become a `Program Fixpoint`.
+    let mut cx = Context::new(name.clone());
+    cx.methods_of = methods_of;
+    let (params, bounds) = type_params(&source.sig.generics)?;
+    cx.scope = TypeScope {
+        numbers: options.numbers,
+        params,
+        self_ty: None,
+    };
+    if let Some(self_ty) = self_ty {
+        cx.scope.self_ty = Some(use_type(&mut cx.types, &cx.scope, self_ty)?);
+    }
+    let measure = recursion::measure(&source.attrs)?;
+    match &measure {
+        Some(measure) if self_ty.is_some() || !recursion::is_recursive(source) => {
+            return Err(syn::Error::new(
+                measure.span(),
+                "only recursive functions need a measure",
+            ))
+        }
+        Some(_) => {}
+        None if self_ty.is_some() => {}
+        None => cx.recursion = find_decreasing_arg(source, &cx.scope)?,
+    }
+
+    // Map the return type, which is mandatory, unless the function takes `&mut` arguments, whose
+    // new values it returns.
     let ret = match &source.sig.output {
+        syn::ReturnType::Default if takes_mut(source) => ast::Ty::unit(),
         syn::ReturnType::Default => {
             return Err(syn::Error::new(
                 source.sig.output.span(),
                 "expected a return type",
             ))
         }
-        syn::ReturnType::Type(_, ty) => type_as_ast(ty)?,
+        syn::ReturnType::Type(_, ty) => use_type(&mut cx.types, &cx.scope, ty)?,
     };
 
-    // Map each of the arguments.
+    // Map each of the arguments, after the type parameters and the instances of the classes
+    // they're bounded by, which are implicit. Those which are destructured by a pattern are given
+    // a fresh name, and destructured at the start of the body.
     let mut args = vec![];
+    for param in &cx.scope.params {
+        args.push(ast::Binder {
+            name: param.clone(),
+            ty: ast::Ty::Type,
+            implicit: true,
+        });
+        for (_, class) in bounds.iter().filter(|(bounded, _)| bounded == param) {
+            args.push(ast::Binder::instance(class, ast::Ty::Var(param.clone())));
+        }
+    }
+    let mut destructured = vec![];
+    let mut assignable = vec![];
+    let mut state = vec![];
     for arg in &source.sig.inputs {
         match arg {
+            // An inherent method taking `&mut self` takes and returns its value, like any other
+            // `&mut` argument.
+            syn::FnArg::Receiver(receiver)
+                if cx.methods_of.is_some()
+                    && receiver.reference.is_some()
+                    && receiver.mutability.is_some() =>
+            {
+                args.push(ast::Binder {
+                    name: "self".into(),
+                    ty: cx.scope.self_ty.clone().unwrap(),
+                    implicit: false,
+                });
+                assignable.push("self".into());
+                state.push(("self".into(), cx.scope.self_ty.clone().unwrap()));
+            }
+
+            // The type of `self` is written `Self`, `&Self` or the like, even if it's elided.
+            syn::FnArg::Receiver(receiver) if self_ty.is_some() => {
+                let ty = use_type(&mut cx.types, &cx.scope, &receiver.ty)?;
+                args.push(ast::Binder {
+                    name: "self".into(),
+                    ty,
+                    implicit: false,
+                });
+                if receiver.reference.is_none() && receiver.mutability.is_some() {
+                    assignable.push("self".into());
+                }
+            }
             syn::FnArg::Receiver(_) => {
                 return Err(syn::Error::new(
                     arg.span(),
@@ -28,30 +291,117 @@ pub fn func_as_ast(source: &syn::ItemFn) -> syn::Result<ast::Definition> {
                 ))
             }
 
+            // A `&mut` argument is passed by value, and its new value is returned along with the
+            // function's result, so it can be assigned to like a `mut` variable.
+            syn::FnArg::Typed(syn::PatType { pat, ty, .. }) if is_mut_ref(ty) => {
+                let syn::Type::Reference(reference) = &**ty else {
+                    unreachable!();
+                };
+                let ast::Pattern::Ident(name) = pat_as_ast(pat)? else {
+                    return Err(syn::Error::new(
+                        pat.span(),
+                        "expected a variable for a `&mut` argument",
+                    ));
+                };
+                let ty = use_type(&mut cx.types, &cx.scope, &reference.elem)?;
+                args.push(ast::Binder {
+                    name: name.clone(),
+                    ty: ty.clone(),
+                    implicit: false,
+                });
+                assignable.push(name.clone());
+                state.push((name, ty));
+            }
+
             syn::FnArg::Typed(pat) => {
-                let name = match &*pat.pat {
-                    syn::Pat::Ident(ident) => ident.ident.to_string(),
+                let name = match pat_as_ast(&pat.pat)? {
+                    ast::Pattern::Ident(name) => name,
+                    ast::Pattern::Wildcard => cx.fresh("arg"),
+                    pattern if is_irrefutable(&pattern) => {
+                        let name = cx.fresh("arg");
+                        destructured.push((pattern, name.clone()));
+                        name
+                    }
                     _ => {
                         return Err(syn::Error::new(
                             pat.pat.span(),
-                            "expected a single identifier, not a pattern, in function argument",
+                            "expected an irrefutable pattern in function argument",
                         ))
                     }
                 };
-                let ty = type_as_ast(&pat.ty)?;
-                args.push(ast::Binder { name, ty });
+                let ty = use_type(&mut cx.types, &cx.scope, &pat.ty)?;
+                args.push(ast::Binder {
+                    name,
+                    ty,
+                    implicit: false,
+                });
+                assignable.extend(mutable_vars(&pat.pat));
             }
         }
     }
 
-    // Parse the body of the statement.
-    let body = block_as_ast(&source.block)?;
-
-    Ok(ast::Definition {
+    // Parse the body of the statement, then infer the types within it to pick the Coq function
+    // for each operator.
+    cx.assignable = assignable;
+    let measure = measure
+        .map(|measure| expr_as_ast(&mut cx, &measure))
+        .transpose()?;
+    let state_vars = state
+        .iter()
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
+    let mut block = source.block.clone();
+    if let (syn::ReturnType::Default, false) = (&source.sig.output, state.is_empty()) {
+        block
+            .stmts
+            .push(syn::Stmt::Expr(syn::parse_quote!(()), None));
+    }
+    let mut body = return_state(strip_returns(stmts_as_ast(&mut cx, &block)?), &state_vars);
+    let ret = std::iter::once(ret)
+        .chain(state.into_iter().map(|(_, ty)| ty))
+        .reduce(ast::Ty::prod)
+        .unwrap();
+    for (pattern, name) in destructured.into_iter().rev() {
+        body = ast::Expr::LetPattern {
+            pattern: Box::new(pattern),
+            ty: None,
+            value: Box::new(ast::Expr::Var(name)),
+            child: Box::new(body),
+        };
+    }
+    let function = Function {
         name,
         args,
         ret,
         body,
+        decreasing: cx.recursion.take().map(|recursion| recursion.arg),
+        measure,
+        bounds,
+        checked: options.checked,
+        numbers: options.numbers,
+
+        // Filled in from `span` and `spans` by the module emitted alongside the function, where
+        // the spans' lines and columns are known.
+        location: String::new(),
+        locations: vec![],
+    };
+
+    // Report type errors here, where they have spans, though calls and the fields of records
+    // can't be elaborated until the functions and types they use are known, when the function's
+    // vernacular is built. Errors in those are reported then, at the lines of `spans`.
+    let mut typing = Typing::default();
+    typing.partial = true;
+    function
+        .elaborate(&mut typing)
+        .map_err(|err| cx.type_error(err, source.block.span()))?;
+    check_type_names(&cx.types)?;
+
+    Ok(Translation {
+        function,
+        callees: cx.callees,
+        types: cx.types,
+        span: source.block.span(),
+        spans: cx.spans,
     })
 }
 
@@ -64,8 +414,61 @@ mod tests {
         syn::parse_str(input).expect("Failed to parse source code")
     }
 
-    fn test_as_def(input: &str) -> ast::Definition {
-        func_as_ast(&parse(input)).expect("Failed to convert function to definition")
+    /// Elaborate a translated function on its own, without the signatures of the functions it
+    /// calls, returning its statement along with the modules and auxiliary statements it needs.
+    fn elaborate(translation: &Translation) -> (ast::Statement, Typing) {
+        let mut typing = Typing::default();
+        let statement = translation
+            .function
+            .elaborate(&mut typing)
+            .expect("Failed to elaborate function");
+        (statement, typing)
+    }
+
+    fn test_as_def(input: &str) -> ast::Statement {
+        let translation = func_as_ast(&parse(input), &Options::default())
+            .expect("Failed to convert function to definition");
+        elaborate(&translation).0
+    }
+
+    /// Convert a function, along with its auxiliary statements.
+    fn test_as_vernacular(input: &str) -> ast::Vernacular {
+        test_with_options(input, Options::default())
+    }
+
+    /// Convert a function with `unchecked` arithmetic, along with its auxiliary statements.
+    fn test_as_unchecked(input: &str) -> ast::Vernacular {
+        let options = Options {
+            checked: false,
+            ..Options::default()
+        };
+        test_with_options(input, options)
+    }
+
+    /// Convert a function with unsigned integers modelled by `numbers`, along with its auxiliary
+    /// statements. Its arithmetic is checked, unless they're `Z`s or words, as it is by default.
+    fn test_as_numbers(input: &str, numbers: Numbers) -> ast::Vernacular {
+        let options = Options {
+            checked: matches!(numbers, Numbers::Nat | Numbers::N),
+            numbers,
+        };
+        test_with_options(input, options)
+    }
+
+    fn test_with_options(input: &str, options: Options) -> ast::Vernacular {
+        let translation = func_as_ast(&parse(input), &options).expect("Failed to convert function");
+        let (statement, typing) = elaborate(&translation);
+        let mut vernacular = ast::Vernacular::default();
+        vernacular.extend(typing.auxiliary);
+        vernacular.push(statement);
+        vernacular
+    }
+
+    fn test_as_err(input: &str) -> String {
+        func_as_ast(&parse(input), &Options::default())
+            .err()
+            .expect("Expected function conversion to fail")
+            .to_string()
     }
 
     #[test]
@@ -77,8 +480,23 @@ mod tests {
                 }
             "#),
             @r###"
-        Definition add (a: nat) (b: nat) : nat :=
-        	(plus a b)
+        Program Definition add (a: nat) (b: nat) : nat :=
+        	match (fits_u64 (plus a b)
+        	)
+        	 with
+        	| true =>
+        		(plus a b)
+        	| false =>
+        		match (andb (fits_u64 a)
+        		 (fits_u64 b)
+        		)
+        		 with
+        		| true =>
+        			(False_rect _ _)
+        		| false =>
+        			(plus a b)
+        		end
+        	end
         .
         "###
         );
@@ -93,8 +511,21 @@ mod tests {
                 }
             "#),
             @r###"
-        Definition add (a: nat) : nat :=
-        	(plus a 1)
+        Program Definition add (a: nat) : nat :=
+        	match (fits_u64 (plus a 1)
+        	)
+        	 with
+        	| true =>
+        		(plus a 1)
+        	| false =>
+        		match (fits_u64 a)
+        		 with
+        		| true =>
+        			(False_rect _ _)
+        		| false =>
+        			(plus a 1)
+        		end
+        	end
         .
         "###
         );
@@ -110,9 +541,24 @@ mod tests {
                 }
             "#),
             @r###"
-        Definition add (a: nat) : nat :=
+        Program Definition add (a: nat) : nat :=
         	let x := 1 in
-        	(plus a x)
+        	match (fits_u64 (plus a x)
+        	)
+        	 with
+        	| true =>
+        		(plus a x)
+        	| false =>
+        		match (andb (fits_u64 a)
+        		 (fits_u64 x)
+        		)
+        		 with
+        		| true =>
+        			(False_rect _ _)
+        		| false =>
+        			(plus a x)
+        		end
+        	end
         .
         "###
         );
@@ -129,12 +575,3713 @@ mod tests {
                 }
             "#),
             @r###"
-        Definition add (a: nat) : nat :=
+        Program Definition add (a: nat) : nat :=
         	let x := 1 in
         	let y := 2 in
-        	(plus x y)
+        	match (fits_u64 (plus x y)
+        	)
+        	 with
+        	| true =>
+        		(plus x y)
+        	| false =>
+        		match (andb (andb (fits_u64 a)
+        		 (fits_u64 x)
+        		)
+        		 (fits_u64 y)
+        		)
+        		 with
+        		| true =>
+        			(False_rect _ _)
+        		| false =>
+        			(plus x y)
+        		end
+        	end
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_call_other_definition() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn quadruple(a: u64) -> u64 {
+                    double(double(a))
+                }
+            "#),
+            @r###"
+        Definition quadruple (a: nat) : nat :=
+        	(double (double a)
+        	)
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_fixpoint_factorial() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn factorial(n: u64) -> u64 {
+                    if n == 0 {
+                        1
+                    } else {
+                        n * factorial(n - 1)
+                    }
+                }
+            "#),
+            @r###"
+        Program Fixpoint factorial (n: nat) {struct n} : nat :=
+        	match n with
+        	| S n' =>
+        		let rhs' := (factorial n')
+        		 in
+        		match (fits_u64 (mult n rhs')
+        		)
+        		 with
+        		| true =>
+        			(mult n rhs')
+        		| false =>
+        			match (andb (fits_u64 n)
+        			 (fits_u64 rhs')
+        			)
+        			 with
+        			| true =>
+        				(False_rect _ _)
+        			| false =>
+        				(mult n rhs')
+        			end
+        		end
+        	| _ =>
+        		1
+        	end
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_fixpoint_fib() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn fib(n: u64) -> u64 {
+                    if n < 2 {
+                        n
+                    } else {
+                        fib(n - 1) + fib(n - 2)
+                    }
+                }
+            "#),
+            @r###"
+        Program Fixpoint fib (n: nat) {struct n} : nat :=
+        	match n with
+        	| S (S n'' as n') =>
+        		let lhs' := (fib n')
+        		 in
+        		let rhs' := (fib n'')
+        		 in
+        		match (fits_u64 (plus lhs' rhs')
+        		)
+        		 with
+        		| true =>
+        			(plus lhs' rhs')
+        		| false =>
+        			match (andb (andb (fits_u64 n)
+        			 (fits_u64 lhs')
+        			)
+        			 (fits_u64 rhs')
+        			)
+        			 with
+        			| true =>
+        				(False_rect _ _)
+        			| false =>
+        				(plus lhs' rhs')
+        			end
+        		end
+        	| _ =>
+        		n
+        	end
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_fixpoint_second_arg() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn sum_to(acc: u64, n: u64) -> u64 {
+                    if 0 < n {
+                        sum_to(acc + n, n - 1)
+                    } else {
+                        acc
+                    }
+                }
+            "#),
+            @r###"
+        Program Fixpoint sum_to (acc: nat) (n: nat) {struct n} : nat :=
+        	match n with
+        	| S n' =>
+        		(sum_to match (fits_u64 (plus acc n)
+        		)
+        		 with
+        		| true =>
+        			(plus acc n)
+        		| false =>
+        			match (andb (fits_u64 acc)
+        			 (fits_u64 n)
+        			)
+        			 with
+        			| true =>
+        				(False_rect _ _)
+        			| false =>
+        				(plus acc n)
+        			end
+        		end
+        		 n')
+        	| _ =>
+        		acc
+        	end
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_fixpoint_not_decreasing() {
+        assert_snapshot!(
+            test_as_err(r#"
+                fn forever(n: u64) -> u64 {
+                    forever(n + 1)
+                }
+            "#),
+            @"can't find a structurally decreasing argument for this recursive call; expected `n - 1` (or `n - k`) to be passed for some `u64` argument `n`"
+        );
+    }
+
+    #[test]
+    fn test_fixpoint_unguarded() {
+        assert_snapshot!(
+            test_as_err(r#"
+                fn countdown(n: u64) -> u64 {
+                    countdown(n - 1)
+                }
+            "#),
+            @"recursive call on `n - k` must be guarded by a check that `n >= k`"
+        );
+    }
+
+    #[test]
+    fn test_same_type_names() {
+        assert_snapshot!(
+            test_as_err(r#"
+                fn apart(a: screen::Point, b: paper::Point) -> bool {
+                    true
+                }
+            "#),
+            @"can't use both `screen::Point` and `paper::Point`, which have the same name; if they're the same type, refer to it by the same path"
+        );
+    }
+
+    #[test]
+    fn test_fixpoint_missing_argument() {
+        assert_snapshot!(
+            test_as_err(r#"
+                fn countdown(a: u64, n: u64) -> u64 {
+                    if n >= 1 {
+                        assert!(countdown(a) > 0);
+                        countdown(a, n - 1)
+                    } else {
+                        a
+                    }
+                }
+            "#),
+            @"expected the decreasing argument `n` in this recursive call"
+        );
+    }
+
+    #[test]
+    fn test_signed() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn abs(x: i64) -> i64 {
+                    if x < 0 {
+                        -x
+                    } else {
+                        x
+                    }
+                }
+            "#),
+            @r###"
+        Definition abs (x: Z) : Z :=
+        	match (Z.ltb x 0%Z)
+        	 with
+        	| true =>
+        		(Z.opp x)
+        	| false =>
+        		x
+        	end
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_signed_let() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn offset(x: i64) -> i64 {
+                    let d: i64 = 3;
+                    let y = x - d;
+                    y * 2
+                }
+            "#),
+            @r###"
+        Definition offset (x: Z) : Z :=
+        	let d : Z := 3%Z in
+        	let y := (Z.sub x d)
+        	 in
+        	(Z.mul y 2%Z)
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_signed_literal_tail() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn sign(x: i64) -> i64 {
+                    if x < 0 {
+                        -1
+                    } else if x == 0 {
+                        0
+                    } else {
+                        1
+                    }
+                }
+            "#),
+            @r###"
+        Definition sign (x: Z) : Z :=
+        	match (Z.ltb x 0%Z)
+        	 with
+        	| true =>
+        		(-1)%Z
+        	| false =>
+        		match (Z.eqb x 0%Z)
+        		 with
+        		| true =>
+        			0%Z
+        		| false =>
+        			1%Z
+        		end
+        	end
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_signed_requires() {
+        let translation = func_as_ast(
+            &parse(
+                r#"
+                fn neg(x: i64) -> i64 {
+                    -x
+                }
+            "#,
+            ),
+            &Options::default(),
+        )
+        .unwrap();
+        assert_eq!(elaborate(&translation).1.requires, ["ZArith"]);
+
+        let translation = func_as_ast(
+            &parse(
+                r#"
+                fn id(x: u64) -> u64 {
+                    x
+                }
+            "#,
+            ),
+            &Options::default(),
+        )
+        .unwrap();
+        assert!(elaborate(&translation).1.requires.is_empty());
+    }
+
+    #[test]
+    fn test_bool_args() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn xor(a: bool, b: bool) -> bool {
+                    (a == b) == false || false
+                }
+            "#),
+            @r###"
+        Definition xor (a: bool) (b: bool) : bool :=
+        	(orb (Bool.eqb (Bool.eqb a b)
+        	 false)
+        	 false)
+        .
+        "###
+        );
+        assert_snapshot!(
+            test_as_def(r#"
+                fn xor(a: bool, b: bool) -> bool {
+                    !(a == b) || false
+                }
+            "#),
+            @r###"
+        Definition xor (a: bool) (b: bool) : bool :=
+        	(orb (negb (Bool.eqb a b)
+        	)
+        	 false)
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_literal_from_return_type() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn seven() -> i64 {
+                    let x = 3 + 4;
+                    x
+                }
+            "#),
+            @r###"
+        Program Definition seven : Z :=
+        	let x := (Z.add 3%Z 4%Z)
+        	 in
+        	x
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_type_mismatch() {
+        assert_snapshot!(
+            test_as_err(r#"
+                fn f(a: u64, b: i64) -> i64 {
+                    let c: i64 = 2;
+                    c * (a + b)
+                }
+            "#),
+            @"mismatched types: expected `Z`, found `nat`"
+        );
+        assert_snapshot!(
+            test_as_err(r#"
+                fn g(a: u64) -> bool {
+                    a + 1
+                }
+            "#),
+            @"mismatched types: expected `bool`, found `nat`"
+        );
+    }
+
+    #[test]
+    fn test_compound_assign() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn mix(mut acc: u64, byte: u64) -> u64 {
+                    acc ^= byte;
+                    acc *= 31;
+                    let mut flags = acc & 0xf0;
+                    flags |= 1;
+                    flags = flags << 2;
+                    flags
+                }
+            "#),
+            @r###"
+        Program Definition mix (acc: nat) (byte: nat) : nat :=
+        	let acc := (Nat.lxor acc byte)
+        	 in
+        	let acc := match (fits_u64 (mult acc 31)
+        	)
+        	 with
+        	| true =>
+        		(mult acc 31)
+        	| false =>
+        		match (andb (fits_u64 byte)
+        		 (fits_u64 acc)
+        		)
+        		 with
+        		| true =>
+        			(False_rect _ _)
+        		| false =>
+        			(mult acc 31)
+        		end
+        	end
+        	 in
+        	let flags := (Nat.land acc 240)
+        	 in
+        	let flags := (Nat.lor flags 1)
+        	 in
+        	let flags := (wrap_u64_nat (Nat.shiftl flags 2)
+        	)
+        	 in
+        	flags
         .
         "###
         );
     }
+
+    #[test]
+    fn test_assign_outer_variable() {
+        assert_snapshot!(
+            test_as_err(r#"
+                fn f(a: u64) -> u64 {
+                    let mut x = a;
+                    let y = if a > 1 {
+                        x += 1;
+                        x
+                    } else {
+                        x
+                    };
+                    x + y
+                }
+            "#),
+            @"can't assign to a variable unless it's declared `mut` in the current block"
+        );
+        assert_snapshot!(
+            test_as_err(r#"
+                fn f(a: u64) -> u64 {
+                    let x = a;
+                    x += 1;
+                    x
+                }
+            "#),
+            @"can't assign to a variable unless it's declared `mut` in the current block"
+        );
+    }
+
+    #[test]
+    fn test_assign_in_if() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn clamp(value: i64, lo: i64, hi: i64) -> i64 {
+                    let mut result = value;
+                    let mut clamped = false;
+                    if value < lo {
+                        result = lo;
+                        clamped = true;
+                    } else if value > hi {
+                        result = hi;
+                        clamped = true;
+                    }
+                    if clamped {
+                        result *= 2;
+                    }
+                    result
+                }
+            "#),
+            @r###"
+        Definition clamp (value: Z) (lo: Z) (hi: Z) : Z :=
+        	let result := value in
+        	let clamped := false in
+        	let '(result, clamped) := match (Z.ltb value lo)
+        	 with
+        	| true =>
+        		let result := lo in
+        		let clamped := true in
+        		(result, clamped)
+        	| false =>
+        		match (Z.ltb hi value)
+        		 with
+        		| true =>
+        			let result := hi in
+        			let clamped := true in
+        			(result, clamped)
+        		| false =>
+        			(result, clamped)
+        		end
+        	end
+        	 in
+        	let result := match clamped with
+        	| true =>
+        		let result := (Z.mul result 2%Z)
+        		 in
+        		result
+        	| false =>
+        		result
+        	end
+        	 in
+        	result
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_assign_in_if_let() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn add_all(a: Option<u64>, b: Option<u64>) -> u64 {
+                    let mut total = 0;
+                    if let Some(x) = a {
+                        total += x;
+                    }
+                    if let Some(y) = b {
+                        let doubled = y * 2;
+                        total += doubled;
+                    } else {
+                        total = 0;
+                    }
+                    total
+                }
+            "#),
+            @r###"
+        Program Definition add_all (a: option nat) (b: option nat) : nat :=
+        	let total := 0 in
+        	let total := match a with
+        	| Some x =>
+        		let total := match (fits_u64 (plus total x)
+        		)
+        		 with
+        		| true =>
+        			(plus total x)
+        		| false =>
+        			match (andb (fits_u64 total)
+        			 (fits_u64 x)
+        			)
+        			 with
+        			| true =>
+        				(False_rect _ _)
+        			| false =>
+        				(plus total x)
+        			end
+        		end
+        		 in
+        		total
+        	| _ =>
+        		total
+        	end
+        	 in
+        	let total := match b with
+        	| Some y =>
+        		let doubled := match (fits_u64 (mult y 2)
+        		)
+        		 with
+        		| true =>
+        			(mult y 2)
+        		| false =>
+        			match (andb (fits_u64 total)
+        			 (fits_u64 y)
+        			)
+        			 with
+        			| true =>
+        				(False_rect _ _)
+        			| false =>
+        				(mult y 2)
+        			end
+        		end
+        		 in
+        		let total := match (fits_u64 (plus total doubled)
+        		)
+        		 with
+        		| true =>
+        			(plus total doubled)
+        		| false =>
+        			match (andb (andb (fits_u64 total)
+        			 (fits_u64 y)
+        			)
+        			 (fits_u64 doubled)
+        			)
+        			 with
+        			| true =>
+        				(False_rect _ _)
+        			| false =>
+        				(plus total doubled)
+        			end
+        		end
+        		 in
+        		total
+        	| _ =>
+        		let total := 0 in
+        		total
+        	end
+        	 in
+        	total
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_assign_in_tail_if() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn f(mut a: u64) -> u64 {
+                    if a > 10 {
+                        a -= 10;
+                        a * 2
+                    } else {
+                        a
+                    }
+                }
+            "#),
+            @r###"
+        Program Definition f (a: nat) : nat :=
+        	match (Nat.ltb 10 a)
+        	 with
+        	| true =>
+        		let a := match (Nat.leb 10 a)
+        		 with
+        		| true =>
+        			(minus a 10)
+        		| false =>
+        			(False_rect _ _)
+        		end
+        		 in
+        		match (fits_u64 (mult a 2)
+        		)
+        		 with
+        		| true =>
+        			(mult a 2)
+        		| false =>
+        			match (fits_u64 a)
+        			 with
+        			| true =>
+        				(False_rect _ _)
+        			| false =>
+        				(mult a 2)
+        			end
+        		end
+        	| false =>
+        		a
+        	end
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_discarded_statements() {
+        assert_snapshot!(
+            test_as_err(r#"
+                fn f(a: u64) -> u64 {
+                    g(a);
+                    a
+                }
+            "#),
+            @"expected a statement which assigns to a variable, since the value of this one is discarded"
+        );
+        assert_snapshot!(
+            test_as_err(r#"
+                fn f(a: u64) -> u64 {
+                    if a > 1 {
+                        g(a);
+                    }
+                    a
+                }
+            "#),
+            @"expected a statement which assigns to a variable, since the value of this one is discarded"
+        );
+        assert_snapshot!(
+            test_as_err(r#"
+                fn f(a: u64) -> u64 {
+                    println!("{a}");
+                    a
+                }
+            "#),
+            @"unsupported macro, expected `vec!`, `panic!`, `unreachable!` or an assertion"
+        );
+    }
+
+    #[test]
+    fn test_record_fields() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn area(rect: Rect) -> u64 {
+                    rect.width * rect.height
+                }
+            "#),
+            @r###"
+        Program Definition area (rect: Rect) : nat :=
+        	let lhs' := (Rect_width rect)
+        	 in
+        	let rhs' := (Rect_height rect)
+        	 in
+        	match (fits_u64 (mult lhs' rhs')
+        	)
+        	 with
+        	| true =>
+        		(mult lhs' rhs')
+        	| false =>
+        		match (andb (fits_u64 lhs')
+        		 (fits_u64 rhs')
+        		)
+        		 with
+        		| true =>
+        			(False_rect _ _)
+        		| false =>
+        			(mult lhs' rhs')
+        		end
+        	end
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_record_literal() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn square(side: u64) -> Rect {
+                    Rect { width: side, height: side }
+                }
+            "#),
+            @r###"
+        Definition square (side: nat) : Rect :=
+        	{| Rect_width := side; Rect_height := side |}
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_record_update() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn widen(rect: Rect, extra: u64) -> Rect {
+                    let wide: Rect = Rect { width: rect.width + extra, ..rect };
+                    Rect { height: wide.height * 2, ..wide }
+                }
+            "#),
+            @r###"
+        Program Definition widen (rect: Rect) (extra: nat) : Rect :=
+        	let wide : Rect := (Rect_set_width rect let lhs' := (Rect_width rect)
+        	 in
+        	match (fits_u64 (plus lhs' extra)
+        	)
+        	 with
+        	| true =>
+        		(plus lhs' extra)
+        	| false =>
+        		match (andb (fits_u64 extra)
+        		 (fits_u64 lhs')
+        		)
+        		 with
+        		| true =>
+        			(False_rect _ _)
+        		| false =>
+        			(plus lhs' extra)
+        		end
+        	end
+        	)
+        	 in
+        	(Rect_set_height wide let lhs' := (Rect_height wide)
+        	 in
+        	match (fits_u64 (mult lhs' 2)
+        	)
+        	 with
+        	| true =>
+        		(mult lhs' 2)
+        	| false =>
+        		match (andb (fits_u64 extra)
+        		 (fits_u64 lhs')
+        		)
+        		 with
+        		| true =>
+        			(False_rect _ _)
+        		| false =>
+        			(mult lhs' 2)
+        		end
+        	end
+        	)
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_record_field_unknown_type() {
+        // The type of the value may only be known once the signatures of the functions called
+        // are, so this is only an error when the function is elaborated with them.
+        let sources = [
+            "fn f(side: u64) -> u64 { let rect = make_rect(side); rect.width }",
+            "fn f(w: &mut Window) { w.size.width = 1; }",
+        ];
+        let errors = sources.map(|source| {
+            let translation = func_as_ast(&parse(source), &Options::default())
+                .expect("Failed to convert function");
+            let mut typing = Typing::default();
+            let err = translation.function.elaborate(&mut typing).unwrap_err();
+            err.to_string()
+        });
+        assert_snapshot!(errors.join("\n"), @r###"
+        can't infer the type of the value whose field `width` is accessed; annotate it with its type (e.g. `let x: T = ...;`)
+        can't infer the type of the value whose field `width` is assigned to; annotate it with its type (e.g. `let x: T = ...;`)
+        "###);
+    }
+
+    #[test]
+    fn test_record_types() {
+        let translation = func_as_ast(
+            &parse(
+                r#"
+                fn f(rect: Rect) -> shapes::Circle {
+                    let w: Window = make(rect);
+                    let p = Point { x: 1, y: 2 };
+                    shapes::Circle { center: p, radius: w.width }
+                }
+            "#,
+            ),
+            &Options::default(),
+        )
+        .unwrap();
+        let types = translation
+            .types
+            .iter()
+            .map(|path| quote::quote!(#path).to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(types, ["shapes :: Circle", "Rect", "Window", "Point"]);
+    }
+
+    #[test]
+    fn test_tuple_return() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn div_rem(a: u64, b: u64) -> (u64, u64) {
+                    (a / b, a % b)
+                }
+            "#),
+            @r###"
+        Program Definition div_rem (a: nat) (b: nat) : prod nat nat :=
+        	(match (negb (Nat.eqb b 0)
+        	)
+        	 with
+        	| true =>
+        		(Nat.div a b)
+        	| false =>
+        		(False_rect _ _)
+        	end
+        	, match (negb (Nat.eqb b 0)
+        	)
+        	 with
+        	| true =>
+        		(Nat.modulo a b)
+        	| false =>
+        		(False_rect _ _)
+        	end
+        	)
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_let_tuple() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn f(a: i64) -> i64 {
+                    let _ = a;
+                    let (x, (y, _)) = (a, (2, 3));
+                    x + y
+                }
+            "#),
+            @r###"
+        Definition f (a: Z) : Z :=
+        	let _ := a in
+        	let '(x, (y, _)) := (a, (2%Z, 3)) in
+        	(Z.add x y)
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_let_discarded() {
+        assert_snapshot!(
+            test_as_vernacular(r#"
+                fn f(a: u64, b: u64) -> u64 {
+                    let _ = a / b;
+                    let _ = if a > b { return a; } else { g(b) };
+                    b
+                }
+            "#),
+            @r###"
+        Program Definition f (a: nat) (b: nat) : nat :=
+        	let _ := match (negb (Nat.eqb b 0)
+        	)
+        	 with
+        	| true =>
+        		(Nat.div a b)
+        	| false =>
+        		(False_rect _ _)
+        	end
+        	 in
+        	match (Nat.ltb b a)
+        	 with
+        	| true =>
+        		a
+        	| false =>
+        		let _ := (g b)
+        		 in
+        		b
+        	end
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_let_tuple_annotated() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn f(p: (i64, bool)) -> i64 {
+                    let (x, flag): (i64, bool) = p;
+                    if flag { x } else { 0 }
+                }
+            "#),
+            @r###"
+        Definition f (p: prod Z bool) : Z :=
+        	let '(x, flag) := (p : prod Z bool) in
+        	match flag with
+        	| true =>
+        		x
+        	| false =>
+        		0%Z
+        	end
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_tuple_args() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn dot((x1, y1): (i64, i64), (x2, y2): (i64, i64), _: bool) -> i64 {
+                    x1 * x2 + y1 * y2
+                }
+            "#),
+            @r###"
+        Definition dot (arg'1: prod Z Z) (arg'2: prod Z Z) (arg'3: bool) : Z :=
+        	let '(x1, y1) := arg'1 in
+        	let '(x2, y2) := arg'2 in
+        	(Z.add (Z.mul x1 x2)
+        	 (Z.mul y1 y2)
+        	)
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_let_refutable() {
+        assert_snapshot!(
+            test_as_err(r#"
+                fn f(o: Option<u64>) -> u64 {
+                    let Some(x) = o;
+                    x
+                }
+            "#),
+            @"expected an irrefutable pattern in local variable"
+        );
+    }
+
+    #[test]
+    fn test_push() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn f(x: i64) -> Vec<i64> {
+                    let mut v = vec![];
+                    v.push(x);
+                    v.push(1);
+                    v
+                }
+            "#),
+            @r###"
+        Definition f (x: Z) : list Z :=
+        	let v := nil in
+        	let v := (app v (x :: nil))
+        	 in
+        	let v := (app v (1%Z :: nil))
+        	 in
+        	v
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_list_requires() {
+        let translation = func_as_ast(
+            &parse(
+                r#"
+                fn f(v: &[i64]) -> Option<i64> {
+                    v.first()
+                }
+            "#,
+            ),
+            &Options::default(),
+        )
+        .unwrap();
+        assert_eq!(elaborate(&translation).1.requires, ["ZArith", "List"]);
+    }
+
+    #[test]
+    fn test_for_range() {
+        assert_snapshot!(
+            test_as_vernacular(r#"
+                fn sum_to(n: u64) -> u64 {
+                    let mut total = 0;
+                    for i in 0..n {
+                        total += i;
+                    }
+                    total
+                }
+            "#),
+            @r###"
+        Definition fits_u64 (n: nat) : bool :=
+        	(N.ltb (N.of_nat n)
+        	 (N.pow 2 64)
+        	)
+        .
+
+
+        Program Fixpoint sum_to_loop'1 (fuel': nat) (i: nat) (total: nat) {struct fuel'} : nat :=
+        	match fuel' with
+        	| O =>
+        		total
+        	| S fuel' =>
+        		let total := match (fits_u64 (plus total i)
+        		)
+        		 with
+        		| true =>
+        			(plus total i)
+        		| false =>
+        			match (andb (fits_u64 i)
+        			 (fits_u64 total)
+        			)
+        			 with
+        			| true =>
+        				(False_rect _ _)
+        			| false =>
+        				(plus total i)
+        			end
+        		end
+        		 in
+        		(sum_to_loop'1 fuel' (plus i 1)
+        		 total)
+        	end
+        .
+
+
+        Definition sum_to (n: nat) : nat :=
+        	let total := 0 in
+        	let total := (sum_to_loop'1 (minus n 0)
+        	 0 total)
+        	 in
+        	total
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_for_range_state() {
+        assert_snapshot!(
+            test_as_vernacular(r#"
+                fn f(lo: i64, hi: i64, step: i64) -> (i64, i64) {
+                    let mut sum = 0;
+                    let mut last = lo;
+                    for x in lo..=hi {
+                        let scaled = x * step;
+                        sum += scaled;
+                        last = x;
+                    }
+                    (sum, last)
+                }
+            "#),
+            @r###"
+        Fixpoint f_loop'1 (fuel': nat) (x: Z) (step: Z) (sum: Z) (last: Z) {struct fuel'} : prod Z Z :=
+        	match fuel' with
+        	| O =>
+        		(sum, last)
+        	| S fuel' =>
+        		let scaled := (Z.mul x step)
+        		 in
+        		let sum := (Z.add sum scaled)
+        		 in
+        		let last := x in
+        		(f_loop'1 fuel' (Z.add x 1%Z)
+        		 step sum last)
+        	end
+        .
+
+
+        Lemma f_loop'1_equation : forall (fuel': nat) (x: Z) (step: Z) (sum: Z) (last: Z),
+        	(f_loop'1 fuel' x step sum last)
+        	 =
+        	match fuel' with
+        	| O =>
+        		(sum, last)
+        	| S fuel' =>
+        		let scaled := (Z.mul x step)
+        		 in
+        		let sum := (Z.add sum scaled)
+        		 in
+        		let last := x in
+        		(f_loop'1 fuel' (Z.add x 1%Z)
+        		 step sum last)
+        	end
+        .
+        Proof.
+        	intros. destruct fuel'; reflexivity.
+        Qed.
+
+
+        Definition f (lo: Z) (hi: Z) (step: Z) : prod Z Z :=
+        	let sum := 0%Z in
+        	let last := lo in
+        	let '(sum, last) := (f_loop'1 (Z.to_nat (Z.sub (Z.add hi 1%Z)
+        	 lo)
+        	)
+        	 lo step sum last)
+        	 in
+        	(sum, last)
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_for_range_nested() {
+        assert_snapshot!(
+            test_as_vernacular(r#"
+                fn f(n: u64) -> u64 {
+                    let mut count = 0;
+                    for i in 0..n {
+                        for _ in 0..i {
+                            count += 1;
+                        }
+                    }
+                    count
+                }
+            "#),
+            @r###"
+        Definition fits_u64 (n: nat) : bool :=
+        	(N.ltb (N.of_nat n)
+        	 (N.pow 2 64)
+        	)
+        .
+
+
+        Program Fixpoint f_loop'2 (fuel': nat) (i'1: nat) (count: nat) {struct fuel'} : nat :=
+        	match fuel' with
+        	| O =>
+        		count
+        	| S fuel' =>
+        		let count := match (fits_u64 (plus count 1)
+        		)
+        		 with
+        		| true =>
+        			(plus count 1)
+        		| false =>
+        			match (fits_u64 count)
+        			 with
+        			| true =>
+        				(False_rect _ _)
+        			| false =>
+        				(plus count 1)
+        			end
+        		end
+        		 in
+        		(f_loop'2 fuel' (plus i'1 1)
+        		 count)
+        	end
+        .
+
+
+        Fixpoint f_loop'3 (fuel': nat) (i: nat) (count: nat) {struct fuel'} : nat :=
+        	match fuel' with
+        	| O =>
+        		count
+        	| S fuel' =>
+        		let count := (f_loop'2 (minus i 0)
+        		 0 count)
+        		 in
+        		(f_loop'3 fuel' (plus i 1)
+        		 count)
+        	end
+        .
+
+
+        Lemma f_loop'3_equation : forall (fuel': nat) (i: nat) (count: nat),
+        	(f_loop'3 fuel' i count)
+        	 =
+        	match fuel' with
+        	| O =>
+        		count
+        	| S fuel' =>
+        		let count := (f_loop'2 (minus i 0)
+        		 0 count)
+        		 in
+        		(f_loop'3 fuel' (plus i 1)
+        		 count)
+        	end
+        .
+        Proof.
+        	intros. destruct fuel'; reflexivity.
+        Qed.
+
+
+        Definition f (n: nat) : nat :=
+        	let count := 0 in
+        	let count := (f_loop'3 (minus n 0)
+        	 0 count)
+        	 in
+        	count
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_for_range_guarded_arms() {
+        assert_snapshot!(
+            test_as_vernacular(r#"
+                fn f(n: u64) -> u64 {
+                    match n {
+                        0 => 0,
+                        x if x < 10 => {
+                            let mut total = 0;
+                            for i in 0..x {
+                                total += i;
+                            }
+                            total
+                        }
+                        x if x < 100 => 2,
+                        _ => 3,
+                    }
+                }
+            "#),
+            @r###"
+        Definition fits_u64 (n: nat) : bool :=
+        	(N.ltb (N.of_nat n)
+        	 (N.pow 2 64)
+        	)
+        .
+
+
+        Program Fixpoint f_loop'1 (fuel': nat) (i: nat) (total: nat) {struct fuel'} : nat :=
+        	match fuel' with
+        	| O =>
+        		total
+        	| S fuel' =>
+        		let total := match (fits_u64 (plus total i)
+        		)
+        		 with
+        		| true =>
+        			(plus total i)
+        		| false =>
+        			match (andb (fits_u64 i)
+        			 (fits_u64 total)
+        			)
+        			 with
+        			| true =>
+        				(False_rect _ _)
+        			| false =>
+        				(plus total i)
+        			end
+        		end
+        		 in
+        		(f_loop'1 fuel' (plus i 1)
+        		 total)
+        	end
+        .
+
+
+        Definition f (n: nat) : nat :=
+        	match n with
+        	| 0 =>
+        		0
+        	| _ =>
+        		match n with
+        		| x =>
+        			match (Nat.ltb x 10)
+        			 with
+        			| true =>
+        				let total := 0 in
+        				let total := (f_loop'1 (minus x 0)
+        				 0 total)
+        				 in
+        				total
+        			| false =>
+        				match n with
+        				| 0 =>
+        					0
+        				| _ =>
+        					match n with
+        					| x =>
+        						match (Nat.ltb x 100)
+        						 with
+        						| true =>
+        							2
+        						| false =>
+        							match n with
+        							| 0 =>
+        								0
+        							| _ =>
+        								3
+        							end
+        						end
+        					end
+        				end
+        			end
+        		end
+        	end
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_for_errors() {
+        assert_snapshot!(
+            test_as_err(r#"
+                fn f(v: Vec<u64>) -> u64 {
+                    let mut total = 0;
+                    for x in v {
+                        total += x;
+                    }
+                    total
+                }
+            "#),
+            @"expected a `for` loop over a range (e.g. `for i in 0..n`)"
+        );
+        assert_snapshot!(
+            test_as_err(r#"
+                fn f(n: u64) -> u64 {
+                    let mut total = 0;
+                    for i in 0..n {
+                        if i == 3 {
+                            break;
+                        }
+                        total += i;
+                    }
+                    total
+                }
+            "#),
+            @"`break`, `continue`, `return` and `?` aren't supported in loops"
+        );
+        assert_snapshot!(
+            test_as_err(r#"
+                fn f(n: u64) -> u64 {
+                    for i in 0..n {
+                        let x = i;
+                    }
+                    n
+                }
+            "#),
+            @"expected the loop to assign to a variable declared outside it"
+        );
+    }
+
+    #[test]
+    fn test_while() {
+        assert_snapshot!(
+            test_as_vernacular(r#"
+                fn log2(n: u64) -> u64 {
+                    let mut x = n;
+                    let mut steps = 0;
+                    #[roq::decreasing(x)]
+                    while x > 1 {
+                        x /= 2;
+                        steps += 1;
+                    }
+                    steps
+                }
+            "#),
+            @r###"
+        Definition fits_u64 (n: nat) : bool :=
+        	(N.ltb (N.of_nat n)
+        	 (N.pow 2 64)
+        	)
+        .
+
+
+        Program Fixpoint log2_loop'1 (x: nat) (steps: nat) {measure x} : prod nat nat :=
+        	match (Nat.ltb 1 x)
+        	 with
+        	| true =>
+        		let x := (Nat.div x 2)
+        		 in
+        		let steps := match (fits_u64 (plus steps 1)
+        		)
+        		 with
+        		| true =>
+        			(plus steps 1)
+        		| false =>
+        			match (andb (fits_u64 steps)
+        			 (fits_u64 x)
+        			)
+        			 with
+        			| true =>
+        				(False_rect _ _)
+        			| false =>
+        				(plus steps 1)
+        			end
+        		end
+        		 in
+        		(log2_loop'1 x steps)
+        	| false =>
+        		(x, steps)
+        	end
+        .
+
+
+        Definition log2 (n: nat) : nat :=
+        	let x := n in
+        	let steps := 0 in
+        	let '(x, steps) := (log2_loop'1 x steps)
+        	 in
+        	steps
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_while_without_measure() {
+        assert_snapshot!(
+            test_as_err(r#"
+                fn f(n: u64) -> u64 {
+                    let mut i = 0;
+                    while i < n {
+                        i += 1;
+                    }
+                    i
+                }
+            "#),
+            @"can't prove that this loop terminates; annotate it with a measure which decreases on each iteration (e.g. `#[roq::decreasing(n - i)]`)"
+        );
+    }
+
+    #[test]
+    fn test_measure() {
+        assert_snapshot!(
+            test_as_def(r#"
+                #[roq::decreasing(hi - lo)]
+                fn count_between(lo: u64, hi: u64) -> u64 {
+                    if lo < hi {
+                        1 + count_between(lo + 1, hi)
+                    } else {
+                        0
+                    }
+                }
+            "#),
+            @r###"
+        Program Fixpoint count_between (lo: nat) (hi: nat) {measure (minus hi lo)} : nat :=
+        	match (Nat.ltb lo hi)
+        	 with
+        	| true =>
+        		let rhs' := (count_between match (fits_u64 (plus lo 1)
+        		)
+        		 with
+        		| true =>
+        			(plus lo 1)
+        		| false =>
+        			match (andb (fits_u64 lo)
+        			 (fits_u64 hi)
+        			)
+        			 with
+        			| true =>
+        				(False_rect _ _)
+        			| false =>
+        				(plus lo 1)
+        			end
+        		end
+        		 hi)
+        		 in
+        		match (fits_u64 (plus 1 rhs')
+        		)
+        		 with
+        		| true =>
+        			(plus 1 rhs')
+        		| false =>
+        			match (andb (andb (fits_u64 lo)
+        			 (fits_u64 hi)
+        			)
+        			 (fits_u64 rhs')
+        			)
+        			 with
+        			| true =>
+        				(False_rect _ _)
+        			| false =>
+        				(plus 1 rhs')
+        			end
+        		end
+        	| false =>
+        		0
+        	end
+        .
+        "###
+        );
+        assert_snapshot!(
+            test_as_err(r#"
+                #[decreasing(n)]
+                fn f(n: u64) -> u64 {
+                    n
+                }
+            "#),
+            @"only recursive functions need a measure"
+        );
+    }
+
+    #[test]
+    fn test_return() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn f(a: u64, b: u64) -> u64 {
+                    if a == 0 {
+                        return b;
+                    }
+                    let c = a + b;
+                    if c > 10 {
+                        return 10;
+                    }
+                    c
+                }
+            "#),
+            @r###"
+        Program Definition f (a: nat) (b: nat) : nat :=
+        	match (Nat.eqb a 0)
+        	 with
+        	| true =>
+        		b
+        	| false =>
+        		let c := match (fits_u64 (plus a b)
+        		)
+        		 with
+        		| true =>
+        			(plus a b)
+        		| false =>
+        			match (andb (fits_u64 a)
+        			 (fits_u64 b)
+        			)
+        			 with
+        			| true =>
+        				(False_rect _ _)
+        			| false =>
+        				(plus a b)
+        			end
+        		end
+        		 in
+        		match (Nat.ltb 10 c)
+        		 with
+        		| true =>
+        			10
+        		| false =>
+        			c
+        		end
+        	end
+        .
+        "###
+        );
+        assert_snapshot!(
+            test_as_def(r#"
+                fn f(o: Option<u64>) -> u64 {
+                    let x = match o {
+                        Some(v) => v,
+                        None => return 0,
+                    };
+                    x + 1
+                }
+            "#),
+            @r###"
+        Program Definition f (o: option nat) : nat :=
+        	match o with
+        	| Some v =>
+        		let x := v in
+        		match (fits_u64 (plus x 1)
+        		)
+        		 with
+        		| true =>
+        			(plus x 1)
+        		| false =>
+        			match (andb (fits_u64 v)
+        			 (fits_u64 x)
+        			)
+        			 with
+        			| true =>
+        				(False_rect _ _)
+        			| false =>
+        				(plus x 1)
+        			end
+        		end
+        	| None =>
+        		0
+        	end
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_return_with_state() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn f(a: u64) -> u64 {
+                    let mut x = a;
+                    if a > 5 {
+                        x = 5;
+                    } else if a == 0 {
+                        return 1;
+                    }
+                    x * 2
+                }
+            "#),
+            @r###"
+        Program Definition f (a: nat) : nat :=
+        	let x := a in
+        	match (Nat.ltb 5 a)
+        	 with
+        	| true =>
+        		let x := 5 in
+        		match (fits_u64 (mult x 2)
+        		)
+        		 with
+        		| true =>
+        			(mult x 2)
+        		| false =>
+        			match (andb (fits_u64 a)
+        			 (fits_u64 x)
+        			)
+        			 with
+        			| true =>
+        				(False_rect _ _)
+        			| false =>
+        				(mult x 2)
+        			end
+        		end
+        	| false =>
+        		match (Nat.eqb a 0)
+        		 with
+        		| true =>
+        			1
+        		| false =>
+        			match (fits_u64 (mult x 2)
+        			)
+        			 with
+        			| true =>
+        				(mult x 2)
+        			| false =>
+        				match (andb (fits_u64 a)
+        				 (fits_u64 x)
+        				)
+        				 with
+        				| true =>
+        					(False_rect _ _)
+        				| false =>
+        					(mult x 2)
+        				end
+        			end
+        		end
+        	end
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_return_guard_clause() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn factorial(n: u64) -> u64 {
+                    if n == 0 {
+                        return 1;
+                    }
+                    n * factorial(n - 1)
+                }
+            "#),
+            @r###"
+        Program Fixpoint factorial (n: nat) {struct n} : nat :=
+        	match n with
+        	| S n' =>
+        		let rhs' := (factorial n')
+        		 in
+        		match (fits_u64 (mult n rhs')
+        		)
+        		 with
+        		| true =>
+        			(mult n rhs')
+        		| false =>
+        			match (andb (fits_u64 n)
+        			 (fits_u64 rhs')
+        			)
+        			 with
+        			| true =>
+        				(False_rect _ _)
+        			| false =>
+        				(mult n rhs')
+        			end
+        		end
+        	| _ =>
+        		1
+        	end
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_try_option() {
+        assert_snapshot!(
+            test_as_vernacular(r#"
+                fn add(a: Option<u64>, b: Option<u64>) -> Option<u64> {
+                    let x = a?;
+                    Some(x + b?)
+                }
+            "#),
+            @r###"
+        Notation "'let?' x ':=' e 'in' k" :=
+        	(match e with Some x => k | None => None end)
+        	(at level 200, x ident, e at level 100, k at level 200).
+
+        Definition fits_u64 (n: nat) : bool :=
+        	(N.ltb (N.of_nat n)
+        	 (N.pow 2 64)
+        	)
+        .
+
+
+        Program Definition add (a: option nat) (b: option nat) : option nat :=
+        	let? x := a in
+        	let? try'1 := b in
+        	(Some match (fits_u64 (plus x try'1)
+        	)
+        	 with
+        	| true =>
+        		(plus x try'1)
+        	| false =>
+        		match (fits_u64 x)
+        		 with
+        		| true =>
+        			(False_rect _ _)
+        		| false =>
+        			(plus x try'1)
+        		end
+        	end
+        	)
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_try_result() {
+        assert_snapshot!(
+            test_as_vernacular(r#"
+                fn at_most(a: Result<i64, bool>, b: i64) -> Result<i64, bool> {
+                    if a? > b {
+                        return Err(true);
+                    }
+                    Ok(b)
+                }
+            "#),
+            @r###"
+        Notation "'let!' x ':=' e 'in' k" :=
+        	(match e with inl x => k | inr err => inr err end)
+        	(at level 200, x ident, e at level 100, k at level 200).
+
+        Definition at_most (a: sum Z bool) (b: Z) : sum Z bool :=
+        	let! try'1 := a in
+        	match (Z.ltb b try'1)
+        	 with
+        	| true =>
+        		(inr true)
+        	| false =>
+        		(inl b)
+        	end
+        .
+        "###
+        );
+        assert_snapshot!(
+            test_as_def(r#"
+                fn flatten(o: Option<Option<u64>>) -> Option<u64> {
+                    let x = match o {
+                        Some(inner) => inner?,
+                        None => 0,
+                    };
+                    Some(x)
+                }
+            "#),
+            @r###"
+        Definition flatten (o: option (option nat)) : option nat :=
+        	match o with
+        	| Some inner =>
+        		let? try'1 := inner in
+        		let x := try'1 in
+        		(Some x)
+        	| None =>
+        		let x := 0 in
+        		(Some x)
+        	end
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_early_return_errors() {
+        assert_snapshot!(
+            test_as_err(r#"
+                fn f(a: Option<u64>) -> u64 {
+                    a?
+                }
+            "#),
+            @"the `?` operator on `option nat` can't return early from a function returning `nat`"
+        );
+        assert_snapshot!(
+            test_as_err(r#"
+                fn f(a: Result<u64, bool>) -> Result<u64, u64> {
+                    Ok(a?)
+                }
+            "#),
+            @"the `?` operator on `sum nat bool` can't return early from a function returning `sum nat nat`"
+        );
+        assert_snapshot!(
+            test_as_err(r#"
+                fn f(o: Option<u64>) -> u64 {
+                    1 + match o {
+                        Some(v) => v,
+                        None => return 0,
+                    }
+                }
+            "#),
+            @"can't return early from the middle of an expression"
+        );
+        assert_snapshot!(
+            test_as_err(r#"
+                fn f(n: u64) -> u64 {
+                    let mut total = 0;
+                    for i in 0..n {
+                        if i > 10 {
+                            return total;
+                        }
+                        total += i;
+                    }
+                    total
+                }
+            "#),
+            @"`break`, `continue`, `return` and `?` aren't supported in loops"
+        );
+    }
+
+    #[test]
+    fn test_assert() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn average(total: u64, count: u64) -> u64 {
+                    assert!(count > 0, "no values");
+                    let average = total / count;
+                    debug_assert_ne!(average, 7);
+                    average
+                }
+            "#),
+            @r###"
+        Program Definition average (total: nat) (count: nat) : nat :=
+        	match (Nat.ltb 0 count)
+        	 with
+        	| true =>
+        		let average := match (negb (Nat.eqb count 0)
+        		)
+        		 with
+        		| true =>
+        			(Nat.div total count)
+        		| false =>
+        			(False_rect _ _)
+        		end
+        		 in
+        		match (negb (Nat.eqb average 7)
+        		)
+        		 with
+        		| true =>
+        			average
+        		| false =>
+        			(False_rect _ _)
+        		end
+        	| false =>
+        		(False_rect _ _)
+        	end
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_unreachable() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn succ(o: Option<u64>) -> u64 {
+                    1 + match o {
+                        Some(v) => v,
+                        None => unreachable!("checked by the caller"),
+                    }
+                }
+            "#),
+            @r###"
+        Program Definition succ (o: option nat) : nat :=
+        	let rhs' := match o with
+        	| Some v =>
+        		v
+        	| None =>
+        		(False_rect _ _)
+        	end
+        	 in
+        	match (fits_u64 (plus 1 rhs')
+        	)
+        	 with
+        	| true =>
+        		(plus 1 rhs')
+        	| false =>
+        		match (fits_u64 rhs')
+        		 with
+        		| true =>
+        			(False_rect _ _)
+        		| false =>
+        			(plus 1 rhs')
+        		end
+        	end
+        .
+        "###
+        );
+        assert_snapshot!(
+            test_as_def(r#"
+                fn sign(x: i64) -> i64 {
+                    if x > 0 {
+                        return 1;
+                    } else if x < 0 {
+                        return -1;
+                    }
+                    panic!("zero has no sign");
+                    0
+                }
+            "#),
+            @r###"
+        Program Definition sign (x: Z) : Z :=
+        	match (Z.ltb 0%Z x)
+        	 with
+        	| true =>
+        		1%Z
+        	| false =>
+        		match (Z.ltb x 0%Z)
+        		 with
+        		| true =>
+        			(-1)%Z
+        		| false =>
+        			(False_rect _ _)
+        		end
+        	end
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_panic_guard_clause() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn halve(n: u64) -> u64 {
+                    if n == 0 {
+                        return 0;
+                    }
+                    if n < 2 {
+                        panic!("odd");
+                    }
+                    1 + halve(n - 2)
+                }
+            "#),
+            @r###"
+        Program Fixpoint halve (n: nat) {struct n} : nat :=
+        	match n with
+        	| S n' =>
+        		match n with
+        		| S (S n'' as n') =>
+        			let rhs' := (halve n'')
+        			 in
+        			match (fits_u64 (plus 1 rhs')
+        			)
+        			 with
+        			| true =>
+        				(plus 1 rhs')
+        			| false =>
+        				match (andb (fits_u64 n)
+        				 (fits_u64 rhs')
+        				)
+        				 with
+        				| true =>
+        					(False_rect _ _)
+        				| false =>
+        					(plus 1 rhs')
+        				end
+        			end
+        		| _ =>
+        			(False_rect _ _)
+        		end
+        	| _ =>
+        		0
+        	end
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_panic_in_loop() {
+        assert_snapshot!(
+            test_as_vernacular(r#"
+                fn sum(n: u64) -> u64 {
+                    let mut total = 0;
+                    for i in 0..n {
+                        assert_eq!(i % 2, 0);
+                        total += i;
+                    }
+                    total
+                }
+            "#),
+            @r###"
+        Definition fits_u64 (n: nat) : bool :=
+        	(N.ltb (N.of_nat n)
+        	 (N.pow 2 64)
+        	)
+        .
+
+
+        Program Fixpoint sum_loop'1 (fuel': nat) (i: nat) (total: nat) {struct fuel'} : nat :=
+        	match fuel' with
+        	| O =>
+        		total
+        	| S fuel' =>
+        		match (Nat.eqb (Nat.modulo i 2)
+        		 0)
+        		 with
+        		| true =>
+        			let total := match (fits_u64 (plus total i)
+        			)
+        			 with
+        			| true =>
+        				(plus total i)
+        			| false =>
+        				match (andb (fits_u64 i)
+        				 (fits_u64 total)
+        				)
+        				 with
+        				| true =>
+        					(False_rect _ _)
+        				| false =>
+        					(plus total i)
+        				end
+        			end
+        			 in
+        			(sum_loop'1 fuel' (plus i 1)
+        			 total)
+        		| false =>
+        			(False_rect _ _)
+        		end
+        	end
+        .
+
+
+        Definition sum (n: nat) : nat :=
+        	let total := 0 in
+        	let total := (sum_loop'1 (minus n 0)
+        	 0 total)
+        	 in
+        	total
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_macro_errors() {
+        assert_snapshot!(
+            test_as_err(r#"
+                fn f(n: u64) -> u64 {
+                    println!("{n}");
+                    n
+                }
+            "#),
+            @"unsupported macro, expected `vec!`, `panic!`, `unreachable!` or an assertion"
+        );
+        assert_snapshot!(
+            test_as_err(r#"
+                fn f(n: u64) -> u64 {
+                    assert_eq!(n);
+                    n
+                }
+            "#),
+            @"expected the condition of the assertion"
+        );
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        assert_snapshot!(
+            test_as_vernacular(r#"
+                fn f(a: u64, b: u64) -> u64 {
+                    a - b
+                }
+            "#),
+            @r###"
+        Program Definition f (a: nat) (b: nat) : nat :=
+        	match (Nat.leb b a)
+        	 with
+        	| true =>
+        		(minus a b)
+        	| false =>
+        		(False_rect _ _)
+        	end
+        .
+        "###
+        );
+        assert_snapshot!(
+            test_as_vernacular(r#"
+                fn f(a: u64, b: u64) -> u64 {
+                    (a + 1) * b % (a - b)
+                }
+            "#),
+            @r###"
+        Definition fits_u64 (n: nat) : bool :=
+        	(N.ltb (N.of_nat n)
+        	 (N.pow 2 64)
+        	)
+        .
+
+
+        Program Definition f (a: nat) (b: nat) : nat :=
+        	let lhs' := let lhs' := match (fits_u64 (plus a 1)
+        	)
+        	 with
+        	| true =>
+        		(plus a 1)
+        	| false =>
+        		match (andb (fits_u64 a)
+        		 (fits_u64 b)
+        		)
+        		 with
+        		| true =>
+        			(False_rect _ _)
+        		| false =>
+        			(plus a 1)
+        		end
+        	end
+        	 in
+        	match (fits_u64 (mult lhs' b)
+        	)
+        	 with
+        	| true =>
+        		(mult lhs' b)
+        	| false =>
+        		match (andb (andb (fits_u64 a)
+        		 (fits_u64 b)
+        		)
+        		 (fits_u64 lhs')
+        		)
+        		 with
+        		| true =>
+        			(False_rect _ _)
+        		| false =>
+        			(mult lhs' b)
+        		end
+        	end
+        	 in
+        	let rhs' := match (Nat.leb b a)
+        	 with
+        	| true =>
+        		(minus a b)
+        	| false =>
+        		(False_rect _ _)
+        	end
+        	 in
+        	match (negb (Nat.eqb rhs' 0)
+        	)
+        	 with
+        	| true =>
+        		(Nat.modulo lhs' rhs')
+        	| false =>
+        		(False_rect _ _)
+        	end
+        .
+        "###
+        );
+        assert_snapshot!(
+            test_as_vernacular(r#"
+                fn f(a: u64, b: u64) -> u64 {
+                    (a << b) + (a >> b) + (a << 3)
+                }
+            "#),
+            @r###"
+        Definition fits_u64 (n: nat) : bool :=
+        	(N.ltb (N.of_nat n)
+        	 (N.pow 2 64)
+        	)
+        .
+
+
+        Definition wrap_u64_nat (n: nat) : nat :=
+        	(N.to_nat (N.modulo (N.of_nat n)
+        	 (N.pow 2%N 64%N)
+        	)
+        	)
+        .
+
+
+        Program Definition f (a: nat) (b: nat) : nat :=
+        	let lhs' := let lhs' := match (Nat.ltb b 64)
+        	 with
+        	| true =>
+        		(wrap_u64_nat (Nat.shiftl a b)
+        		)
+        	| false =>
+        		(False_rect _ _)
+        	end
+        	 in
+        	let rhs' := match (Nat.ltb b 64)
+        	 with
+        	| true =>
+        		(Nat.shiftr a b)
+        	| false =>
+        		(False_rect _ _)
+        	end
+        	 in
+        	match (fits_u64 (plus lhs' rhs')
+        	)
+        	 with
+        	| true =>
+        		(plus lhs' rhs')
+        	| false =>
+        		match (andb (andb (andb (fits_u64 a)
+        		 (fits_u64 b)
+        		)
+        		 (fits_u64 lhs')
+        		)
+        		 (fits_u64 rhs')
+        		)
+        		 with
+        		| true =>
+        			(False_rect _ _)
+        		| false =>
+        			(plus lhs' rhs')
+        		end
+        	end
+        	 in
+        	let rhs' := (wrap_u64_nat (Nat.shiftl a 3)
+        	)
+        	 in
+        	match (fits_u64 (plus lhs' rhs')
+        	)
+        	 with
+        	| true =>
+        		(plus lhs' rhs')
+        	| false =>
+        		match (andb (andb (andb (fits_u64 a)
+        		 (fits_u64 b)
+        		)
+        		 (fits_u64 lhs')
+        		)
+        		 (fits_u64 rhs')
+        		)
+        		 with
+        		| true =>
+        			(False_rect _ _)
+        		| false =>
+        			(plus lhs' rhs')
+        		end
+        	end
+        .
+        "###
+        );
+        assert_snapshot!(
+            test_as_vernacular(r#"
+                fn f(v: &[u64], n: u64, x: i64) -> i64 {
+                    let total: u64 = v.iter().sum();
+                    if total / 2 > n {
+                        x + 1
+                    } else {
+                        x
+                    }
+                }
+            "#),
+            @r###"
+        Definition fits_u64 (n: nat) : bool :=
+        	(N.ltb (N.of_nat n)
+        	 (N.pow 2 64)
+        	)
+        .
+
+
+        Program Definition f (v: list nat) (n: nat) (x: Z) : Z :=
+        	let total : nat := match (fits_u64 (list_sum v)
+        	)
+        	 with
+        	| true =>
+        		(list_sum v)
+        	| false =>
+        		match (fits_u64 n)
+        		 with
+        		| true =>
+        			(False_rect _ _)
+        		| false =>
+        			(list_sum v)
+        		end
+        	end
+        	 in
+        	match (Nat.ltb n (Nat.div total 2)
+        	)
+        	 with
+        	| true =>
+        		(Z.add x 1%Z)
+        	| false =>
+        		x
+        	end
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_checked_loop() {
+        assert_snapshot!(
+            test_as_vernacular(r#"
+                fn sum_to(n: u64) -> u64 {
+                    let mut total = 0;
+                    for i in 0..n {
+                        total += i;
+                    }
+                    total
+                }
+            "#),
+            @r###"
+        Definition fits_u64 (n: nat) : bool :=
+        	(N.ltb (N.of_nat n)
+        	 (N.pow 2 64)
+        	)
+        .
+
+
+        Program Fixpoint sum_to_loop'1 (fuel': nat) (i: nat) (total: nat) {struct fuel'} : nat :=
+        	match fuel' with
+        	| O =>
+        		total
+        	| S fuel' =>
+        		let total := match (fits_u64 (plus total i)
+        		)
+        		 with
+        		| true =>
+        			(plus total i)
+        		| false =>
+        			match (andb (fits_u64 i)
+        			 (fits_u64 total)
+        			)
+        			 with
+        			| true =>
+        				(False_rect _ _)
+        			| false =>
+        				(plus total i)
+        			end
+        		end
+        		 in
+        		(sum_to_loop'1 fuel' (plus i 1)
+        		 total)
+        	end
+        .
+
+
+        Definition sum_to (n: nat) : nat :=
+        	let total := 0 in
+        	let total := (sum_to_loop'1 (minus n 0)
+        	 0 total)
+        	 in
+        	total
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_unchecked_opt_out() {
+        assert_snapshot!(
+            test_as_unchecked(r#"
+                fn f(a: u64, b: u64) -> u64 {
+                    (a - b) * 2 / b << 1
+                }
+            "#),
+            @r###"
+        Definition f (a: nat) (b: nat) : nat :=
+        	(Nat.shiftl (Nat.div (mult (minus a b)
+        	 2)
+        	 b)
+        	 1)
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_checked_arithmetic_safe() {
+        assert_snapshot!(
+            test_as_vernacular(r#"
+                fn f(a: u64, x: i64) -> bool {
+                    a / 2 == a % 3 && x - 1 < 0
+                }
+            "#),
+            @r###"
+        Definition f (a: nat) (x: Z) : bool :=
+        	(andb (Nat.eqb (Nat.div a 2)
+        	 (Nat.modulo a 3)
+        	)
+        	 (Z.ltb (Z.sub x 1%Z)
+        	 0%Z)
+        	)
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_checked_signed_division() {
+        assert_snapshot!(
+            test_as_vernacular(r#"
+                fn f(x: i64, y: i64) -> i64 {
+                    x / y + x % 2 + x / -1
+                }
+            "#),
+            @r###"
+        Definition is_int_min (n: Z) : bool :=
+        	(orb (orb (orb (orb (Z.eqb n (-128)%Z)
+        	 (Z.eqb n (-32768)%Z)
+        	)
+        	 (Z.eqb n (-2147483648)%Z)
+        	)
+        	 (Z.eqb n (-9223372036854775808)%Z)
+        	)
+        	 (Z.eqb n (-170141183460469231731687303715884105728)%Z)
+        	)
+        .
+
+
+        Program Definition f (x: Z) (y: Z) : Z :=
+        	(Z.add (Z.add match (andb (negb (Z.eqb y 0%Z)
+        	)
+        	 (negb (andb (Z.eqb y (-1)%Z)
+        	 (is_int_min x)
+        	)
+        	)
+        	)
+        	 with
+        	| true =>
+        		(Z.quot x y)
+        	| false =>
+        		(False_rect _ _)
+        	end
+        	 (Z.rem x 2%Z)
+        	)
+        	 match (negb (andb (Z.eqb (-1)%Z (-1)%Z)
+        	 (is_int_min x)
+        	)
+        	)
+        	 with
+        	| true =>
+        		(Z.quot x (-1)%Z)
+        	| false =>
+        		(False_rect _ _)
+        	end
+        	)
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_word_signed_division() {
+        assert_snapshot!(
+            test_as_numbers(r#"
+                fn f(x: i64, y: i64) -> i64 {
+                    x % y
+                }
+            "#, Numbers::Word),
+            @r###"
+        Definition is_int_min (n: Z) : bool :=
+        	(orb (orb (orb (orb (Z.eqb n (-128)%Z)
+        	 (Z.eqb n (-32768)%Z)
+        	)
+        	 (Z.eqb n (-2147483648)%Z)
+        	)
+        	 (Z.eqb n (-9223372036854775808)%Z)
+        	)
+        	 (Z.eqb n (-170141183460469231731687303715884105728)%Z)
+        	)
+        .
+
+
+        Program Definition f (x: Z) (y: Z) : Z :=
+        	match (andb (negb (Z.eqb y 0%Z)
+        	)
+        	 (negb (andb (Z.eqb y (-1)%Z)
+        	 (is_int_min x)
+        	)
+        	)
+        	)
+        	 with
+        	| true =>
+        		(Z.rem x y)
+        	| false =>
+        		(False_rect _ _)
+        	end
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_numbers_n() {
+        assert_snapshot!(
+            test_as_numbers(r#"
+                fn f(secs: u64, values: &[u64]) -> Option<u64> {
+                    if secs >= 86400 && values.len() > 2 {
+                        return values.get(secs % 3);
+                    }
+                    Some(secs * 2 + values.iter().sum::<u64>())
+                }
+            "#, Numbers::N),
+            @r###"
+        Definition fits_u64_N (n: N) : bool :=
+        	(N.ltb n (N.pow 2 64)
+        	)
+        .
+
+
+        Program Definition f (secs: N) (values: list N) : option N :=
+        	match (andb (N.leb 86400%N secs)
+        	 (N.ltb 2%N (N.of_nat (length values)
+        	)
+        	)
+        	)
+        	 with
+        	| true =>
+        		(nth_error values (N.to_nat (N.modulo secs 3%N)
+        		)
+        		)
+        	| false =>
+        		(Some let lhs' := match (fits_u64_N (N.mul secs 2%N)
+        		)
+        		 with
+        		| true =>
+        			(N.mul secs 2%N)
+        		| false =>
+        			match (fits_u64_N secs)
+        			 with
+        			| true =>
+        				(False_rect _ _)
+        			| false =>
+        				(N.mul secs 2%N)
+        			end
+        		end
+        		 in
+        		let rhs' := match (fits_u64_N (fold_right N.add 0%N values)
+        		)
+        		 with
+        		| true =>
+        			(fold_right N.add 0%N values)
+        		| false =>
+        			match (fits_u64_N secs)
+        			 with
+        			| true =>
+        				(False_rect _ _)
+        			| false =>
+        				(fold_right N.add 0%N values)
+        			end
+        		end
+        		 in
+        		match (fits_u64_N (N.add lhs' rhs')
+        		)
+        		 with
+        		| true =>
+        			(N.add lhs' rhs')
+        		| false =>
+        			match (andb (andb (fits_u64_N secs)
+        			 (fits_u64_N lhs')
+        			)
+        			 (fits_u64_N rhs')
+        			)
+        			 with
+        			| true =>
+        				(False_rect _ _)
+        			| false =>
+        				(N.add lhs' rhs')
+        			end
+        		end
+        		)
+        	end
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_numbers_loop() {
+        assert_snapshot!(
+            test_as_numbers(r#"
+                fn f(n: u64) -> u64 {
+                    let mut total = 0;
+                    for i in 0..n {
+                        total += i;
+                    }
+                    let mut x = total;
+                    #[roq::decreasing(x)]
+                    while x > 4096 {
+                        x = x / 2;
+                    }
+                    x
+                }
+            "#, Numbers::N),
+            @r###"
+        Definition fits_u64_N (n: N) : bool :=
+        	(N.ltb n (N.pow 2 64)
+        	)
+        .
+
+
+        Program Fixpoint f_loop'1 (fuel': nat) (i: N) (total: N) {struct fuel'} : N :=
+        	match fuel' with
+        	| O =>
+        		total
+        	| S fuel' =>
+        		let total := match (fits_u64_N (N.add total i)
+        		)
+        		 with
+        		| true =>
+        			(N.add total i)
+        		| false =>
+        			match (andb (fits_u64_N i)
+        			 (fits_u64_N total)
+        			)
+        			 with
+        			| true =>
+        				(False_rect _ _)
+        			| false =>
+        				(N.add total i)
+        			end
+        		end
+        		 in
+        		(f_loop'1 fuel' (N.add i 1%N)
+        		 total)
+        	end
+        .
+
+
+        Program Fixpoint f_loop'2 (x: N) {measure (N.to_nat x)} : N :=
+        	match (N.ltb 4096%N x)
+        	 with
+        	| true =>
+        		let x := (N.div x 2%N)
+        		 in
+        		(f_loop'2 x)
+        	| false =>
+        		x
+        	end
+        .
+
+
+        Definition f (n: N) : N :=
+        	let total := 0%N in
+        	let total := (f_loop'1 (N.to_nat (N.sub n 0%N)
+        	)
+        	 0%N total)
+        	 in
+        	let x := total in
+        	let x := (f_loop'2 x)
+        	 in
+        	x
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_numbers_z() {
+        assert_snapshot!(
+            test_as_numbers(r#"
+                fn f(a: u64, b: u64) -> bool {
+                    a - b * 2 < 0
+                }
+            "#, Numbers::Z),
+            @r###"
+        Definition f (a: Z) (b: Z) : bool :=
+        	(Z.ltb (Z.sub a (Z.mul b 2%Z)
+        	)
+        	 0%Z)
+        .
+        "###
+        );
+        assert_snapshot!(
+            test_as_numbers(r#"
+                fn max() -> u64 {
+                    18446744073709551615
+                }
+            "#, Numbers::Z),
+            @r###"
+        Definition max : Z :=
+        	18446744073709551615%Z
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_numbers_word() {
+        assert_snapshot!(
+            test_as_numbers(r#"
+                fn f(a: u64, b: u64) -> u64 {
+                    (a - b) * 3 + (a << b) / 2
+                }
+            "#, Numbers::Word),
+            @r###"
+        Definition wrap_u64 (n: N) : N :=
+        	(N.modulo n (N.pow 2%N 64%N)
+        	)
+        .
+
+
+        Definition f (a: N) (b: N) : N :=
+        	(wrap_u64 (N.add (wrap_u64 (N.mul (wrap_u64 (N.sub (N.add a (N.pow 2%N 64%N)
+        	)
+        	 b)
+        	)
+        	 3%N)
+        	)
+        	 (N.div (wrap_u64 (N.shiftl a (N.modulo b 64%N)
+        	)
+        	)
+        	 2%N)
+        	)
+        	)
+        .
+        "###
+        );
+        assert_snapshot!(
+            test_as_numbers(r#"
+                fn f(a: u64, b: u64) -> u64 {
+                    a % b
+                }
+            "#, Numbers::Word),
+            @r###"
+        Program Definition f (a: N) (b: N) : N :=
+        	match (negb (N.eqb b 0%N)
+        	)
+        	 with
+        	| true =>
+        		(N.modulo a b)
+        	| false =>
+        		(False_rect _ _)
+        	end
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_numbers_checked() {
+        let options = Options {
+            checked: true,
+            numbers: Numbers::N,
+        };
+        assert_snapshot!(
+            test_with_options(r#"
+                fn f(a: u64, b: u64) -> u64 {
+                    a + b - 1
+                }
+            "#, options),
+            @r###"
+        Definition fits_u64_N (n: N) : bool :=
+        	(N.ltb n (N.pow 2 64)
+        	)
+        .
+
+
+        Program Definition f (a: N) (b: N) : N :=
+        	let lhs' := match (fits_u64_N (N.add a b)
+        	)
+        	 with
+        	| true =>
+        		(N.add a b)
+        	| false =>
+        		match (andb (fits_u64_N a)
+        		 (fits_u64_N b)
+        		)
+        		 with
+        		| true =>
+        			(False_rect _ _)
+        		| false =>
+        			(N.add a b)
+        		end
+        	end
+        	 in
+        	match (N.leb 1%N lhs')
+        	 with
+        	| true =>
+        		(N.sub lhs' 1%N)
+        	| false =>
+        		(False_rect _ _)
+        	end
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_numbers_recursion() {
+        let source = parse(
+            r#"
+            fn f(n: u64) -> u64 {
+                if n == 0 { 0 } else { f(n - 1) }
+            }
+        "#,
+        );
+        let options = Options {
+            numbers: Numbers::N,
+            ..Options::default()
+        };
+        let err = func_as_ast(&source, &options).err().unwrap();
+        assert_snapshot!(err, @r###"
+        can't find a structurally decreasing argument for this recursive call; expected `n - 1` (or `n - k`) to be passed for some `u64` argument `n`, but structural recursion needs `numbers = "nat"`; give the function a measure with `#[roq::decreasing(...)]` instead
+        "###);
+    }
+
+    #[test]
+    fn test_generic() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn id<T>(x: T) -> T {
+                    x
+                }
+            "#),
+            @r###"
+        Definition id {T: Type} (x: T) : T :=
+        	x
+        .
+        "###
+        );
+        assert_snapshot!(
+            test_as_def(r#"
+                fn first_or<'a, T>(values: &'a [T], default: &'a T) -> Option<&'a T> {
+                    let first: Option<&T> = values.first();
+                    if values.is_empty() {
+                        return Some(default);
+                    }
+                    first
+                }
+            "#),
+            @r###"
+        Definition first_or {T: Type} (values: list T) (default: T) : option T :=
+        	let first : option T := (hd_error values)
+        	 in
+        	match (Nat.eqb (length values)
+        	 0)
+        	 with
+        	| true =>
+        		(Some default)
+        	| false =>
+        		first
+        	end
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_generic_recursion() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn replicate<T>(n: u64, x: T, acc: Vec<T>) -> Vec<T> {
+                    if n == 0 {
+                        acc
+                    } else {
+                        let mut acc = acc;
+                        acc.push(x);
+                        replicate(n - 1, x, acc)
+                    }
+                }
+            "#),
+            @r###"
+        Fixpoint replicate {T: Type} (n: nat) (x: T) (acc: list T) {struct n} : list T :=
+        	match n with
+        	| S n' =>
+        		let acc := (app acc (x :: nil))
+        		 in
+        		(replicate n' x acc)
+        	| _ =>
+        		acc
+        	end
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_generic_loop() {
+        assert_snapshot!(
+            test_as_vernacular(r#"
+                fn last_of<T>(values: Vec<T>, n: u64, x: T) -> T {
+                    let mut last = x;
+                    for i in 0..n {
+                        last = x;
+                    }
+                    last
+                }
+            "#),
+            @r###"
+        Fixpoint last_of_loop'1 {T: Type} (fuel': nat) (i: nat) (x: T) (last: T) {struct fuel'} : T :=
+        	match fuel' with
+        	| O =>
+        		last
+        	| S fuel' =>
+        		let last := x in
+        		(last_of_loop'1 fuel' (plus i 1)
+        		 x last)
+        	end
+        .
+
+
+        Lemma last_of_loop'1_equation : forall {T: Type} (fuel': nat) (i: nat) (x: T) (last: T),
+        	(last_of_loop'1 fuel' i x last)
+        	 =
+        	match fuel' with
+        	| O =>
+        		last
+        	| S fuel' =>
+        		let last := x in
+        		(last_of_loop'1 fuel' (plus i 1)
+        		 x last)
+        	end
+        .
+        Proof.
+        	intros. destruct fuel'; reflexivity.
+        Qed.
+
+
+        Definition last_of {T: Type} (values: list T) (n: nat) (x: T) : T :=
+        	let last := x in
+        	let last := (last_of_loop'1 (minus n 0)
+        	 0 x last)
+        	 in
+        	last
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_generic_errors() {
+        assert_snapshot!(
+            test_as_err(r#"
+                fn f<T: Hash>(x: T) -> T {
+                    x
+                }
+            "#),
+            @"unsupported trait bound, expected `PartialEq`, `Eq`, `PartialOrd`, `Ord`, `Add<Output = T>`, `Default`, `Clone` or `Copy`"
+        );
+        assert_snapshot!(
+            test_as_err(r#"
+                fn f<T>(x: T, y: T) -> bool {
+                    x == y
+                }
+            "#),
+            @"operator `==` isn't supported on `T`, without a bound `T: PartialEq`"
+        );
+        assert_snapshot!(
+            test_as_err(r#"
+                fn f<T: PartialEq>(x: T, y: T) -> T {
+                    x - y
+                }
+            "#),
+            @"operator `-` isn't supported on `T`"
+        );
+        assert_snapshot!(
+            test_as_err(r#"
+                fn f<T: Clone>() -> T {
+                    T::default()
+                }
+            "#),
+            @"`Default::default()` isn't supported on `T`, without a bound `T: Default`"
+        );
+    }
+
+    #[test]
+    fn test_bounds() {
+        assert_snapshot!(
+            test_as_vernacular(r#"
+                fn max<T: Ord + Copy>(a: T, b: T) -> T {
+                    if a.ge(&b) {
+                        a
+                    } else {
+                        b
+                    }
+                }
+            "#),
+            @r###"
+        Class PartialEq (T: Type) := {
+        	PartialEq_eqb: T -> T -> bool
+        }.
+
+
+        Instance PartialEq_nat : PartialEq nat := {
+        	PartialEq_eqb := Nat.eqb
+        }.
+
+
+        Instance PartialEq_N : PartialEq N := {
+        	PartialEq_eqb := N.eqb
+        }.
+
+
+        Instance PartialEq_Z : PartialEq Z := {
+        	PartialEq_eqb := Z.eqb
+        }.
+
+
+        Instance PartialEq_bool : PartialEq bool := {
+        	PartialEq_eqb := Bool.eqb
+        }.
+
+
+        Class Ord (T: Type) `{PartialEq T} := {
+        	Ord_ltb: T -> T -> bool;
+        	Ord_leb: T -> T -> bool
+        }.
+
+
+        Instance Ord_nat : Ord nat := {
+        	Ord_ltb := Nat.ltb;
+        	Ord_leb := Nat.leb
+        }.
+
+
+        Instance Ord_N : Ord N := {
+        	Ord_ltb := N.ltb;
+        	Ord_leb := N.leb
+        }.
+
+
+        Instance Ord_Z : Ord Z := {
+        	Ord_ltb := Z.ltb;
+        	Ord_leb := Z.leb
+        }.
+
+
+        Definition max {T: Type} `{Ord T} (a: T) (b: T) : T :=
+        	match (Ord_leb b a)
+        	 with
+        	| true =>
+        		a
+        	| false =>
+        		b
+        	end
+        .
+        "###
+        );
+        assert_snapshot!(
+            test_as_def(r#"
+                fn count<T: Eq>(values: &[T], x: T) -> bool {
+                    x != x.clone() || values.contains(&x)
+                }
+            "#),
+            @r###"
+        Definition count {T: Type} `{PartialEq T} (values: list T) (x: T) : bool :=
+        	(orb (negb (PartialEq_eqb x x)
+        	)
+        	 (existsb (PartialEq_eqb x)
+        	 values)
+        	)
+        .
+        "###
+        );
+        assert_snapshot!(
+            test_as_def(r#"
+                fn same<T>(a: T, b: T) -> bool
+                where
+                    T: Ord,
+                {
+                    a == b && a <= b
+                }
+            "#),
+            @r###"
+        Definition same {T: Type} `{Ord T} (a: T) (b: T) : bool :=
+        	(andb (PartialEq_eqb a b)
+        	 (Ord_leb a b)
+        	)
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_trait_method_unbounded() {
+        assert_snapshot!(
+            test_as_err(r#"
+                fn total(a: Money, b: Money) -> Money {
+                    a.add(b)
+                }
+            "#),
+            @"the method for `+` is only supported on values of a type parameter"
+        );
+        assert_snapshot!(
+            test_as_err(r#"
+                fn before(a: u64, b: u64) -> bool {
+                    a.lt(&b)
+                }
+            "#),
+            @"the method for `<` is only supported on values of a type parameter"
+        );
+    }
+
+    #[test]
+    fn test_bounds_loop() {
+        assert_snapshot!(
+            test_as_vernacular(r#"
+                fn times<T: Add<Output = T> + Default + Copy>(x: T, n: u64) -> T {
+                    let mut total = T::default();
+                    for i in 0..n {
+                        total = total + x;
+                    }
+                    total
+                }
+            "#),
+            @r###"
+        Class Add (T: Type) := {
+        	Add_add: T -> T -> T
+        }.
+
+
+        Instance Add_nat : Add nat := {
+        	Add_add := plus
+        }.
+
+
+        Instance Add_N : Add N := {
+        	Add_add := N.add
+        }.
+
+
+        Instance Add_Z : Add Z := {
+        	Add_add := Z.add
+        }.
+
+
+        Class Default (T: Type) := {
+        	Default_default: T
+        }.
+
+
+        Instance Default_nat : Default nat := {
+        	Default_default := 0
+        }.
+
+
+        Instance Default_N : Default N := {
+        	Default_default := 0%N
+        }.
+
+
+        Instance Default_Z : Default Z := {
+        	Default_default := 0%Z
+        }.
+
+
+        Instance Default_bool : Default bool := {
+        	Default_default := false
+        }.
+
+
+        Instance Default_list {T: Type} : Default (list T) := {
+        	Default_default := nil
+        }.
+
+
+        Instance Default_option {T: Type} : Default (option T) := {
+        	Default_default := None
+        }.
+
+
+        Fixpoint times_loop'1 {T: Type} `{Add T} `{Default T} (fuel': nat) (i: nat) (x: T) (total: T) {struct fuel'} : T :=
+        	match fuel' with
+        	| O =>
+        		total
+        	| S fuel' =>
+        		let total := (Add_add total x)
+        		 in
+        		(times_loop'1 fuel' (plus i 1)
+        		 x total)
+        	end
+        .
+
+
+        Lemma times_loop'1_equation : forall {T: Type} `{Add T} `{Default T} (fuel': nat) (i: nat) (x: T) (total: T),
+        	(times_loop'1 fuel' i x total)
+        	 =
+        	match fuel' with
+        	| O =>
+        		total
+        	| S fuel' =>
+        		let total := (Add_add total x)
+        		 in
+        		(times_loop'1 fuel' (plus i 1)
+        		 x total)
+        	end
+        .
+        Proof.
+        	intros. destruct fuel'; reflexivity.
+        Qed.
+
+
+        Definition times {T: Type} `{Add T} `{Default T} (x: T) (n: nat) : T :=
+        	let total := Default_default in
+        	let total := (times_loop'1 (minus n 0)
+        	 0 x total)
+        	 in
+        	total
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_options() {
+        assert!(Options::parse(quote::quote!(checked)).unwrap().checked);
+        assert!(Options::parse(quote::quote!()).unwrap().checked);
+        assert!(!Options::parse(quote::quote!(unchecked)).unwrap().checked);
+        assert!(
+            !Options::parse(quote::quote!(numbers = "Z"))
+                .unwrap()
+                .checked
+        );
+        assert_snapshot!(
+            Options::parse(quote::quote!(checked, unchecked)).unwrap_err(),
+            @"a function can't be both `checked` and `unchecked`"
+        );
+        assert_snapshot!(
+            Options::parse(quote::quote!(overflow)).unwrap_err(),
+            @"unsupported option, expected `checked`, `unchecked` or `numbers`"
+        );
+
+        let options = Options::parse(quote::quote!(checked, numbers = "N")).unwrap();
+        assert_eq!((options.checked, options.numbers), (true, Numbers::N));
+        assert_eq!(
+            Options::parse(quote::quote!(numbers = "word"))
+                .unwrap()
+                .numbers,
+            Numbers::Word
+        );
+        assert_snapshot!(
+            Options::parse(quote::quote!(numbers = "u64")).unwrap_err(),
+            @"unsupported numbers `u64`, expected `nat`, `N`, `Z` or `word`"
+        );
+        assert_snapshot!(
+            Options::parse(quote::quote!(numbers = "word", checked)).unwrap_err(),
+            @r###"
+        `checked` is only supported with `numbers = "nat"` or `numbers = "N"`
+        "###
+        );
+    }
+
+    /// Convert the methods of an inherent `impl` block, listing the callees of each.
+    fn test_impl_block(input: &str) -> syn::Result<String> {
+        let translations = impl_block_as_ast(&syn::parse_str(input).unwrap(), &Options::default())?;
+        Ok(translations
+            .into_iter()
+            .map(|(name, translation)| {
+                let callees = translation
+                    .callees
+                    .iter()
+                    .map(|path| quote::quote!(#path).to_string())
+                    .collect::<Vec<_>>();
+                let statement = elaborate(&translation).0;
+                format!("(* {name} calls {callees:?} *)\n{statement}")
+            })
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    #[test]
+    fn test_methods() {
+        assert_snapshot!(
+            test_impl_block(r#"
+                impl Rect {
+                    fn new(width: u64, height: u64) -> Self {
+                        Rect { width, height }
+                    }
+                    fn square(side: u64) -> Rect {
+                        Self::new(side, side)
+                    }
+                    fn area(&self) -> u64 {
+                        self.width * self.height
+                    }
+                    fn fits(&self, other: &Rect) -> bool {
+                        self.area() <= Rect::area(other) && self.clone().width <= other.width
+                    }
+                    fn widen(self, extra: u64) -> Rect {
+                        Rect { width: self.width + extra, ..self }
+                    }
+                }
+            "#)
+            .unwrap()
+        , @r###"
+        (* Rect_new calls [] *)
+        Definition Rect_new (width: nat) (height: nat) : Rect :=
+        	{| Rect_width := width; Rect_height := height |}
+        .
+
+        (* Rect_square calls ["Rect_new"] *)
+        Definition Rect_square (side: nat) : Rect :=
+        	(Rect_new side side)
+        .
+
+        (* Rect_area calls [] *)
+        Program Definition Rect_area (self: Rect) : nat :=
+        	let lhs' := (Rect_width self)
+        	 in
+        	let rhs' := (Rect_height self)
+        	 in
+        	match (fits_u64 (mult lhs' rhs')
+        	)
+        	 with
+        	| true =>
+        		(mult lhs' rhs')
+        	| false =>
+        		match (andb (fits_u64 lhs')
+        		 (fits_u64 rhs')
+        		)
+        		 with
+        		| true =>
+        			(False_rect _ _)
+        		| false =>
+        			(mult lhs' rhs')
+        		end
+        	end
+        .
+
+        (* Rect_fits calls ["Rect_area"] *)
+        Definition Rect_fits (self: Rect) (other: Rect) : bool :=
+        	(andb (Nat.leb (Rect_area self)
+        	 (Rect_area other)
+        	)
+        	 (Nat.leb (Rect_width self)
+        	 (Rect_width other)
+        	)
+        	)
+        .
+
+        (* Rect_widen calls [] *)
+        Program Definition Rect_widen (self: Rect) (extra: nat) : Rect :=
+        	(Rect_set_width self let lhs' := (Rect_width self)
+        	 in
+        	match (fits_u64 (plus lhs' extra)
+        	)
+        	 with
+        	| true =>
+        		(plus lhs' extra)
+        	| false =>
+        		match (andb (fits_u64 extra)
+        		 (fits_u64 lhs')
+        		)
+        		 with
+        		| true =>
+        			(False_rect _ _)
+        		| false =>
+        			(plus lhs' extra)
+        		end
+        	end
+        	)
+        .
+        "###);
+    }
+
+    #[test]
+    fn test_method_errors() {
+        let errors = [
+            "impl Fee for Flat { fn fee(&self) -> u64 { 0 } }",
+            "impl<T> Wrapper<T> { fn get(&self) -> u64 { 0 } }",
+            "impl (u64, u64) { fn first(&self) -> u64 { 0 } }",
+            "impl Rect { const SIDES: u64 = 4; }",
+            "impl Rect { fn area(&self) -> u64 { self.area() } }",
+            "impl Rect { fn count(n: u64) -> u64 { if n == 0 { 0 } else { Self::count(n - 1) } } }",
+        ]
+        .map(|input| test_impl_block(input).err().unwrap().to_string());
+        assert_snapshot!(errors.join("\n"), @r###"
+        expected an inherent `impl` block, use `#[instance]` for an implementation of a trait
+        can't generate Coq `Definition` for the methods of a generic `impl` block
+        expected a user-defined type, like a `#[record]` struct
+        expected a method, other items aren't supported
+        methods can't call themselves
+        methods can't call themselves
+        "###);
+    }
+
+    #[test]
+    fn test_mut_args() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn swap_add(x: &mut u64, y: &mut u64) -> u64 {
+                    let old = *x;
+                    *x = *y;
+                    *y += old;
+                    *x + *y
+                }
+            "#),
+            @r###"
+        Program Definition swap_add (x: nat) (y: nat) : prod (prod nat nat) nat :=
+        	let old := x in
+        	let x := y in
+        	let y := match (fits_u64 (plus y old)
+        	)
+        	 with
+        	| true =>
+        		(plus y old)
+        	| false =>
+        		match (andb (andb (fits_u64 y)
+        		 (fits_u64 old)
+        		)
+        		 (fits_u64 x)
+        		)
+        		 with
+        		| true =>
+        			(False_rect _ _)
+        		| false =>
+        			(plus y old)
+        		end
+        	end
+        	 in
+        	(match (fits_u64 (plus x y)
+        	)
+        	 with
+        	| true =>
+        		(plus x y)
+        	| false =>
+        		match (andb (andb (fits_u64 old)
+        		 (fits_u64 x)
+        		)
+        		 (fits_u64 y)
+        		)
+        		 with
+        		| true =>
+        			(False_rect _ _)
+        		| false =>
+        			(plus x y)
+        		end
+        	end
+        	, x, y)
+        .
+        "###
+        );
+        assert_snapshot!(
+            test_as_def(r#"
+                fn reset(counter: &mut Counter) {
+                    counter.count = 0;
+                    counter.resets += 1;
+                }
+            "#),
+            @r###"
+        Program Definition reset (counter: Counter) : prod unit Counter :=
+        	let counter := (Counter_set_count counter 0)
+        	 in
+        	let counter := (Counter_set_resets counter let lhs' := (Counter_resets counter)
+        	 in
+        	match (fits_u64 (plus lhs' 1)
+        	)
+        	 with
+        	| true =>
+        		(plus lhs' 1)
+        	| false =>
+        		match (fits_u64 lhs')
+        		 with
+        		| true =>
+        			(False_rect _ _)
+        		| false =>
+        			(plus lhs' 1)
+        		end
+        	end
+        	)
+        	 in
+        	(tt, counter)
+        .
+        "###
+        );
+        assert_snapshot!(
+            test_as_def(r#"
+                fn take(counter: &mut Counter, n: u64) -> Option<u64> {
+                    if counter.count < n {
+                        return None;
+                    }
+                    counter.count -= n;
+                    Some(counter.count)
+                }
+            "#),
+            @r###"
+        Program Definition take (counter: Counter) (n: nat) : prod (option nat) Counter :=
+        	match (Nat.ltb (Counter_count counter)
+        	 n)
+        	 with
+        	| true =>
+        		(None, counter)
+        	| false =>
+        		let counter := (Counter_set_count counter let lhs' := (Counter_count counter)
+        		 in
+        		match (Nat.leb n lhs')
+        		 with
+        		| true =>
+        			(minus lhs' n)
+        		| false =>
+        			(False_rect _ _)
+        		end
+        		)
+        		 in
+        		((Some (Counter_count counter)
+        		)
+        		, counter)
+        	end
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_mut_calls() {
+        assert_snapshot!(
+            test_as_def(r#"
+                fn reset_all(a: Counter, b: &mut Counter) -> u64 {
+                    let mut a = a;
+                    reset(&mut a);
+                    let left = take(&mut a, 3);
+                    if a.count == 0 {
+                        reset(&mut b);
+                    }
+                    add(&mut a, &mut b)
+                }
+            "#),
+            @r###"
+        Definition reset_all (a: Counter) (b: Counter) : prod nat Counter :=
+        	let '(_, a) := (reset a)
+        	 in
+        	let '(left, a) := (take a 3)
+        	 in
+        	let b := match (Nat.eqb (Counter_count a)
+        	 0)
+        	 with
+        	| true =>
+        		let '(_, b) := (reset b)
+        		 in
+        		b
+        	| false =>
+        		b
+        	end
+        	 in
+        	let '(result'1, a, b) := (add a b)
+        	 in
+        	(result'1, b)
+        .
+        "###
+        );
+        assert_snapshot!(
+            test_as_def(r#"
+                fn reset_and_bump(counter: &mut Counter) -> u64 {
+                    reset(&mut *counter);
+                    bump(&mut *counter)
+                }
+            "#),
+            @r###"
+        Definition reset_and_bump (counter: Counter) : prod nat Counter :=
+        	let '(_, counter) := (reset counter)
+        	 in
+        	let '(result'1, counter) := (bump counter)
+        	 in
+        	(result'1, counter)
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_mut_methods() {
+        assert_snapshot!(
+            test_impl_block(r#"
+                impl Counter {
+                    fn bump(&mut self) -> u64 {
+                        self.count += 1;
+                        self.count
+                    }
+                    fn bump_twice(mut self) -> Counter {
+                        Counter::bump(&mut self);
+                        Self::bump(&mut self);
+                        self
+                    }
+                }
+            "#)
+            .unwrap(),
+            @r###"
+        (* Counter_bump calls [] *)
+        Program Definition Counter_bump (self: Counter) : prod nat Counter :=
+        	let self := (Counter_set_count self let lhs' := (Counter_count self)
+        	 in
+        	match (fits_u64 (plus lhs' 1)
+        	)
+        	 with
+        	| true =>
+        		(plus lhs' 1)
+        	| false =>
+        		match (fits_u64 lhs')
+        		 with
+        		| true =>
+        			(False_rect _ _)
+        		| false =>
+        			(plus lhs' 1)
+        		end
+        	end
+        	)
+        	 in
+        	((Counter_count self)
+        	, self)
+        .
+
+        (* Counter_bump_twice calls ["Counter_bump"] *)
+        Definition Counter_bump_twice (self: Counter) : Counter :=
+        	let '(_, self) := (Counter_bump self)
+        	 in
+        	let '(_, self) := (Counter_bump self)
+        	 in
+        	self
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_mut_errors() {
+        let errors = [
+            "fn f((a, b): &mut (u64, u64)) -> u64 { a }",
+            "fn f(c: Counter) -> u64 { reset(&mut c); 0 }",
+            "fn f(mut c: Counter) -> u64 { reset(&mut c.inner); 0 }",
+            "fn f(mut c: Counter) -> u64 { add(&mut c, &mut c) }",
+            "fn f(mut c: Counter) -> u64 { reset(&mut c) + 1 }",
+            "fn f(mut p: (u64, u64)) -> u64 { p.0 = 1; p.1 }",
+            "fn f(c: &mut u64) { c.count = 1; }",
+        ]
+        .map(test_as_err);
+        assert_snapshot!(errors.join("\n"), @r###"
+        expected a variable for a `&mut` argument
+        can't assign to a variable unless it's declared `mut` in the current block
+        expected a variable to be passed by `&mut`
+        can't pass the same variable by `&mut` twice
+        `&mut` is only supported on the arguments of a call which is a statement, or the value of a `let`
+        expected a named field on the left of an assignment
+        `nat` has no field `count`
+        "###);
+    }
 }
@@ -0,0 +1,275 @@
+use roq_core::ast;
+use syn::spanned::Spanned;
+
+use crate::ty::{check_type_names, default_numbers, use_type, TypeScope};
+
+/// A translated enum, along with the other types it refers to.
+pub struct Translation {
+    pub statements: Vec<ast::Statement>,
+    pub types: Vec<syn::Path>,
+}
+
+/// Convert an enum to a Coq `Inductive`, preceded by a `Record` for each struct variant.
+pub fn inductive_as_ast(source: &syn::ItemEnum) -> syn::Result<Translation> {
+    if !source.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            source.generics.span(),
+            "can't generate Coq `Inductive` for a generic enum",
+        ));
+    }
+
+    let name = source.ident.to_string();
+    let scope = TypeScope {
+        numbers: default_numbers()?,
+        ..TypeScope::default()
+    };
+    let mut statements = vec![];
+    let mut types = vec![];
+    let mut constructors = vec![];
+    for variant in &source.variants {
+        if let Some((eq, _)) = &variant.discriminant {
+            return Err(syn::Error::new(
+                eq.span(),
+                "explicit discriminants aren't supported",
+            ));
+        }
+
+        let constructor = ast::Inductive::constructor(&name, &variant.ident.to_string());
+        let args = match &variant.fields {
+            syn::Fields::Unit => vec![],
+            syn::Fields::Unnamed(unnamed) => unnamed
+                .unnamed
+                .iter()
+                .map(|field| use_type(&mut types, &scope, &field.ty))
+                .collect::<syn::Result<_>>()?,
+            syn::Fields::Named(named) => {
+                let mut fields = vec![];
+                for field in &named.named {
+                    let ident = field.ident.as_ref().unwrap().to_string();
+                    fields.push(ast::Binder {
+                        name: ast::Record::projection(&constructor, &ident),
+                        ty: use_type(&mut types, &scope, &field.ty)?,
+                        implicit: false,
+                    });
+                }
+                let record = ast::Inductive::fields(&constructor);
+                statements.push(ast::Statement::Record(ast::Record {
+                    name: record.clone(),
+                    fields,
+                }));
+                vec![ast::Ty::Named(record)]
+            }
+        };
+        constructors.push(ast::Constructor {
+            name: constructor,
+            args,
+        });
+    }
+
+    statements.push(ast::Statement::Inductive(ast::Inductive {
+        name,
+        constructors,
+    }));
+    // A recursive enum is defined by its own `Inductive`, rather than depending on itself.
+    types.retain(|path| !path.is_ident(&source.ident));
+    check_type_names(&types)?;
+    Ok(Translation { statements, types })
+}
+
+/// If `path` names a variant of an enum, like `Shape::Circle`, split it into the path to the enum
+/// and the Coq name of the variant's constructor.
+///
+/// Variants are recognised by being capitalized, as is the segment before them (the enum's name).
+/// The variants of `Option` and `Result` are left to `prelude_constructor`.
+pub fn constructor_of(path: &syn::Path) -> Option<(syn::Path, ast::Ident)> {
+    if prelude_constructor(path).is_some() {
+        return None;
+    }
+    let is_capitalized = |segment: &syn::PathSegment| {
+        segment.ident.to_string().starts_with(char::is_uppercase) && segment.arguments.is_empty()
+    };
+    let mut inductive = path.clone();
+    let variant = inductive.segments.pop()?.into_value();
+    let last = inductive.segments.pop()?.into_value();
+    if !is_capitalized(&variant) || !is_capitalized(&last) {
+        return None;
+    }
+    let constructor =
+        ast::Inductive::constructor(&last.ident.to_string(), &variant.ident.to_string());
+    inductive.segments.push(last);
+    Some((inductive, constructor))
+}
+
+/// If `path` names a variant of `Option` or `Result`, the Coq constructor it corresponds to.
+pub fn prelude_constructor(path: &syn::Path) -> Option<&'static str> {
+    let mut segments = path.segments.iter().rev();
+    let variant = segments.next()?;
+    let constructor = match variant.ident.to_string().as_str() {
+        "Some" => "Some",
+        "None" => "None",
+        "Ok" => "inl",
+        "Err" => "inr",
+        _ => return None,
+    };
+
+    // The variant may be qualified by its type, e.g. `Option::Some`.
+    let ty = if constructor == "Some" || constructor == "None" {
+        "Option"
+    } else {
+        "Result"
+    };
+    match segments.next() {
+        None => Some(constructor),
+        Some(segment) if segment.ident == ty => Some(constructor),
+        Some(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_snapshot;
+
+    fn parse(input: &str) -> syn::ItemEnum {
+        syn::parse_str(input).expect("Failed to parse source code")
+    }
+
+    fn test_as_vernacular(input: &str) -> ast::Vernacular {
+        let translation = inductive_as_ast(&parse(input)).expect("Failed to convert enum");
+        ast::Vernacular {
+            statements: translation.statements,
+        }
+    }
+
+    fn test_as_err(input: &str) -> String {
+        inductive_as_ast(&parse(input))
+            .err()
+            .expect("Expected enum conversion to fail")
+            .to_string()
+    }
+
+    fn constructor(input: &str) -> Option<(String, ast::Ident)> {
+        let (path, constructor) = constructor_of(&syn::parse_str(input).unwrap())?;
+        Some((quote::quote!(#path).to_string(), constructor))
+    }
+
+    #[test]
+    fn test_inductive() {
+        assert_snapshot!(
+            test_as_vernacular(r#"
+                enum Shape {
+                    Empty,
+                    Circle(u64),
+                    Line(i64, i64),
+                }
+            "#),
+            @r###"
+        Inductive Shape :=
+        | Shape_Empty : Shape
+        | Shape_Circle : nat -> Shape
+        | Shape_Line : Z -> Z -> Shape.
+        "###
+        );
+    }
+
+    #[test]
+    fn test_struct_variant() {
+        assert_snapshot!(
+            test_as_vernacular(r#"
+                enum Command {
+                    Stop,
+                    Move { dx: i64, dy: i64 },
+                }
+            "#),
+            @r###"
+        Record Command_Move_fields := {
+        	Command_Move_dx: Z;
+        	Command_Move_dy: Z
+        }.
+
+
+        Inductive Command :=
+        | Command_Stop : Command
+        | Command_Move : Command_Move_fields -> Command.
+        "###
+        );
+    }
+
+    #[test]
+    fn test_inductive_types() {
+        let translation = inductive_as_ast(&parse(
+            r#"
+                enum Message {
+                    Resize(Size),
+                    Draw { shape: shapes::Shape, visible: bool },
+                }
+            "#,
+        ))
+        .unwrap();
+        let types = translation
+            .types
+            .iter()
+            .map(|path| quote::quote!(#path).to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(types, ["Size", "shapes :: Shape"]);
+    }
+
+    #[test]
+    fn test_recursive_inductive() {
+        let source = r#"
+            enum Tree {
+                Leaf,
+                Node(Vec<Tree>),
+            }
+        "#;
+        assert!(inductive_as_ast(&parse(source)).unwrap().types.is_empty());
+        assert_snapshot!(test_as_vernacular(source), @r###"
+        Inductive Tree :=
+        | Tree_Leaf : Tree
+        | Tree_Node : list Tree -> Tree.
+        "###);
+    }
+
+    #[test]
+    fn test_constructor_of() {
+        assert_eq!(
+            constructor("Shape::Circle"),
+            Some(("Shape".into(), "Shape_Circle".into()))
+        );
+        assert_eq!(
+            constructor("shapes::Shape::Empty"),
+            Some(("shapes :: Shape".into(), "Shape_Empty".into()))
+        );
+        assert_eq!(constructor("Some"), None);
+        assert_eq!(constructor("util::max"), None);
+        assert_eq!(constructor("Shape::new"), None);
+        assert_eq!(constructor("Option::Some"), None);
+    }
+
+    #[test]
+    fn test_prelude_constructor() {
+        let prelude = |input| prelude_constructor(&syn::parse_str(input).unwrap());
+        assert_eq!(prelude("Some"), Some("Some"));
+        assert_eq!(prelude("Option::None"), Some("None"));
+        assert_eq!(prelude("Ok"), Some("inl"));
+        assert_eq!(prelude("std::result::Result::Err"), Some("inr"));
+        assert_eq!(prelude("Reply::Ok"), None);
+        assert_eq!(prelude("Shape::Circle"), None);
+    }
+
+    #[test]
+    fn test_generic_enum() {
+        assert_snapshot!(
+            test_as_err("enum Either<A, B> { Left(A), Right(B) }"),
+            @"can't generate Coq `Inductive` for a generic enum"
+        );
+    }
+
+    #[test]
+    fn test_discriminant() {
+        assert_snapshot!(
+            test_as_err("enum Level { Low = 1, High = 2 }"),
+            @"explicit discriminants aren't supported"
+        );
+    }
+}
@@ -1,53 +1,637 @@
+//! Procedural macros translating Rust functions and types into Coq vernacular.
+//!
+//! # Functions
+//!
+//! Functions which call themselves are emitted as a `Fixpoint`, and must be structurally
+//! recursive: each recursive call passes `n - k` for the same `u64` argument `n`, guarded by a
+//! check like `if n >= k` (or `if n == 0` for `k = 1`). A recursive function which isn't can be
+//! given a measure with `#[roq::decreasing(...)]` instead, and becomes a `Program Fixpoint`.
+//!
+//! Any other function called must also be annotated with `#[definition]`. Its vernacular is
+//! included before the caller's, so `prove!` only needs to name the function being proved. The
+//! Coq name of a function outside the crate's root module is qualified by the modules it's in,
+//! so `util::max` is `util_max`. Functions can't be mutually recursive.
+//!
+//! A generic function's type parameters become implicit arguments, so `fn id<T>(x: T) -> T` is
+//! `Definition id {T: Type} (x: T) : T`. Its lifetimes are erased.
+//!
+//! Bounds on `PartialEq` (or `Eq`), `Ord` (or `PartialOrd`), `Add<Output = T>` and `Default`
+//! become instances of Coq classes of the same names, like `` `{Ord T} ``, and are defined along
+//! with instances for `nat`, `N`, `Z` and (where it makes sense) `bool`. Operators on values of a
+//! type parameter, or the trait methods they stand for such as `a.lt(&b)`, apply the class's
+//! method, like `Ord_ltb a b`, and `T::default()` is `Default_default`. `Clone` and `Copy` bounds
+//! are erased, as is `clone()`.
+//!
+//! # Types
+//!
+//! Unsigned integers (`u64`) become `nat` by default, and signed integers (`i8` through `i128`,
+//! and `isize`) become `Z`, for which `ZArith` is imported.
+//!
+//! `Option<T>` becomes `option T`, and `Result<T, E>` becomes `sum T E`, with `Ok` and `Err`
+//! translated to `inl` and `inr`. Tuples become (nested) pairs, and can be destructured by `let`
+//! and in arguments.
+//!
+//! `Vec<T>`, slices and arrays all become `list T`, and `usize` becomes `nat`. Their `len`,
+//! `is_empty`, `first`, `get`, `contains`, `iter().sum()` and `push` are translated to the
+//! corresponding functions of Coq's `List` module.
+//!
+//! Iterator pipelines over a list are translated as operations on the list itself: closures
+//! become `fun` abstractions, and `map`, `filter`, `fold`, `any`, `all` and `count` become `map`,
+//! `filter`, `fold_left`, `existsb`, `forallb` and `length`. Adapters which don't change the
+//! elements, like `iter`, `copied` and `collect`, are erased.
+//!
+//! # Mutation and control flow
+//!
+//! Assigning to a `mut` variable shadows it with a new `let`, as does assigning to one of its
+//! fields, which updates it with the record's setter (`Counter_set_count counter ...`). An `if`
+//! whose branches assign to variables declared outside it evaluates to a tuple of their new
+//! values, which rebinds them. Statements evaluated only for their value would be dropped, so
+//! they're rejected.
+//!
+//! A `&mut` argument is passed by value, and can be assigned to like a `mut` one. The function
+//! returns its result followed by the arguments' new values, so `fn reset(c: &mut Counter)` is
+//! `Definition reset (c: Counter) : prod unit Counter`. A call passing `&mut` variables (or
+//! `&mut *c` for an `&mut` argument `c`), as a statement or the value of a `let`, rebinds them to
+//! their new values.
+//!
+//! A `return` moves the rest of the function into the branches which don't return, so guard
+//! clauses like `if b == 0 { return None; }` become an `if` around the rest of the function. The
+//! `?` operator binds the value in an `Option` or `Result` with a `let?` or `let!` notation,
+//! which returns the `None` or error early.
+//!
+//! # Loops
+//!
+//! A `for` loop over a range (`a..b` or `a..=b`) which reassigns `let mut` variables declared
+//! outside it becomes an auxiliary `Fixpoint`, `f_loop'N`, on the number of iterations left. It
+//! takes the loop variable, the variables the body uses and those it reassigns, and returns the
+//! latter's final values. The lemma `f_loop'N_equation` unfolds one iteration.
+//!
+//! A `while` loop must be annotated with a measure, a `u64` expression which decreases on each
+//! iteration (e.g. `#[roq::decreasing(n - i)]`), and becomes a `Program Fixpoint` with that
+//! measure. Coq leaves an obligation that the measure decreases, to be proved with
+//! `Next Obligation` in `prove!`. A `Program Fixpoint` isn't defined until its obligations are
+//! proved, so a loop's must be proved before the function containing it is defined: a `loop f`
+//! part emits `f`'s statements up to its next loop with obligations, which can then be proved in
+//! an `inline` part before the `function f` part emits the rest.
+//!
+//! # Panics
+//!
+//! A function which may panic, because it calls `panic!`, `unreachable!`, `todo!` or
+//! `unimplemented!`, or has an assertion (`assert!`, `assert_eq!`, `assert_ne!` or their
+//! `debug_` forms), is defined with `Program`. Each panic becomes `False_rect _ _`, leaving an
+//! obligation to prove it's never reached, given the conditions of the branches leading to it.
+//! Proving these with `Next Obligation` proves the function can't panic, and Coq doesn't define
+//! it until then.
+//!
+//! Each operation on a `u64` which might overflow, underflow or divide by zero panics unless it
+//! doesn't, like Rust with overflow checks enabled. An overflow is checked with `fits_u64`, which
+//! is defined along with the function, and its obligation assumes the `u64` variables in scope
+//! fit. With `#[definition(unchecked)]`, a `u64` which would overflow is modelled as if it
+//! didn't, since a `nat` is unbounded, and subtraction which would underflow gives 0, as does
+//! division by zero; this is simpler to prove things about, but not faithful to Rust.
+//!
+//! Signed integers are unbounded, but dividing one panics unless the divisor isn't 0 and the
+//! division doesn't overflow. A `Z` doesn't record which signed type it stands for, so
+//! `is_int_min` holds for the minimum of any of them, and dividing it by -1 panics.
+//!
+//! # Numbers
+//!
+//! Computing with large `nat` literals is impractically slow, so unsigned integers can be
+//! modelled by another type with `#[definition(numbers = "...")]`: `"nat"`, `"N"`, `"Z"` or
+//! `"word"`, an `N` which wraps around at `2^64` (with `wrap_u64`) like a `u64` without overflow
+//! checks, though division by zero still panics. Lengths and indices are converted to and from
+//! the `nat`s the `List` module uses. Only `nat`s can be structurally recursive, and only `nat`s
+//! and `N`s can be `checked`.
+//!
+//! The default for a whole crate, which `#[record]` and `#[inductive]` types use too, is read
+//! from the `ROQ_NUMBERS` environment variable, e.g. set in the `[env]` section of
+//! `.cargo/config.toml`. The crate is rebuilt when it changes.
+
 use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
-use quote::quote;
-use roq_core::ast;
+use quote::{quote, quote_spanned, ToTokens};
+use syn::spanned::Spanned;
 
 mod block;
+mod class;
+mod context;
 mod expr;
 mod func;
+mod inductive;
+mod pat;
+mod record;
+mod recursion;
 mod ty;
 
-/// Generate a Coq `Definition` statement from a Rust function.
+/// Generate a Coq `Definition` statement from a Rust function, or a `Fixpoint` if it's recursive.
+/// See the [crate documentation](crate) for how each part of the function is translated.
+///
+/// The options are:
+///
+/// - `checked` (the default for `nat`s and `N`s) or `unchecked`: whether arithmetic on `u64`s
+///   panics where Rust's would, when it overflows, underflows or divides by zero.
+/// - `numbers = "..."`: the Coq type modelling unsigned integers, `"nat"`, `"N"`, `"Z"` or
+///   `"word"`, defaulting to the `ROQ_NUMBERS` environment variable, or else `"nat"`.
+///
+/// A function which isn't structurally recursive, or a `while` loop, is given a measure with
+/// `#[roq::decreasing(...)]`, below `#[definition]` or on the loop.
 #[proc_macro_attribute]
-pub fn definition(_attr: TokenStream, item: TokenStream) -> TokenStream {
-    let item = TokenStream2::from(item);
-    let input: syn::ItemFn = match syn::parse2(item.clone()) {
+pub fn definition(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let options = match func::Options::parse(attr.into()) {
+        Ok(options) => options,
+        Err(err) => return TokenStream::from(err.to_compile_error()),
+    };
+    let input: syn::ItemFn = match syn::parse(item) {
         Ok(input) => input,
         Err(err) => return TokenStream::from(err.to_compile_error()),
     };
 
-    // Convert the function to a Coq `Definition` AST node.
-    let definition: ast::Definition = match func::func_as_ast(&input) {
-        Ok(definition) => definition,
+    // Convert the function to a Coq `Definition` or `Fixpoint` AST node.
+    let translation = match func::func_as_ast(&input, &options) {
+        Ok(translation) => translation,
         Err(err) => return TokenStream::from(err.to_compile_error()),
     };
 
-    // Serialize this to Rust literal tokens.
-    let definition_str = uneval::to_string(definition)
-        .expect("Failed to serialize Coq AST to Rust literal expressions");
-    let definition_tokens: TokenStream2 = definition_str
-        .parse()
-        .expect("Serialized AST is not a valid Rust literal expression");
+    // Emit the original function, without the measures only we use, plus a module named
+    // `$fn_name::roq` containing a `vernacular` function that returns the Coq `Definition` or
+    // `Fixpoint`, along with everything it depends on.
+    let mut item = input;
+    recursion::strip_measures(&mut item);
+    let module = definition_module(&item.sig.ident, translation);
+    TokenStream::from(quote! {
+        #item
+        #module
+    })
+}
 
-    // Emit the original, unmodified function, plus a module named `$fn_name::roq` containing an
-    // `as_definition` function that returns the Coq `Definition`.
-    let fn_name = input.sig.ident;
+/// Generate a Coq function from each method of an inherent `impl` block.
+///
+/// Each method is translated like a `#[definition]` function (taking the same options) named
+/// `Type_method`, which takes `self` as its first argument if the method does, so `self.width`
+/// is `Rect_width self`. Calls of the type's other methods, on `self` (`self.area()`) or by path
+/// (`Self::new(...)` or `Rect::new(...)`, which `#[definition]` functions can use too), call their
+/// functions, and include their vernacular like any other callee. Methods can't call themselves.
+/// `&mut self` is a `&mut` argument, so a method taking it must be called by path, like
+/// `Self::bump(&mut *self)`, to rebind `self`.
+///
+/// Each method's vernacular is included in a `prove!` with a `function` part naming it, like
+/// `function Rect_area`.
+#[proc_macro_attribute]
+pub fn impl_block(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let options = match func::Options::parse(attr.into()) {
+        Ok(options) => options,
+        Err(err) => return TokenStream::from(err.to_compile_error()),
+    };
+    let mut input: syn::ItemImpl = match syn::parse(item) {
+        Ok(input) => input,
+        Err(err) => return TokenStream::from(err.to_compile_error()),
+    };
+    let translations = match func::impl_block_as_ast(&input, &options) {
+        Ok(translations) => translations,
+        Err(err) => return TokenStream::from(err.to_compile_error()),
+    };
 
+    // Emit the `impl` block, without the measures only we use, plus a module for each method like
+    // that of a `#[definition]` function, named after the function it's translated to.
+    recursion::strip_impl_measures(&mut input);
+    let modules = translations.into_iter().map(|(name, translation)| {
+        let name = syn::Ident::new(&name, proc_macro2::Span::call_site());
+        definition_module(&name, translation)
+    });
     TokenStream::from(quote! {
-        #item
+        #input
+        #(
+            #[allow(non_snake_case)]
+            #modules
+        )*
+    })
+}
+
+/// Emit a module named `$fn_name::roq` containing a `vernacular` function that returns the Coq
+/// `Definition` or `Fixpoint` a function is translated to, along with everything it depends on,
+/// and a `signature` function that returns its type, for elaborating calls to it.
+fn definition_module(fn_name: &syn::Ident, translation: func::Translation) -> TokenStream2 {
+    // Serialize this to Rust literal tokens.
+    let function_tokens = function_as_tokens(&translation, &quote!(names()));
+    let func::Translation { callees, types, .. } = translation;
+
+    // Refer to each callee's generated module, and each type, from inside our own `$fn_name::roq`
+    // module.
+    let callees = callees
+        .iter()
+        .map(|path| callee_as_tokens(path, &outer_path(path)));
+    let names = names(types.iter().map(|path| type_names(path, &outer_path(path))));
+    let types = types.iter().map(|path| {
+        let span = path.span();
+        let path = outer_path(path);
+        quote_spanned!(span=> <#path as ::roq_core::CoqType>::vernacular)
+    });
+    let module = format!("::{fn_name}::roq");
+    let numbers = track_numbers();
+
+    quote! {
         #[doc(hidden)]
         pub mod #fn_name {
             pub mod roq {
-                pub fn definition() -> roq_core::ast::Definition {
-                    use ::roq_core::ast::*;
-                    #definition_tokens
+                /// The function, before it's elaborated, with the names of the types it uses
+                /// qualified.
+                pub fn function() -> ::roq_core::function::Function {
+                    #function_tokens
+                }
+                /// The prefix qualifying the function's Coq name by the module it's defined in.
+                pub fn prefix() -> ::std::string::String {
+                    ::roq_core::function::prefix(::std::module_path!().strip_suffix(#module).unwrap())
+                }
+                /// The function's Coq name and type.
+                pub fn signature() -> ::roq_core::typing::Signature {
+                    function().signature(&prefix())
+                }
+                /// Each `#[definition]` function called by this one.
+                pub fn callees() -> ::std::vec::Vec<::roq_core::function::Callee> {
+                    ::std::vec![#(#callees),*]
+                }
+                /// The vernacular of each user-defined type used by this function.
+                pub fn types() -> ::std::vec::Vec<fn() -> roq_core::ast::Vernacular> {
+                    ::std::vec![#(#types),*]
                 }
-                pub fn vernacular() -> roq_core::ast::Vernacular {
-                    let defn = definition();
-                    ::roq_core::ast::Statement::Definition(defn).into()
+                /// The qualified names of what the types used by this function define.
+                pub fn names() -> ::roq_core::rename::Names {
+                    #names
+                }
+                /// This function's statement, preceded by the modules it needs, the
+                /// statements of the types it uses and everything it calls, and its auxiliary
+                /// statements, or the first error elaborating them.
+                pub fn vernacular() -> ::std::result::Result<
+                    ::roq_core::ast::Vernacular,
+                    ::roq_core::function::TranslationError,
+                > {
+                    #numbers
+                    ::roq_core::function::vernacular(&prefix(), &[function()], &types(), &callees())
                 }
             }
         }
+    }
+}
+
+/// Give the measure which decreases on each iteration of a `while` loop, or each recursive call
+/// of a function, in a `#[definition]` function.
+///
+/// The measure is read (and removed) by `#[definition]`, so on a function, this must come after
+/// it.
+#[proc_macro_attribute]
+pub fn decreasing(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item = TokenStream2::from(item);
+    let err = syn::Error::new(
+        proc_macro2::Span::call_site(),
+        "`#[decreasing]` must be placed below `#[definition]`",
+    );
+    let err = err.to_compile_error();
+    TokenStream::from(quote!(#err #item))
+}
+
+/// Generate a Coq `Record` from a Rust struct with named fields.
+///
+/// Each field becomes a projection prefixed with the struct's name (so `config.width` is
+/// `Config_width config`), since Coq doesn't allow two records to have fields with the same
+/// name. A `Config_set_width` function is also defined for each field, which is used to
+/// translate struct update syntax (`Config { width, ..config }`).
+///
+/// Functions using the struct include its `Record` in their vernacular, like their callees. Like a
+/// function's, its names are qualified by the modules it's in, so the field `x` of `geo::Point` is
+/// `geo_Point_x`. A function can't use two types of the same name.
+#[proc_macro_attribute]
+pub fn record(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item = TokenStream2::from(item);
+    let input: syn::ItemStruct = match syn::parse2(item.clone()) {
+        Ok(input) => input,
+        Err(err) => return TokenStream::from(err.to_compile_error()),
+    };
+
+    let record::Translation { statements, types } = match record::record_as_ast(&input) {
+        Ok(translation) => translation,
+        Err(err) => return TokenStream::from(err.to_compile_error()),
+    };
+
+    // Emit the original, unmodified struct, plus an implementation of `CoqType` for it.
+    TokenStream::from(coq_type(item, &input.ident, statements, &types))
+}
+
+/// Generate a Coq `Inductive` type from a Rust enum.
+///
+/// Each variant becomes a constructor prefixed with the enum's name, like the fields of a
+/// `#[record]`, so `Shape::Circle(r)` is `Shape_Circle r`. The fields of a struct variant are
+/// gathered into a record, `Shape_Rect_fields`, whose projections are prefixed with the
+/// constructor's name (`Shape_Rect_width`).
+///
+/// Functions using the enum include its `Inductive` in their vernacular, like their callees, and
+/// its names are qualified like a `#[record]`'s.
+#[proc_macro_attribute]
+pub fn inductive(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item = TokenStream2::from(item);
+    let input: syn::ItemEnum = match syn::parse2(item.clone()) {
+        Ok(input) => input,
+        Err(err) => return TokenStream::from(err.to_compile_error()),
+    };
+
+    let inductive::Translation { statements, types } = match inductive::inductive_as_ast(&input) {
+        Ok(translation) => translation,
+        Err(err) => return TokenStream::from(err.to_compile_error()),
+    };
+
+    // Emit the original, unmodified enum, plus an implementation of `CoqType` for it.
+    TokenStream::from(coq_type(item, &input.ident, statements, &types))
+}
+
+/// Generate a Coq `Class` from a Rust trait, with a method for each of the trait's.
+///
+/// The class is over the type implementing the trait, `Self`, and each method is a function
+/// prefixed with the trait's name which takes `self` (if the method does) followed by the method's
+/// arguments, so `fn fee(&self, amount: u64) -> u64` in `trait Fee` is
+/// `Fee_fee: Self -> nat -> nat`. Generic traits and methods, supertraits and default methods
+/// aren't supported.
+///
+/// The vernacular is included in a `prove!` with a `class` part naming the trait.
+#[proc_macro_attribute]
+pub fn class(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item = TokenStream2::from(item);
+    let input: syn::ItemTrait = match syn::parse2(item.clone()) {
+        Ok(input) => input,
+        Err(err) => return TokenStream::from(err.to_compile_error()),
+    };
+
+    let class::Translation {
+        statements,
+        types,
+        requires,
+        ..
+    } = match class::class_as_ast(&input) {
+        Ok(translation) => translation,
+        Err(err) => return TokenStream::from(err.to_compile_error()),
+    };
+
+    // Emit the original, unmodified trait, plus a function of the same name returning the
+    // `Class`. Functions and traits live in different namespaces, so importing the trait imports
+    // the function too.
+    let name = &input.ident;
+    let names = names(types.iter().map(|path| type_names(path, path)));
+    let vernacular = vernacular(statements, &types, &requires, names);
+    TokenStream::from(quote! {
+        #item
+        #[doc(hidden)]
+        #[allow(non_snake_case)]
+        pub fn #name() -> ::roq_core::ast::Vernacular {
+            #vernacular
+        }
+    })
+}
+
+/// Generate a Coq `Instance` from an implementation of a `#[class]` trait for a user-defined
+/// type.
+///
+/// Each method is translated like a `#[definition]` function (taking the same options) named
+/// after the instance, `Trait_Type_method`, with `self` as its first argument, so `self.fee` is
+/// `Flat_fee self`. The instance, `Trait_Type`, defines each of the class's methods as the
+/// corresponding function.
+///
+/// The vernacular, which includes the class's, is included in a `prove!` with an `instance` part
+/// naming the instance, like `instance Fee_Flat`.
+#[proc_macro_attribute]
+pub fn instance(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let options = match func::Options::parse(attr.into()) {
+        Ok(options) => options,
+        Err(err) => return TokenStream::from(err.to_compile_error()),
+    };
+    let item = TokenStream2::from(item);
+    let input: syn::ItemImpl = match syn::parse2(item.clone()) {
+        Ok(input) => input,
+        Err(err) => return TokenStream::from(err.to_compile_error()),
+    };
+
+    let class::Translation {
+        statements,
+        functions,
+        callees,
+        types,
+        ..
+    } = match class::instance_as_ast(&input, &options) {
+        Ok(translation) => translation,
+        Err(err) => return TokenStream::from(err.to_compile_error()),
+    };
+
+    // The class is defined first, by the function `#[class]` emits alongside the trait, like a
+    // type the methods use.
+    let (_, trait_path, _) = input.trait_.as_ref().unwrap();
+    let names = names(types.iter().map(|path| type_names(path, path)));
+    let types = types
+        .iter()
+        .map(|path| {
+            let span = path.span();
+            quote_spanned!(span=> <#path as ::roq_core::CoqType>::vernacular)
+        })
+        .chain(std::iter::once(
+            quote_spanned!(trait_path.span()=> #trait_path),
+        ));
+    let callees = callees.iter().map(|path| callee_as_tokens(path, path));
+    let functions = functions
+        .iter()
+        .map(|function| function_as_tokens(function, &quote!(names)));
+
+    // Emit the original, unmodified `impl` block, plus a function named after the instance
+    // returning its vernacular. The methods' functions aren't qualified by the module they're
+    // defined in, like the instance.
+    let roq_core::ast::Statement::Instance(instance) = statements.last().unwrap() else {
+        unreachable!("the last statement should be the instance");
+    };
+    let name = syn::Ident::new(&instance.name, proc_macro2::Span::call_site());
+    let statements = statements.into_iter().map(statement_as_tokens);
+    let numbers = track_numbers();
+    TokenStream::from(quote! {
+        #item
+        #[doc(hidden)]
+        #[allow(non_snake_case)]
+        pub fn #name() -> ::std::result::Result<
+            ::roq_core::ast::Vernacular,
+            ::roq_core::function::TranslationError,
+        > {
+            #numbers
+            let names = #names;
+            let mut vernacular = ::roq_core::function::vernacular(
+                "",
+                &[#({ #functions }),*],
+                &[#(#types),*],
+                &[#(#callees),*],
+            )?;
+            #(
+                vernacular.push({
+                    use ::roq_core::ast::*;
+                    let mut statement: Statement = #statements;
+                    ::roq_core::rename::Rename::rename(&mut statement, &names);
+                    statement
+                });
+            )*
+            ::std::result::Result::Ok(vernacular)
+        }
     })
 }
+
+/// An expression building the vernacular of `statements`, preceded by the modules they need and
+/// those of the types they use, with the names in `names`, an expression of type `Names`,
+/// qualified.
+fn vernacular(
+    statements: Vec<roq_core::ast::Statement>,
+    types: &[syn::Path],
+    requires: &[String],
+    names: TokenStream2,
+) -> TokenStream2 {
+    let statements = statements.into_iter().map(statement_as_tokens);
+    let types = types.iter().map(|path| {
+        let span = path.span();
+        quote_spanned!(span=> <#path as ::roq_core::CoqType>::vernacular)
+    });
+    let numbers = track_numbers();
+    quote! {
+        #numbers
+        let names = #names;
+        let mut vernacular = ::roq_core::ast::Vernacular::default();
+        #(
+            vernacular.push(::roq_core::ast::Statement::Require(#requires.into()));
+        )*
+        #(
+            vernacular.extend(#types().statements);
+        )*
+        #(
+            vernacular.push({
+                use ::roq_core::ast::*;
+                let mut statement: Statement = #statements;
+                ::roq_core::rename::Rename::rename(&mut statement, &names);
+                statement
+            });
+        )*
+        vernacular
+    }
+}
+
+/// The function returning the names the type at `path` defines (see `CoqType::names`), which
+/// `resolved` refers to from where it's emitted.
+fn type_names(path: &syn::Path, resolved: &impl ToTokens) -> TokenStream2 {
+    quote_spanned!(path.span()=> <#resolved as ::roq_core::CoqType>::names)
+}
+
+/// An expression collecting the names returned by each of the functions `names` into `Names`.
+fn names(names: impl Iterator<Item = TokenStream2>) -> TokenStream2 {
+    quote! {{
+        let mut names = ::roq_core::rename::Names::new();
+        #(
+            names.extend(#names());
+        )*
+        names
+    }}
+}
+
+/// Emit a user-defined type's item, followed by an implementation of `CoqType` whose vernacular is
+/// the given statements, preceded by those of the other types it uses.
+fn coq_type(
+    item: TokenStream2,
+    name: &syn::Ident,
+    statements: Vec<roq_core::ast::Statement>,
+    types: &[syn::Path],
+) -> TokenStream2 {
+    let defined = roq_core::rename::defined(&statements);
+    let names = names(
+        types
+            .iter()
+            .map(|path| type_names(path, path))
+            .chain(std::iter::once(quote!(
+                <Self as ::roq_core::CoqType>::names
+            ))),
+    );
+    let vernacular = vernacular(statements, types, &[], names);
+    quote! {
+        #item
+        impl ::roq_core::CoqType for #name {
+            fn names() -> ::std::vec::Vec<(::std::string::String, ::std::string::String)> {
+                let prefix = ::roq_core::function::prefix(::std::module_path!());
+                [#(#defined),*]
+                    .into_iter()
+                    .map(|name: &str| (name.into(), ::std::format!("{prefix}{name}")))
+                    .collect()
+            }
+            fn vernacular() -> ::roq_core::ast::Vernacular {
+                #vernacular
+            }
+        }
+    }
+}
+
+/// A statement reading `ROQ_NUMBERS`, which the translation's default model of unsigned integers
+/// was read from (see `ty::default_numbers`), so that Cargo rebuilds the crate when it changes.
+fn track_numbers() -> TokenStream2 {
+    quote!(let _ = ::std::option_env!("ROQ_NUMBERS");)
+}
+
+/// An expression describing the `#[definition]` function called by `path`, whose generated module
+/// is at `resolved`, which refers to it from where the expression is emitted.
+fn callee_as_tokens(path: &syn::Path, resolved: &impl ToTokens) -> TokenStream2 {
+    let name = context::callee_name(path);
+    quote_spanned! {path.span()=>
+        ::roq_core::function::Callee {
+            path: #name,
+            signature: #resolved::roq::signature,
+            vernacular: #resolved::roq::vernacular,
+        }
+    }
+}
+
+/// Serialize a translated function to Rust literal tokens, which build it when evaluated, located
+/// by the `file:line:column` of its spans, with the names in `names`, an expression of type
+/// `Names`, qualified.
+fn function_as_tokens(translation: &func::Translation, names: &TokenStream2) -> TokenStream2 {
+    let function_str = uneval::to_string(&translation.function)
+        .expect("Failed to serialize Coq AST to Rust literal expressions");
+    let function: TokenStream2 = function_str
+        .parse()
+        .expect("Serialized AST is not a valid Rust literal expression");
+    let locations = translation.spans.iter().map(|span| location(*span));
+    let location = location(translation.span);
+    quote! {
+        use ::roq_core::ast::*;
+        use ::roq_core::function::Function;
+        use ::roq_core::typing::Numbers;
+        let mut function: Function = #function;
+        function.location = #location.into();
+        function.locations = ::std::vec![#(#locations.into()),*];
+        ::roq_core::rename::Rename::rename(&mut function, &#names);
+        function
+    }
+}
+
+/// Serialize a statement to Rust literal tokens, which build it when evaluated.
+fn statement_as_tokens(statement: roq_core::ast::Statement) -> TokenStream2 {
+    let statement_str = uneval::to_string(statement)
+        .expect("Failed to serialize Coq AST to Rust literal expressions");
+    statement_str
+        .parse()
+        .expect("Serialized AST is not a valid Rust literal expression")
+}
+
+/// A string literal of where `span` is in the source, as `file:line:column`. Only the compiler
+/// knows, so this asks it with macros spanned there.
+fn location(span: proc_macro2::Span) -> TokenStream2 {
+    quote_spanned! {span=>
+        ::std::concat!(::std::file!(), ":", ::std::line!(), ":", ::std::column!())
+    }
+}
+
+/// Convert a path relative to the module containing a `#[definition]` function to one relative
+/// to the `$fn_name::roq` module generated inside it.
+fn outer_path(path: &syn::Path) -> TokenStream2 {
+    let first = &path.segments.first().unwrap().ident;
+    if path.leading_colon.is_some() || first == "crate" {
+        quote!(#path)
+    } else if first == "self" {
+        let rest = path.segments.iter().skip(1);
+        quote!(super::super::#(#rest)::*)
+    } else {
+        quote!(super::super::#path)
+    }
+}
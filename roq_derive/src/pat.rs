@@ -0,0 +1,270 @@
+use roq_core::ast;
+use syn::spanned::Spanned;
+use syn::visit::Visit;
+
+use crate::inductive::{constructor_of, prelude_constructor};
+
+pub fn pat_as_ast(source: &syn::Pat) -> syn::Result<ast::Pattern> {
+    match source {
+        // Traverse parenthesized patterns.
+        syn::Pat::Paren(syn::PatParen { pat, .. }) => pat_as_ast(pat),
+
+        // References are erased, so match through them.
+        syn::Pat::Reference(syn::PatReference { pat, .. }) => pat_as_ast(pat),
+
+        // Match the wildcard pattern.
+        syn::Pat::Wild(_) => Ok(ast::Pattern::Wildcard),
+
+        // Match literal patterns.
+        syn::Pat::Lit(syn::ExprLit {
+            lit: syn::Lit::Int(int),
+            ..
+        }) => Ok(ast::Pattern::Expr(ast::Expr::Nat(int.base10_parse()?))),
+        syn::Pat::Lit(syn::ExprLit {
+            lit: syn::Lit::Bool(boolean),
+            ..
+        }) => Ok(ast::Pattern::Expr(ast::Expr::Bool(boolean.value))),
+
+        // Match bindings, like `x` or `x @ Some(_)`.
+        syn::Pat::Ident(ident) => {
+            let name = ident.ident.to_string();
+
+            // A bare identifier is a binding, unless it's `None`. Anything else capitalized could
+            // be a constant or an imported variant, which needs its path to be resolved.
+            if ident.subpat.is_none() && name.starts_with(char::is_uppercase) {
+                let path = syn::Path::from(ident.ident.clone());
+                return Ok(ast::Pattern::Constructor {
+                    name: constructor_name(&path)?,
+                    args: vec![],
+                });
+            }
+
+            match &ident.subpat {
+                None => Ok(ast::Pattern::Ident(name)),
+                Some((_at, subpat)) => Ok(ast::Pattern::As {
+                    pattern: Box::new(pat_as_ast(subpat)?),
+                    ident: name,
+                }),
+            }
+        }
+
+        // Match unit constructors, like `Shape::Empty`.
+        syn::Pat::Path(syn::PatPath {
+            qself: None, path, ..
+        }) => Ok(ast::Pattern::Constructor {
+            name: constructor_name(path)?,
+            args: vec![],
+        }),
+
+        // Match constructors applied to patterns, like `Some(x)`.
+        syn::Pat::TupleStruct(syn::PatTupleStruct {
+            qself: None,
+            path,
+            elems,
+            ..
+        }) => Ok(ast::Pattern::Constructor {
+            name: constructor_name(path)?,
+            args: elems.iter().map(pat_as_ast).collect::<syn::Result<_>>()?,
+        }),
+
+        // Match struct variants, like `Shape::Rect { w, .. }`, by the record of their fields, and
+        // structs by their own fields.
+        syn::Pat::Struct(syn::PatStruct {
+            qself: None,
+            path,
+            fields,
+            ..
+        }) => {
+            let (record, constructor) = match constructor_of(path) {
+                Some((_, constructor)) => (constructor.clone(), Some(constructor)),
+                None => (path.segments.last().unwrap().ident.to_string(), None),
+            };
+            let mut patterns = vec![];
+            for field in fields {
+                let syn::Member::Named(ident) = &field.member else {
+                    return Err(syn::Error::new(
+                        field.member.span(),
+                        "expected a named field",
+                    ));
+                };
+                let projection = ast::Record::projection(&record, &ident.to_string());
+                patterns.push((projection, pat_as_ast(&field.pat)?));
+            }
+            let pattern = ast::Pattern::Record { fields: patterns };
+            Ok(match constructor {
+                Some(name) => ast::Pattern::Constructor {
+                    name,
+                    args: vec![pattern],
+                },
+                None => pattern,
+            })
+        }
+
+        // Match tuples.
+        syn::Pat::Tuple(syn::PatTuple { elems, .. }) => Ok(ast::Pattern::Tuple(
+            elems.iter().map(pat_as_ast).collect::<syn::Result<_>>()?,
+        )),
+
+        // Match or-patterns.
+        syn::Pat::Or(syn::PatOr { cases, .. }) => Ok(ast::Pattern::Or(
+            cases.iter().map(pat_as_ast).collect::<syn::Result<_>>()?,
+        )),
+
+        _ => Err(syn::Error::new(source.span(), "unsupported pattern")),
+    }
+}
+
+/// Check whether a pattern matches every value of its type, without knowing what that type is.
+pub fn is_irrefutable(pattern: &ast::Pattern) -> bool {
+    match pattern {
+        ast::Pattern::Wildcard | ast::Pattern::Ident(_) => true,
+        ast::Pattern::As { pattern, .. } => is_irrefutable(pattern),
+        ast::Pattern::Tuple(elems) => elems.iter().all(is_irrefutable),
+        ast::Pattern::Record { fields } => fields.iter().all(|(_, field)| is_irrefutable(field)),
+        ast::Pattern::Or(alternatives) => alternatives.iter().any(is_irrefutable),
+        ast::Pattern::Expr(_) | ast::Pattern::Constructor { .. } => false,
+    }
+}
+
+/// The variables bound by a pattern, in order.
+pub fn bound_vars(pattern: &ast::Pattern) -> Vec<ast::Ident> {
+    match pattern {
+        ast::Pattern::Expr(_) | ast::Pattern::Wildcard => vec![],
+        ast::Pattern::Ident(ident) => vec![ident.clone()],
+        ast::Pattern::Constructor { args: elems, .. } | ast::Pattern::Tuple(elems) => {
+            elems.iter().flat_map(bound_vars).collect()
+        }
+        ast::Pattern::As { pattern, ident } => {
+            let mut vars = bound_vars(pattern);
+            vars.push(ident.clone());
+            vars
+        }
+        ast::Pattern::Record { fields } => fields
+            .iter()
+            .flat_map(|(_, pattern)| bound_vars(pattern))
+            .collect(),
+
+        // Every alternative binds the same variables.
+        ast::Pattern::Or(alternatives) => alternatives.first().map_or(vec![], bound_vars),
+    }
+}
+
+/// The variables bound by a pattern which are declared `mut`, and so can be reassigned.
+pub fn mutable_vars(source: &syn::Pat) -> Vec<ast::Ident> {
+    struct Mutable(Vec<ast::Ident>);
+
+    impl<'ast> Visit<'ast> for Mutable {
+        fn visit_pat_ident(&mut self, node: &'ast syn::PatIdent) {
+            if node.mutability.is_some() {
+                self.0.push(node.ident.to_string());
+            }
+            syn::visit::visit_pat_ident(self, node);
+        }
+    }
+
+    let mut mutable = Mutable(vec![]);
+    mutable.visit_pat(source);
+    mutable.0
+}
+
+/// The Coq name of the constructor at `path`: the prefixed name of an enum's variant, or the
+/// equivalent of a variant of `Option` or `Result`.
+fn constructor_name(path: &syn::Path) -> syn::Result<ast::Ident> {
+    if let Some(constructor) = prelude_constructor(path) {
+        return Ok(constructor.into());
+    }
+    match constructor_of(path) {
+        Some((_, constructor)) => Ok(constructor),
+        None => Err(syn::Error::new(
+            path.span(),
+            "expected a variant of an enum, named along with the enum like `Shape::Empty`",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_snapshot;
+
+    fn parse(input: &str) -> syn::Pat {
+        syn::parse::Parser::parse_str(syn::Pat::parse_multi, input)
+            .expect("Failed to parse source code")
+    }
+
+    fn pat(input: &str) -> ast::Pattern {
+        pat_as_ast(&parse(input)).expect("Failed to convert to pattern")
+    }
+
+    #[test]
+    fn test_simple() {
+        assert_snapshot!(pat("_"), @"_");
+        assert_snapshot!(pat("x"), @"x");
+        assert_snapshot!(pat("3"), @"3");
+        assert_snapshot!(pat("true"), @"true");
+    }
+
+    #[test]
+    fn test_constructor() {
+        assert_snapshot!(pat("None"), @"None");
+        assert_snapshot!(pat("Some(x)"), @"Some x");
+        assert_snapshot!(pat("Some(Some(_))"), @"Some (Some _)");
+        assert_snapshot!(pat("Shape::Rect(w, 0)"), @"Shape_Rect w 0");
+        assert_snapshot!(pat("Shape::Empty"), @"Shape_Empty");
+        assert_snapshot!(pat("Ok(x)"), @"inl x");
+        assert_snapshot!(pat("Result::Err(_)"), @"inr _");
+        assert_snapshot!(pat("Option::None"), @"None");
+    }
+
+    #[test]
+    fn test_constant_unsupported() {
+        let err = |input| pat_as_ast(&parse(input)).unwrap_err().to_string();
+        assert_snapshot!(err("MAX"), @"expected a variant of an enum, named along with the enum like `Shape::Empty`");
+        assert_snapshot!(err("limits::MAX"), @"expected a variant of an enum, named along with the enum like `Shape::Empty`");
+        assert_snapshot!(err("Empty"), @"expected a variant of an enum, named along with the enum like `Shape::Empty`");
+        assert_snapshot!(err("Wrapper(x)"), @"expected a variant of an enum, named along with the enum like `Shape::Empty`");
+    }
+
+    #[test]
+    fn test_struct() {
+        assert_snapshot!(pat("Shape::Rect { w, h: 0 }"), @"Shape_Rect {| Shape_Rect_w := w; Shape_Rect_h := 0 |}");
+        assert_snapshot!(pat("Shape::Rect { w: Some(w), .. }"), @"Shape_Rect {| Shape_Rect_w := Some w |}");
+        assert_snapshot!(pat("Size { width, .. }"), @"{| Size_width := width |}");
+    }
+
+    #[test]
+    fn test_binding() {
+        assert_snapshot!(pat("x @ Some(_)"), @"Some _ as x");
+        assert_snapshot!(pat("Some(y @ Some(_))"), @"Some (Some _ as y)");
+    }
+
+    #[test]
+    fn test_tuple() {
+        assert_snapshot!(pat("(a, _, 0)"), @"(a, _, 0)");
+        assert_snapshot!(pat("Some((a, b))"), @"Some (a, b)");
+    }
+
+    #[test]
+    fn test_bound_vars() {
+        assert_eq!(bound_vars(&pat("(a, _, (b, 0))")), ["a", "b"]);
+        assert_eq!(
+            bound_vars(&pat("x @ Some((y, Size { width, .. }))")),
+            ["y", "width", "x"]
+        );
+        assert_eq!(bound_vars(&pat("(0, x) | (x, 0)")), ["x"]);
+    }
+
+    #[test]
+    fn test_mutable_vars() {
+        assert_eq!(mutable_vars(&parse("mut x")), ["x"]);
+        assert_eq!(mutable_vars(&parse("(mut a, b, Some(mut c))")), ["a", "c"]);
+        assert!(mutable_vars(&parse("(a, ref b)")).is_empty());
+    }
+
+    #[test]
+    fn test_or() {
+        assert_snapshot!(pat("0 | 1"), @"0 | 1");
+        assert_snapshot!(pat("(0 | 1, x)"), @"((0 | 1), x)");
+        assert_snapshot!(pat("Some(0 | 1)"), @"Some (0 | 1)");
+    }
+}
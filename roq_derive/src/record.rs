@@ -0,0 +1,200 @@
+use roq_core::ast;
+use syn::spanned::Spanned;
+
+use crate::ty::{check_type_names, default_numbers, use_type, TypeScope};
+
+/// A translated struct, along with the other types it refers to.
+pub struct Translation {
+    pub statements: Vec<ast::Statement>,
+    pub types: Vec<syn::Path>,
+}
+
+/// Convert a struct to a Coq `Record`, followed by a setter for each of its fields.
+pub fn record_as_ast(source: &syn::ItemStruct) -> syn::Result<Translation> {
+    if !source.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            source.generics.span(),
+            "can't generate Coq `Record` for a generic struct",
+        ));
+    }
+    let syn::Fields::Named(named) = &source.fields else {
+        return Err(syn::Error::new(
+            source.fields.span(),
+            "expected a struct with named fields",
+        ));
+    };
+
+    let name = source.ident.to_string();
+    let scope = TypeScope {
+        numbers: default_numbers()?,
+        ..TypeScope::default()
+    };
+    let mut types = vec![];
+    let mut fields = vec![];
+    for field in &named.named {
+        let ident = field.ident.as_ref().unwrap().to_string();
+        let ty = use_type(&mut types, &scope, &field.ty)?;
+        fields.push((ident, ty));
+    }
+
+    let record = ast::Record {
+        name: name.clone(),
+        fields: fields
+            .iter()
+            .map(|(field, ty)| ast::Binder {
+                name: ast::Record::projection(&name, field),
+                ty: ty.clone(),
+                implicit: false,
+            })
+            .collect(),
+    };
+
+    // Each setter rebuilds the record, with the new value in place of the old one.
+    let mut statements = vec![ast::Statement::Record(record)];
+    for (field, ty) in &fields {
+        let body = ast::Expr::Record {
+            name: name.clone(),
+            fields: fields
+                .iter()
+                .map(|(other, _)| {
+                    let projection = ast::Record::projection(&name, other);
+                    let value = if other == field {
+                        ast::Expr::Var("v'".into())
+                    } else {
+                        ast::Expr::Apply {
+                            func: projection.clone(),
+                            args: vec![ast::Expr::Var("r'".into())],
+                        }
+                    };
+                    (projection, value)
+                })
+                .collect(),
+        };
+        statements.push(ast::Statement::Definition(ast::Definition {
+            name: ast::Record::setter(&name, field),
+            args: vec![
+                ast::Binder {
+                    name: "r'".into(),
+                    ty: ast::Ty::Named(name.clone()),
+                    implicit: false,
+                },
+                ast::Binder {
+                    name: "v'".into(),
+                    ty: ty.clone(),
+                    implicit: false,
+                },
+            ],
+            ret: ast::Ty::Named(name.clone()),
+            body,
+            program: false,
+        }));
+    }
+
+    // A recursive struct is defined by its own `Record`, rather than depending on itself.
+    types.retain(|path| !path.is_ident(&source.ident));
+    check_type_names(&types)?;
+    Ok(Translation { statements, types })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_snapshot;
+
+    fn parse(input: &str) -> syn::ItemStruct {
+        syn::parse_str(input).expect("Failed to parse source code")
+    }
+
+    fn test_as_vernacular(input: &str) -> ast::Vernacular {
+        let translation = record_as_ast(&parse(input)).expect("Failed to convert struct");
+        ast::Vernacular {
+            statements: translation.statements,
+        }
+    }
+
+    fn test_as_err(input: &str) -> String {
+        record_as_ast(&parse(input))
+            .err()
+            .expect("Expected struct conversion to fail")
+            .to_string()
+    }
+
+    #[test]
+    fn test_record() {
+        assert_snapshot!(
+            test_as_vernacular(r#"
+                struct Config {
+                    width: u64,
+                    verbose: bool,
+                }
+            "#),
+            @r###"
+        Record Config := {
+        	Config_width: nat;
+        	Config_verbose: bool
+        }.
+
+
+        Definition Config_set_width (r': Config) (v': nat) : Config :=
+        	{| Config_width := v'; Config_verbose := (Config_verbose r')
+        	 |}
+        .
+
+
+        Definition Config_set_verbose (r': Config) (v': bool) : Config :=
+        	{| Config_width := (Config_width r')
+        	; Config_verbose := v' |}
+        .
+        "###
+        );
+    }
+
+    #[test]
+    fn test_record_types() {
+        let translation = record_as_ast(&parse(
+            r#"
+                struct Window {
+                    config: Config,
+                    offset: i64,
+                    inner: layout::Rect,
+                }
+            "#,
+        ))
+        .unwrap();
+        let types = translation
+            .types
+            .iter()
+            .map(|path| quote::quote!(#path).to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(types, ["Config", "layout :: Rect"]);
+    }
+
+    #[test]
+    fn test_recursive_record() {
+        let translation = record_as_ast(&parse(
+            r#"
+                struct Tree {
+                    children: Vec<Tree>,
+                }
+            "#,
+        ))
+        .unwrap();
+        assert!(translation.types.is_empty());
+    }
+
+    #[test]
+    fn test_tuple_struct() {
+        assert_snapshot!(
+            test_as_err("struct Meters(u64);"),
+            @"expected a struct with named fields"
+        );
+    }
+
+    #[test]
+    fn test_generic_struct() {
+        assert_snapshot!(
+            test_as_err("struct Wrapper<T> { inner: T }"),
+            @"can't generate Coq `Record` for a generic struct"
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use roq_core::ast;
+use roq_core::typing::Numbers;
+use syn::spanned::Spanned;
+use syn::visit::Visit;
+use syn::visit_mut::VisitMut;
+
+use crate::context::Recursion;
+use crate::ty::{type_as_ast, TypeScope};
+
+/// Find the argument a recursive function is structurally decreasing on.
+///
+/// Returns `None` if the function never calls itself. Otherwise, every recursive call must pass
+/// `n - k` (for some literal `k >= 1`) in the position of the same `nat` argument `n`, so
+/// unsigned integers must be modelled with `nat`s in `scope`.
+pub fn find_decreasing_arg(
+    source: &syn::ItemFn,
+    scope: &TypeScope,
+) -> syn::Result<Option<Recursion>> {
+    let mut calls = SelfCalls {
+        func: &source.sig.ident,
+        calls: vec![],
+    };
+    calls.visit_block(&source.block);
+
+    let Some(first_call) = calls.calls.first() else {
+        return Ok(None);
+    };
+
+    for (index, input) in source.sig.inputs.iter().enumerate() {
+        let syn::FnArg::Typed(pat) = input else {
+            continue;
+        };
+        let syn::Pat::Ident(ident) = &*pat.pat else {
+            continue;
+        };
+        if !matches!(type_as_ast(&pat.ty, scope), Ok(ast::Ty::Nat)) {
+            continue;
+        }
+
+        let arg = ident.ident.to_string();
+        let decreasing = calls.calls.iter().all(|call| {
+            call.args
+                .iter()
+                .nth(index)
+                .is_some_and(|expr| predecessor_of(expr, &arg).is_some())
+        });
+        if decreasing {
+            return Ok(Some(Recursion {
+                index,
+                arg,
+                depth: 0,
+            }));
+        }
+    }
+
+    let message = "can't find a structurally decreasing argument for this recursive call; \
+                   expected `n - 1` (or `n - k`) to be passed for some `u64` argument `n`";
+    let message = match scope.numbers {
+        Numbers::Nat => message.to_string(),
+        _ => format!(
+            "{message}, but structural recursion needs `numbers = \"nat\"`; give the function \
+             a measure with `#[roq::decreasing(...)]` instead"
+        ),
+    };
+    Err(syn::Error::new(first_call.span(), message))
+}
+
+/// Check whether a function calls itself.
+pub fn is_recursive(source: &syn::ItemFn) -> bool {
+    let mut calls = SelfCalls {
+        func: &source.sig.ident,
+        calls: vec![],
+    };
+    calls.visit_block(&source.block);
+    !calls.calls.is_empty()
+}
+
+/// The measure given by a `#[decreasing(expr)]` attribute (however it's qualified, e.g.
+/// `#[roq::decreasing(expr)]`), if there is one.
+pub fn measure(attrs: &[syn::Attribute]) -> syn::Result<Option<syn::Expr>> {
+    attrs
+        .iter()
+        .find(|attr| is_measure(attr))
+        .map(|attr| attr.parse_args())
+        .transpose()
+}
+
+fn is_measure(attr: &syn::Attribute) -> bool {
+    attr.path()
+        .segments
+        .last()
+        .is_some_and(|segment| segment.ident == "decreasing")
+}
+
+/// Remove the `#[decreasing]` attributes from a function and the loops within it, which only
+/// mean something to us (and attributes on expressions aren't stable anyway).
+pub fn strip_measures(source: &mut syn::ItemFn) {
+    source.attrs.retain(|attr| !is_measure(attr));
+    StripMeasures.visit_block_mut(&mut source.block);
+}
+
+/// Remove the measures from the methods of an `impl` block, and the `while` loops in them.
+pub fn strip_impl_measures(source: &mut syn::ItemImpl) {
+    for item in &mut source.items {
+        if let syn::ImplItem::Fn(method) = item {
+            method.attrs.retain(|attr| !is_measure(attr));
+            StripMeasures.visit_block_mut(&mut method.block);
+        }
+    }
+}
+
+struct StripMeasures;
+
+impl VisitMut for StripMeasures {
+    fn visit_expr_while_mut(&mut self, node: &mut syn::ExprWhile) {
+        node.attrs.retain(|attr| !is_measure(attr));
+        syn::visit_mut::visit_expr_while_mut(self, node);
+    }
+}
+
+/// Collects every call to the function named `func`.
+struct SelfCalls<'a> {
+    func: &'a syn::Ident,
+    calls: Vec<syn::ExprCall>,
+}
+
+impl<'ast> Visit<'ast> for SelfCalls<'_> {
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        if is_path_to(&call.func, &self.func.to_string()) {
+            self.calls.push(call.clone());
+        }
+        syn::visit::visit_expr_call(self, call);
+    }
+}
+
+/// Check whether `expr` is a path consisting of the single identifier `name`.
+pub fn is_path_to(expr: &syn::Expr, name: &str) -> bool {
+    match expr {
+        syn::Expr::Paren(syn::ExprParen { expr, .. }) => is_path_to(expr, name),
+        syn::Expr::Path(path) => path.qself.is_none() && path.path.is_ident(name),
+        _ => false,
+    }
+}
+
+/// Match an integer literal, returning its value.
+fn int_literal(expr: &syn::Expr) -> Option<usize> {
+    match expr {
+        syn::Expr::Paren(syn::ExprParen { expr, .. }) => int_literal(expr),
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Int(int),
+            ..
+        }) => int.base10_parse().ok(),
+        _ => None,
+    }
+}
+
+/// If `expr` is `arg - k` for some literal `k >= 1`, return `k`.
+pub fn predecessor_of(expr: &syn::Expr, arg: &str) -> Option<usize> {
+    match expr {
+        syn::Expr::Paren(syn::ExprParen { expr, .. }) => predecessor_of(expr, arg),
+        syn::Expr::Binary(syn::ExprBinary {
+            left,
+            op: syn::BinOp::Sub(_),
+            right,
+            ..
+        }) if is_path_to(left, arg) => int_literal(right).filter(|k| *k >= 1),
+        _ => None,
+    }
+}
+
+/// If `cond` compares `arg` against a literal, return the `k` such that the condition is
+/// equivalent to `arg >= k` (if the bool is `true`) or `arg < k` (if it is `false`).
+pub fn guard_threshold(cond: &syn::Expr, arg: &str) -> Option<(usize, bool)> {
+    let syn::Expr::Binary(syn::ExprBinary {
+        left, op, right, ..
+    }) = cond
+    else {
+        return match cond {
+            syn::Expr::Paren(syn::ExprParen { expr, .. }) => guard_threshold(expr, arg),
+            _ => None,
+        };
+    };
+
+    // Normalize to `arg <op> k`, flipping the operator if the literal is on the left.
+    let (k, flipped) = if is_path_to(left, arg) {
+        (int_literal(right)?, false)
+    } else if is_path_to(right, arg) {
+        (int_literal(left)?, true)
+    } else {
+        return None;
+    };
+
+    let guard = match (op, flipped) {
+        (syn::BinOp::Eq(_), _) if k == 0 => (1, false),
+        (syn::BinOp::Ne(_), _) if k == 0 => (1, true),
+        (syn::BinOp::Lt(_), false) | (syn::BinOp::Gt(_), true) => (k, false),
+        (syn::BinOp::Le(_), false) | (syn::BinOp::Ge(_), true) => (k + 1, false),
+        (syn::BinOp::Gt(_), false) | (syn::BinOp::Lt(_), true) => (k + 1, true),
+        (syn::BinOp::Ge(_), false) | (syn::BinOp::Le(_), true) => (k, true),
+        _ => return None,
+    };
+
+    // A guard of `arg >= 0` tells us nothing.
+    (guard.0 >= 1).then_some(guard)
+}
+
+/// Build the pattern matching `arg` when it is at least `depth`, binding each predecessor.
+///
+/// For a depth of 2, this is `S (S n'' as n')`.
+pub fn successor_pattern(recursion: &Recursion, depth: usize) -> ast::Pattern {
+    let mut pattern = ast::Pattern::Ident(recursion.predecessor(depth));
+    for n in (1..depth).rev() {
+        pattern = ast::Pattern::As {
+            pattern: Box::new(ast::Pattern::Constructor {
+                name: "S".into(),
+                args: vec![pattern],
+            }),
+            ident: recursion.predecessor(n),
+        };
+    }
+    ast::Pattern::Constructor {
+        name: "S".into(),
+        args: vec![pattern],
+    }
+}
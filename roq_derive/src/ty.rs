@@ -1,7 +1,74 @@
 use roq_core::ast;
+use roq_core::classes;
+use roq_core::typing::Numbers;
 use syn::spanned::Spanned;
 
-pub fn type_as_ast(source: &syn::Type) -> syn::Result<ast::Ty> {
+use crate::context::callee_name;
+
+/// What the types in an item are converted in the context of.
+#[derive(Debug, Clone, Default)]
+pub struct TypeScope {
+    /// The Coq type unsigned integers are modelled with.
+    pub numbers: Numbers,
+
+    /// The names of the item's type parameters.
+    pub params: Vec<ast::Ident>,
+
+    /// The type `Self` stands for, in a trait or an `impl` block.
+    pub self_ty: Option<ast::Ty>,
+}
+
+pub fn type_as_ast(source: &syn::Type, scope: &TypeScope) -> syn::Result<ast::Ty> {
+    use_type(&mut vec![], scope, source)
+}
+
+/// The model of unsigned integers used unless one is given, which is `nat`, or else the value of
+/// the `ROQ_NUMBERS` environment variable when the crate is compiled (e.g. set in the `[env]`
+/// section of `.cargo/config.toml`). The code each macro emits reads it too, so that Cargo, which
+/// doesn't know a proc macro read it, rebuilds the crate when it changes.
+pub fn default_numbers() -> syn::Result<Numbers> {
+    match std::env::var("ROQ_NUMBERS") {
+        Ok(numbers) => numbers.parse().map_err(|err: String| {
+            syn::Error::new(
+                proc_macro2::Span::call_site(),
+                format!("in `ROQ_NUMBERS`: {err}"),
+            )
+        }),
+        Err(_) => Ok(Numbers::default()),
+    }
+}
+
+/// Check that no two of the user-defined types at `types` have the same name. The translation
+/// refers to each by its name, until they're qualified by the modules defining them when the
+/// vernacular is built, so it can't tell them apart.
+pub fn check_type_names(types: &[syn::Path]) -> syn::Result<()> {
+    let name = |path: &syn::Path| path.segments.last().unwrap().ident.clone();
+    for (i, path) in types.iter().enumerate() {
+        if let Some(other) = types[..i]
+            .iter()
+            .find(|other| *other != path && name(other) == name(path))
+        {
+            return Err(syn::Error::new(
+                path.span(),
+                format!(
+                    "can't use both `{}` and `{}`, which have the same name; if they're the same \
+                     type, refer to it by the same path",
+                    callee_name(other),
+                    callee_name(path)
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Convert a type, recording the path to any user-defined type so that its definition can be
+/// included in the vernacular.
+pub fn use_type(
+    types: &mut Vec<syn::Path>,
+    scope: &TypeScope,
+    source: &syn::Type,
+) -> syn::Result<ast::Ty> {
     match source {
         syn::Type::Path(ty) => {
             let segments_str = ty
@@ -13,18 +80,261 @@ pub fn type_as_ast(source: &syn::Type) -> syn::Result<ast::Ty> {
             let segments_refs = segments_str.iter().map(|s| s.as_str()).collect::<Vec<_>>();
 
             match segments_refs[..] {
-                ["u64"] | ["std", "u64"] => Ok(ast::Ty::Nat),
-                ["bool"] | ["std", "bool"] => Ok(ast::Ty::Nat),
+                [param] if ty.qself.is_none() && scope.params.iter().any(|p| p == param) => {
+                    Ok(ast::Ty::Var(param.into()))
+                }
+                ["Self"] if ty.qself.is_none() && scope.self_ty.is_some() => {
+                    Ok(scope.self_ty.clone().unwrap())
+                }
+                ["u64"] | ["std", "u64"] | ["usize"] | ["std", "usize"] => Ok(scope.numbers.ty()),
+                ["bool"] | ["std", "bool"] => Ok(ast::Ty::Bool),
+                [int] | ["std", int] if is_signed_int(int) => Ok(ast::Ty::Z),
+
+                // `Option` and `Result` become Coq's `option` and `sum`.
+                ["Option"] | ["std", "option", "Option"] => {
+                    let [ty] = type_args(types, scope, &ty.path)?;
+                    Ok(ast::Ty::option(ty))
+                }
+                ["Vec"] | ["std", "vec", "Vec"] => {
+                    let [ty] = type_args(types, scope, &ty.path)?;
+                    Ok(ast::Ty::list(ty))
+                }
+                ["Result"] | ["std", "result", "Result"] => {
+                    let [ok, err] = type_args(types, scope, &ty.path)?;
+                    Ok(ast::Ty::sum(ok, err))
+                }
+
+                // Other primitive and standard library types have no translation, and mustn't be
+                // mistaken for user-defined types.
+                [name] if is_primitive(name) || name == "String" => Err(syn::Error::new(
+                    ty.span(),
+                    format!("unsupported type `{name}`"),
+                )),
+                ["std" | "core" | "alloc", ..] => {
+                    Err(syn::Error::new(ty.span(), "unsupported type"))
+                }
+
+                // Anything else must be a user-defined type, such as a `#[record]` struct.
+                _ if ty.qself.is_none()
+                    && ty
+                        .path
+                        .segments
+                        .iter()
+                        .all(|segment| segment.arguments.is_none()) =>
+                {
+                    if !types.contains(&ty.path) {
+                        types.push(ty.path.clone());
+                    }
+                    Ok(ast::Ty::Named(segments_str.last().unwrap().clone()))
+                }
                 _ => Err(syn::Error::new(ty.span(), "unsupported type")),
             }
         }
+
+        // `()` is `unit`, and longer tuples are nested pairs.
+        syn::Type::Tuple(tuple) => {
+            let mut elems = vec![];
+            for elem in &tuple.elems {
+                elems.push(use_type(types, scope, elem)?);
+            }
+            Ok(elems
+                .into_iter()
+                .reduce(ast::Ty::prod)
+                .unwrap_or_else(ast::Ty::unit))
+        }
+        syn::Type::Paren(syn::TypeParen { elem, .. }) => use_type(types, scope, elem),
+
+        // Slices and arrays are lists, and shared references are erased.
+        syn::Type::Slice(syn::TypeSlice { elem, .. })
+        | syn::Type::Array(syn::TypeArray { elem, .. }) => {
+            Ok(ast::Ty::list(use_type(types, scope, elem)?))
+        }
+        syn::Type::Reference(syn::TypeReference {
+            mutability: None,
+            elem,
+            ..
+        }) => use_type(types, scope, elem),
+        syn::Type::Reference(reference) => Err(syn::Error::new(
+            reference.span(),
+            "mutable references aren't supported",
+        )),
+
         _ => Err(syn::Error::new(
             source.span(),
-            "expected a path type (e.g. `std::u64` or `bool`)",
+            "expected a path type (e.g. `std::u64` or `bool`) or a tuple",
         )),
     }
 }
 
+/// A trait bound on a type parameter, translated to a class, as `(param, class)`.
+pub type Bound = (ast::Ident, ast::Ident);
+
+/// The names of an item's type parameters, and the classes their trait bounds are translated to.
+/// Lifetimes are erased, along with bounds which only involve lifetimes, and `Clone` and `Copy`,
+/// since any value can be used more than once in Coq.
+pub fn type_params(generics: &syn::Generics) -> syn::Result<(Vec<ast::Ident>, Vec<Bound>)> {
+    let mut params = vec![];
+    let mut bounds = vec![];
+    for param in &generics.params {
+        match param {
+            syn::GenericParam::Type(param) => {
+                let name = param.ident.to_string();
+                for bound in &param.bounds {
+                    bound_as_class(&mut bounds, &name, bound)?;
+                }
+                params.push(name);
+            }
+            syn::GenericParam::Lifetime(_) => {}
+            syn::GenericParam::Const(param) => {
+                return Err(syn::Error::new(
+                    param.span(),
+                    "const generics aren't supported",
+                ))
+            }
+        }
+    }
+    for predicate in generics
+        .where_clause
+        .iter()
+        .flat_map(|clause| &clause.predicates)
+    {
+        match predicate {
+            syn::WherePredicate::Type(predicate) => {
+                let param = match &predicate.bounded_ty {
+                    syn::Type::Path(syn::TypePath { qself: None, path }) => path
+                        .get_ident()
+                        .map(|ident| ident.to_string())
+                        .filter(|name| params.contains(name)),
+                    _ => None,
+                };
+                let is_lifetime =
+                    |bound: &syn::TypeParamBound| matches!(bound, syn::TypeParamBound::Lifetime(_));
+                match param {
+                    Some(param) => {
+                        for bound in &predicate.bounds {
+                            bound_as_class(&mut bounds, &param, bound)?;
+                        }
+                    }
+                    None if predicate.bounds.iter().all(is_lifetime) => {}
+                    None => {
+                        return Err(syn::Error::new(
+                            predicate.bounded_ty.span(),
+                            "trait bounds are only supported on type parameters",
+                        ))
+                    }
+                }
+            }
+            syn::WherePredicate::Lifetime(_) => {}
+            _ => {
+                return Err(syn::Error::new(
+                    predicate.span(),
+                    "unsupported where clause",
+                ))
+            }
+        }
+    }
+
+    // An instance of a class brings along one of the class it extends, so a bound on both would
+    // be ambiguous.
+    let extended = bounds
+        .iter()
+        .filter_map(|(param, class)| Some((param.clone(), classes::superclass(class)?.into())))
+        .collect::<Vec<_>>();
+    bounds.retain(|bound| !extended.contains(bound));
+    Ok((params, bounds))
+}
+
+/// Add the class a trait bound on `param` is translated to, unless it's erased or there's already
+/// a bound on it.
+fn bound_as_class(
+    bounds: &mut Vec<Bound>,
+    param: &str,
+    bound: &syn::TypeParamBound,
+) -> syn::Result<()> {
+    let syn::TypeParamBound::Trait(bound) = bound else {
+        return Ok(());
+    };
+    let unsupported = || {
+        syn::Error::new(
+            bound.span(),
+            format!(
+                "unsupported trait bound, expected `PartialEq`, `Eq`, `PartialOrd`, `Ord`, \
+                 `Add<Output = {param}>`, `Default`, `Clone` or `Copy`"
+            ),
+        )
+    };
+    if let syn::TraitBoundModifier::Maybe(_) = bound.modifier {
+        return Ok(());
+    }
+    let segment = bound.path.segments.last().unwrap();
+    let name = segment.ident.to_string();
+
+    // The result of adding two values must be of the same type, to add it to another.
+    let arguments_supported = match &segment.arguments {
+        syn::PathArguments::None => name != "Add",
+        syn::PathArguments::AngleBracketed(args) if name == "Add" => {
+            matches!(
+                args.args.iter().collect::<Vec<_>>()[..],
+                [syn::GenericArgument::AssocType(syn::AssocType { ident, ty: syn::Type::Path(ty), .. })]
+                    if ident == "Output" && ty.path.is_ident(param)
+            )
+        }
+        _ => false,
+    };
+    if !arguments_supported {
+        return Err(unsupported());
+    }
+    let class = match name.as_str() {
+        "Clone" | "Copy" => return Ok(()),
+        name => classes::class_of_trait(name).ok_or_else(unsupported)?,
+    };
+    let bound = (param.to_string(), class.to_string());
+    if !bounds.contains(&bound) {
+        bounds.push(bound);
+    }
+    Ok(())
+}
+
+/// Convert the `N` type arguments of a generic type, like the `T` of `Option<T>`.
+fn type_args<const N: usize>(
+    types: &mut Vec<syn::Path>,
+    scope: &TypeScope,
+    path: &syn::Path,
+) -> syn::Result<[ast::Ty; N]> {
+    let expected = match N {
+        1 => "expected 1 type argument".to_string(),
+        _ => format!("expected {N} type arguments"),
+    };
+    let segment = path.segments.last().unwrap();
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return Err(syn::Error::new(segment.span(), &expected));
+    };
+    let mut tys = vec![];
+    for arg in &args.args {
+        let ty = match arg {
+            syn::GenericArgument::Type(ty) => ty,
+            syn::GenericArgument::Lifetime(_) => continue,
+            _ => return Err(syn::Error::new(arg.span(), "expected a type argument")),
+        };
+        tys.push(use_type(types, scope, ty)?);
+    }
+    tys.try_into()
+        .map_err(|_| syn::Error::new(args.span(), &expected))
+}
+
+/// Check whether `name` is one of Rust's signed integer types, which are all mapped to `Z`.
+pub fn is_signed_int(name: &str) -> bool {
+    matches!(name, "i8" | "i16" | "i32" | "i64" | "i128" | "isize")
+}
+
+/// Check whether `name` is one of Rust's primitive types which has no translation, like `u32`.
+fn is_primitive(name: &str) -> bool {
+    matches!(
+        name,
+        "u8" | "u16" | "u32" | "u128" | "f32" | "f64" | "char" | "str"
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -35,7 +345,7 @@ mod tests {
     }
 
     fn test_as_ty(input: &str) -> ast::Ty {
-        type_as_ast(&parse(input)).expect("Failed to convert to type")
+        type_as_ast(&parse(input), &TypeScope::default()).expect("Failed to convert to type")
     }
 
     #[test]
@@ -54,11 +364,89 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_usize() {
+        assert_snapshot!(test_as_ty("usize"), @"nat");
+    }
+
+    #[test]
+    fn test_numbers() {
+        let ty = |input, numbers| {
+            let scope = TypeScope {
+                numbers,
+                ..TypeScope::default()
+            };
+            type_as_ast(&parse(input), &scope).unwrap()
+        };
+        assert_snapshot!(ty("(u64, usize)", Numbers::N), @"prod N N");
+        assert_snapshot!(ty("Vec<u64>", Numbers::Z), @"list Z");
+        assert_snapshot!(ty("Option<u64>", Numbers::Word), @"option N");
+        assert_snapshot!(ty("i64", Numbers::N), @"Z");
+    }
+
+    #[test]
+    fn test_params() {
+        let scope = TypeScope {
+            params: vec!["T".into(), "E".into()],
+            ..TypeScope::default()
+        };
+        let ty = |input| type_as_ast(&parse(input), &scope).unwrap();
+        assert_snapshot!(ty("T"), @"T");
+        assert_snapshot!(ty("Result<Vec<T>, E>"), @"sum (list T) E");
+        assert_snapshot!(ty("&'a [(T, u64)]"), @"list (prod T nat)");
+        assert_snapshot!(ty("Option<&'a T>"), @"option T");
+    }
+
+    #[test]
+    fn test_type_params() {
+        let params = |input: &str| {
+            let item: syn::ItemFn = syn::parse_str(input).unwrap();
+            type_params(&item.sig.generics)
+        };
+        assert_eq!(params("fn f() {}").unwrap(), (vec![], vec![]));
+        assert_eq!(
+            params("fn f<'a, T: 'a, U>(x: &'a T) where U: 'a {}")
+                .unwrap()
+                .0,
+            ["T", "U"]
+        );
+        assert_snapshot!(params("fn f<const N: usize>() {}").unwrap_err(), @"const generics aren't supported");
+    }
+
+    #[test]
+    fn test_bounds() {
+        let bounds = |input: &str| {
+            let item: syn::ItemFn = syn::parse_str(input).unwrap();
+            let (_, bounds) = type_params(&item.sig.generics)?;
+            Ok::<_, syn::Error>(
+                bounds
+                    .into_iter()
+                    .map(|(param, class)| format!("{param}: {class}"))
+                    .collect::<Vec<_>>(),
+            )
+        };
+        assert_eq!(
+            bounds("fn f<T: Eq + Copy, U: Default>() {}").unwrap(),
+            ["T: PartialEq", "U: Default"]
+        );
+        assert_eq!(
+            bounds("fn f<T>() where T: PartialEq + Ord + Add<Output = T> {}").unwrap(),
+            ["T: Ord", "T: Add"]
+        );
+        assert_eq!(
+            bounds("fn f<T: ?Sized + PartialOrd>() {}").unwrap(),
+            ["T: Ord"]
+        );
+        assert_snapshot!(bounds("fn f<T: Hash>() {}").unwrap_err(), @"unsupported trait bound, expected `PartialEq`, `Eq`, `PartialOrd`, `Ord`, `Add<Output = T>`, `Default`, `Clone` or `Copy`");
+        assert_snapshot!(bounds("fn f<T: Add>() {}").unwrap_err(), @"unsupported trait bound, expected `PartialEq`, `Eq`, `PartialOrd`, `Ord`, `Add<Output = T>`, `Default`, `Clone` or `Copy`");
+        assert_snapshot!(bounds("fn f<T>() where Vec<T>: Clone {}").unwrap_err(), @"trait bounds are only supported on type parameters");
+    }
+
     #[test]
     fn test_bool() {
         assert_snapshot!(
             test_as_ty("bool"),
-            @"nat"
+            @"bool"
         );
     }
 
@@ -66,7 +454,94 @@ mod tests {
     fn test_std_bool() {
         assert_snapshot!(
             test_as_ty("std::bool"),
-            @"nat"
+            @"bool"
         );
     }
+
+    #[test]
+    fn test_signed() {
+        assert_snapshot!(test_as_ty("i8"), @"Z");
+        assert_snapshot!(test_as_ty("i32"), @"Z");
+        assert_snapshot!(test_as_ty("i64"), @"Z");
+        assert_snapshot!(test_as_ty("std::i64"), @"Z");
+        assert_snapshot!(test_as_ty("isize"), @"Z");
+    }
+
+    #[test]
+    fn test_named() {
+        assert_snapshot!(test_as_ty("Config"), @"Config");
+        assert_snapshot!(test_as_ty("config::Config"), @"Config");
+    }
+
+    #[test]
+    fn test_option_result() {
+        assert_snapshot!(test_as_ty("Option<u64>"), @"option nat");
+        assert_snapshot!(test_as_ty("Result<i64, bool>"), @"sum Z bool");
+        assert_snapshot!(test_as_ty("std::option::Option<Option<Config>>"), @"option (option Config)");
+        assert_snapshot!(test_as_ty("Result<Option<u64>, Error>"), @"sum (option nat) Error");
+    }
+
+    #[test]
+    fn test_option_types() {
+        let mut types = vec![];
+        use_type(
+            &mut types,
+            &TypeScope::default(),
+            &parse("Result<Option<Config>, error::Error>"),
+        )
+        .unwrap();
+        let types = types
+            .iter()
+            .map(|path| quote::quote!(#path).to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(types, ["Config", "error :: Error"]);
+    }
+
+    #[test]
+    fn test_option_arguments() {
+        let err = type_as_ast(&parse("Option"), &TypeScope::default()).unwrap_err();
+        assert_snapshot!(err, @"expected 1 type argument");
+        let err = type_as_ast(&parse("Result<u64>"), &TypeScope::default()).unwrap_err();
+        assert_snapshot!(err, @"expected 2 type arguments");
+    }
+
+    #[test]
+    fn test_tuple() {
+        assert_snapshot!(test_as_ty("()"), @"unit");
+        assert_snapshot!(test_as_ty("(u64, bool)"), @"prod nat bool");
+        assert_snapshot!(test_as_ty("(u64, i64, Config)"), @"prod (prod nat Z) Config");
+        assert_snapshot!(test_as_ty("Option<(u64, u64)>"), @"option (prod nat nat)");
+    }
+
+    #[test]
+    fn test_list() {
+        assert_snapshot!(test_as_ty("Vec<u64>"), @"list nat");
+        assert_snapshot!(test_as_ty("&[i64]"), @"list Z");
+        assert_snapshot!(test_as_ty("[bool; 4]"), @"list bool");
+        assert_snapshot!(test_as_ty("&Vec<(u64, Config)>"), @"list (prod nat Config)");
+    }
+
+    #[test]
+    fn test_mut_reference_unsupported() {
+        let err = type_as_ast(&parse("&mut u64"), &TypeScope::default()).unwrap_err();
+        assert_snapshot!(err, @"mutable references aren't supported");
+    }
+
+    #[test]
+    fn test_generic_unsupported() {
+        let err = type_as_ast(&parse("HashMap<u64, u64>"), &TypeScope::default()).unwrap_err();
+        assert_snapshot!(err, @"unsupported type");
+    }
+
+    #[test]
+    fn test_primitive_unsupported() {
+        let err = |input| type_as_ast(&parse(input), &TypeScope::default()).unwrap_err();
+        assert_snapshot!(err("u32"), @"unsupported type `u32`");
+        assert_snapshot!(err("char"), @"unsupported type `char`");
+        assert_snapshot!(err("f64"), @"unsupported type `f64`");
+        assert_snapshot!(err("&str"), @"unsupported type `str`");
+        assert_snapshot!(err("String"), @"unsupported type `String`");
+        assert_snapshot!(err("std::string::String"), @"unsupported type");
+        assert_snapshot!(err("Option<u8>"), @"unsupported type `u8`");
+    }
 }
@@ -1,4 +1,5 @@
 use eyre::{Result, WrapErr};
+use roq_core::ast::{Statement, Vernacular};
 use std::io::Write;
 
 pub mod coqtop;
@@ -10,8 +11,9 @@ pub use roq_derive::*;
 #[macro_export]
 macro_rules! try_prove {
     ($($tag:ident $t:tt),* $(,)?) => {
-        {
+        (|| -> $crate::_Result<String> {
             let mut batch = String::new();
+            let mut emitted = $crate::_Emitted::default();
             $(
                 batch.push_str(match stringify!($tag) {
                     "inline" => "(** ** inline *)\n".to_string(),
@@ -23,9 +25,21 @@ macro_rules! try_prove {
                         "(** ** function: {} *)\n",
                         stringify!($t)
                     ),
+                    "loop" => format!(
+                        "(** ** loop: {} *)\n",
+                        stringify!($t)
+                    ),
+                    "class" => format!(
+                        "(** ** class: {} *)\n",
+                        stringify!($t)
+                    ),
+                    "instance" => format!(
+                        "(** ** instance: {} *)\n",
+                        stringify!($t)
+                    ),
                     _ => "(** ** chunk *)\n".to_string(),
                 }.as_str());
-                batch.push_str($crate::_part_to_str!($tag $t));
+                batch.push_str(&$crate::_part_to_str!(emitted; $tag $t));
                 batch.push_str("\n\n\n");
             )*
 
@@ -34,7 +48,7 @@ macro_rules! try_prove {
             eprintln!("```");
 
             $crate::_try_prove(&batch)
-        }
+        })()
     }
 }
 
@@ -48,15 +62,75 @@ macro_rules! prove {
 #[macro_export]
 #[doc(hidden)]
 macro_rules! _part_to_str {
-    (inline $s:literal) => {
+    ($emitted:ident; inline $s:literal) => {
         $s
     };
-    (file $file:literal) => {
+    ($emitted:ident; file $file:literal) => {
         include_str!($file)
     };
-    (function $f:ident) => {
-        $f::roq::vernacular().to_string().as_str()
+    ($emitted:ident; function $f:ident) => {
+        $emitted.include($f::roq::vernacular()?)
     };
+    ($emitted:ident; loop $f:ident) => {
+        $emitted.include_loop(stringify!($f), $f::roq::vernacular()?)?
+    };
+    ($emitted:ident; class $c:ident) => {
+        $emitted.include($c())
+    };
+    ($emitted:ident; instance $i:ident) => {
+        $emitted.include($i()?)
+    };
+}
+
+#[doc(hidden)]
+pub use eyre::Result as _Result;
+
+/// Tracks the statements already emitted by a `prove!` batch, so that definitions shared between
+/// several `function` parts (e.g. a common callee) are only emitted once.
+#[doc(hidden)]
+#[derive(Default)]
+pub struct _Emitted(Vernacular);
+
+impl _Emitted {
+    /// Render the statements of `vernacular` which haven't been emitted yet.
+    pub fn include(&mut self, vernacular: Vernacular) -> String {
+        let fresh = Vernacular {
+            statements: vernacular
+                .statements
+                .into_iter()
+                .filter(|stmt| !self.0.statements.contains(stmt))
+                .collect(),
+        };
+        self.0.extend(fresh.statements.iter().cloned());
+        fresh.to_string()
+    }
+
+    /// Render the statements of `vernacular`, the function `name`'s, which haven't been emitted
+    /// yet, up to and including the next one left with obligations before the function itself,
+    /// such as the `Program Fixpoint` a loop is translated to. Its obligations can then be proved
+    /// with `Next Obligation` in the following part, before the function is defined.
+    ///
+    /// Returns an error if there's no such statement left.
+    pub fn include_loop(&mut self, name: &str, vernacular: Vernacular) -> Result<String> {
+        let mut statements = vernacular.statements;
+        statements.pop();
+        statements.retain(|stmt| !self.0.statements.contains(stmt));
+        let Some(end) = statements.iter().position(has_obligations) else {
+            eyre::bail!("`{name}` has no loop left with obligations to prove");
+        };
+        statements.truncate(end + 1);
+        Ok(self.include(Vernacular { statements }))
+    }
+}
+
+/// Whether a statement is defined with `Program`, so it may leave obligations to prove.
+fn has_obligations(stmt: &Statement) -> bool {
+    match stmt {
+        Statement::ProgramFixpoint(_) => true,
+        Statement::Fixpoint(fixpoint) => fixpoint.program,
+        Statement::Definition(definition) => definition.program,
+        _ => false,
+    }
 }
 
 /// Stick the source into a temporary file, and run it through Coq.
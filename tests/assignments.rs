@@ -0,0 +1,99 @@
+use roq_derive::definition;
+
+#[definition]
+fn clamp(value: i64, lo: i64, hi: i64) -> i64 {
+    let mut result = value;
+    if value < lo {
+        result = lo;
+    } else if value > hi {
+        result = hi;
+    }
+    result
+}
+
+// Unchecked, since `i % k` panics when `k` is 0.
+#[definition(unchecked)]
+fn count_multiples(n: u64, k: u64) -> u64 {
+    let mut count = 0;
+    for i in 0..n {
+        if i % k == 0 {
+            count += 1;
+        }
+    }
+    count
+}
+
+#[definition]
+fn or_default(value: Option<u64>, default: u64) -> u64 {
+    let mut result = default;
+    if let Some(x) = value {
+        result = x;
+    }
+    result
+}
+
+#[test]
+fn test_clamp() {
+    assert_eq!(clamp(-5, 0, 10), 0);
+    assert_eq!(clamp(5, 0, 10), 5);
+    assert_eq!(clamp(15, 0, 10), 10);
+}
+
+#[test]
+fn test_count_multiples() {
+    assert_eq!(count_multiples(10, 3), 4);
+    assert_eq!(count_multiples(0, 3), 0);
+    assert_eq!(or_default(Some(3), 7), 3);
+    assert_eq!(or_default(None, 7), 7);
+}
+
+/// Prove that the clamped value is within the bounds, if they're in order.
+#[test]
+fn prove_clamp_bounds() {
+    roq::prove! {
+        function clamp,
+        inline r"
+            Require Import Lia.
+
+            Theorem clamp_bounds : forall value lo hi : Z,
+              (lo <= hi)%Z -> (lo <= clamp value lo hi <= hi)%Z.
+            Proof.
+              intros value lo hi H. unfold clamp.
+              destruct (Z.ltb value lo) eqn:Hlo; simpl.
+              - lia.
+              - destruct (Z.ltb hi value) eqn:Hhi; simpl.
+                + lia.
+                + apply Z.ltb_ge in Hlo, Hhi. lia.
+            Qed.
+        "
+    };
+}
+
+/// Prove the number of multiples of 3 below 10 by computation.
+#[test]
+fn prove_count_multiples_example() {
+    roq::prove! {
+        function count_multiples,
+        inline r"
+            Theorem count_multiples_example : count_multiples 10 3 = 4.
+            Proof.
+              reflexivity.
+            Qed.
+        "
+    };
+}
+
+/// Prove that a present value is always used instead of the default.
+#[test]
+fn prove_or_default_some() {
+    roq::prove! {
+        function or_default,
+        inline r"
+            Theorem or_default_some : forall x default : nat,
+              or_default (Some x) default = x.
+            Proof.
+              reflexivity.
+            Qed.
+        "
+    };
+}
@@ -0,0 +1,63 @@
+use roq_derive::definition;
+
+const READ: u64 = 0b001;
+
+#[definition]
+fn set_flag(flags: u64, flag: u64) -> u64 {
+    flags | flag
+}
+
+#[definition]
+fn clear_flag(flags: u64, flag: u64) -> u64 {
+    flags & (flags ^ flag)
+}
+
+#[definition]
+fn checksum_step(mut sum: u64, byte: u64) -> u64 {
+    sum ^= byte;
+    sum <<= 1;
+    sum &= 0xff;
+    sum
+}
+
+#[test]
+fn test_flags() {
+    assert_eq!(set_flag(0b100, READ), 0b101);
+    assert_eq!(clear_flag(0b101, READ), 0b100);
+    assert_eq!(clear_flag(0b100, READ), 0b100);
+}
+
+#[test]
+fn test_checksum_step() {
+    assert_eq!(checksum_step(0, 0x81), 0x02);
+    assert_eq!(checksum_step(0x0f, 0xf0), 0xfe);
+}
+
+/// Prove that clearing a flag undoes setting it, for a particular set of flags.
+#[test]
+fn prove_flags() {
+    roq::prove! {
+        function set_flag,
+        function clear_flag,
+        inline r"
+            Theorem clear_set_flag : clear_flag (set_flag 4 1) 1 = 4.
+            Proof.
+              reflexivity.
+            Qed.
+        "
+    };
+}
+
+/// Check a checksum step which overflows a byte.
+#[test]
+fn prove_checksum_step() {
+    roq::prove! {
+        function checksum_step,
+        inline r"
+            Theorem checksum_step_example : checksum_step 15 240 = 254.
+            Proof.
+              reflexivity.
+            Qed.
+        "
+    };
+}
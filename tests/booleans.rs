@@ -0,0 +1,106 @@
+use roq_derive::definition;
+
+#[definition]
+fn same(a: bool, b: bool) -> bool {
+    a == b
+}
+
+#[definition]
+fn in_range(lo: u64, x: u64, hi: u64) -> bool {
+    lo <= x && x < hi
+}
+
+#[definition]
+#[allow(clippy::nonminimal_bool)]
+fn strictly_descending(a: u64, b: u64, c: u64) -> bool {
+    a > b && b >= c && !(b == c) && a != c
+}
+
+#[definition]
+fn sign_flips(a: i64, b: i64) -> bool {
+    (-a > 0) != (-b > 0)
+}
+
+#[test]
+fn test_same() {
+    assert!(same(true, true));
+    assert!(!same(true, false));
+}
+
+#[test]
+fn test_in_range() {
+    assert!(in_range(1, 2, 3));
+    assert!(!in_range(1, 3, 3));
+}
+
+#[test]
+fn test_strictly_descending() {
+    assert!(strictly_descending(3, 2, 1));
+    assert!(!strictly_descending(3, 2, 2));
+}
+
+#[test]
+fn test_sign_flips() {
+    assert!(sign_flips(-1, 1));
+    assert!(!sign_flips(2, 1));
+}
+
+/// Prove that `same` is `true` exactly when its arguments are equal.
+#[test]
+fn prove_same() {
+    roq::prove! {
+        function same,
+        inline r"
+            Theorem same_refl : forall a : bool, same a a = true.
+            Proof.
+              intros [|]; reflexivity.
+            Qed.
+        "
+    };
+}
+
+/// Prove that ranges include their lower bound, but nothing is in an empty range.
+#[test]
+fn prove_in_range() {
+    roq::prove! {
+        function in_range,
+        inline r"
+            Theorem in_range_2 : in_range 1 2 3 = true.
+            Proof.
+              reflexivity.
+            Qed.
+
+            Theorem in_range_empty : forall n : nat, in_range n n n = false.
+            Proof.
+              intros. unfold in_range.
+              rewrite Nat.leb_refl, Nat.ltb_irrefl.
+              reflexivity.
+            Qed.
+        "
+    };
+}
+
+/// Check the comparisons which are written in terms of `ltb` and `leb` with flipped arguments.
+#[test]
+fn prove_flipped_comparisons() {
+    roq::prove! {
+        function strictly_descending,
+        function sign_flips,
+        inline r"
+            Theorem strictly_descending_3_2_1 : strictly_descending 3 2 1 = true.
+            Proof.
+              reflexivity.
+            Qed.
+
+            Theorem strictly_descending_3_2_2 : strictly_descending 3 2 2 = false.
+            Proof.
+              reflexivity.
+            Qed.
+
+            Theorem sign_flips_example : sign_flips (-1) 1 = true.
+            Proof.
+              reflexivity.
+            Qed.
+        "
+    };
+}
@@ -0,0 +1,150 @@
+use roq_derive::definition;
+
+// Unchecked, since `a + a` can overflow: the proofs below are about the unbounded sum.
+#[definition(unchecked)]
+fn double(a: u64) -> u64 {
+    a + a
+}
+
+#[definition]
+fn quadruple(a: u64) -> u64 {
+    double(double(a))
+}
+
+#[definition]
+fn octuple(a: u64) -> u64 {
+    double(quadruple(a))
+}
+
+mod util {
+    use roq_derive::definition;
+
+    #[definition]
+    pub fn max(a: u64, b: u64) -> u64 {
+        if a < b {
+            b
+        } else {
+            a
+        }
+    }
+}
+
+mod wide {
+    use roq_derive::definition;
+
+    #[definition(numbers = "N")]
+    pub fn max(a: u64, b: u64) -> u64 {
+        if a < b {
+            b
+        } else {
+            a
+        }
+    }
+}
+
+#[definition]
+fn max_double(a: u64, b: u64) -> u64 {
+    double(util::max(a, b))
+}
+
+#[definition]
+fn max_sum(a: u64, b: u64, c: u64) -> u64 {
+    util::max(a, b) + wide::max(b, c)
+}
+
+#[definition]
+fn is_even(n: u64) -> bool {
+    if n == 0 {
+        true
+    } else {
+        is_odd(n - 1)
+    }
+}
+
+#[definition]
+fn is_odd(n: u64) -> bool {
+    if n == 0 {
+        false
+    } else {
+        is_even(n - 1)
+    }
+}
+
+#[test]
+fn test_octuple() {
+    assert_eq!(octuple(3), 24);
+}
+
+#[test]
+fn test_max_double() {
+    assert_eq!(max_double(3, 5), 10);
+    assert_eq!(max_double(5, 3), 10);
+}
+
+#[test]
+fn test_max_sum() {
+    assert_eq!(max_sum(1, 2, 3), 5);
+    assert!(is_even(4) && is_odd(3));
+}
+
+#[test]
+fn test_callees_emitted_once() {
+    let vernacular = octuple::roq::vernacular().unwrap().to_string();
+    assert_eq!(vernacular.matches("Definition double").count(), 1);
+    assert!(vernacular.find("Definition double") < vernacular.find("Definition quadruple"));
+    assert!(vernacular.find("Definition quadruple") < vernacular.find("Definition octuple"));
+}
+
+#[test]
+fn test_callees_qualified() {
+    let vernacular = max_sum::roq::vernacular().unwrap().to_string();
+    assert!(vernacular.contains("Definition util_max"));
+    assert!(vernacular.contains("Definition wide_max"));
+    assert!(vernacular.contains("(util_max a b)"));
+    assert!(vernacular.contains("(N.to_nat (wide_max (N.of_nat b)"));
+}
+
+#[test]
+#[should_panic(expected = "`is_even` calls `is_odd`, which calls `is_even`")]
+fn test_mutual_recursion() {
+    is_even::roq::vernacular().unwrap();
+}
+
+/// Prove that `octuple(n) = 8 * n`, without listing `double` or `quadruple`.
+#[test]
+fn prove_octuple() {
+    roq::prove! {
+        function octuple,
+        inline r"
+            Require Import Lia.
+            Theorem octuple_mul : forall n : nat,
+              octuple n = 8 * n.
+            Proof.
+              intros.
+              unfold octuple, quadruple, double.
+              lia.
+            Qed.
+        "
+    };
+}
+
+/// Prove that `max_double(a, b) >= 2 * a`, using a callee from another module.
+#[test]
+fn prove_max_double() {
+    roq::prove! {
+        function max_double,
+        function quadruple,
+        inline r"
+            Require Import Lia.
+            Theorem max_double_ge : forall a b : nat,
+              max_double a b >= 2 * a.
+            Proof.
+              intros.
+              unfold max_double, util_max, double.
+              destruct (Nat.ltb a b) eqn: H;
+              try apply Nat.ltb_lt in H;
+              lia.
+            Qed.
+        "
+    };
+}
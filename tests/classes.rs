@@ -0,0 +1,57 @@
+use roq_derive::{class, instance, record};
+
+/// How much is charged for a transfer of `amount`.
+#[class]
+trait Fee {
+    fn fee(&self, amount: u64) -> u64;
+}
+
+#[record]
+struct Flat {
+    fee: u64,
+}
+
+#[record]
+struct Percent {
+    rate: u64,
+}
+
+#[instance]
+impl Fee for Flat {
+    fn fee(&self, _amount: u64) -> u64 {
+        self.fee
+    }
+}
+
+// Unchecked, since `amount * self.rate` can overflow.
+#[instance(unchecked)]
+impl Fee for Percent {
+    fn fee(&self, amount: u64) -> u64 {
+        amount * self.rate / 100
+    }
+}
+
+#[test]
+fn test_fees() {
+    assert_eq!(Flat { fee: 5 }.fee(1000), 5);
+    assert_eq!(Percent { rate: 2 }.fee(1000), 20);
+    assert_eq!(Percent { rate: 0 }.fee(1000), Flat { fee: 0 }.fee(1000));
+}
+
+/// Prove that two strategies are interchangeable when neither charges anything.
+#[test]
+fn prove_free_strategies_agree() {
+    roq::prove! {
+        instance Fee_Flat,
+        instance Fee_Percent,
+        inline r"
+            Theorem free_strategies_agree : forall (amount : nat),
+              Fee_fee {| Flat_fee := 0 |} amount = Fee_fee {| Percent_rate := 0 |} amount.
+            Proof.
+              intros amount.
+              cbv [Fee_fee Fee_Flat Fee_Percent Fee_Flat_fee Fee_Percent_fee Flat_fee Percent_rate].
+              rewrite Nat.mul_0_r. reflexivity.
+            Qed.
+        ",
+    };
+}
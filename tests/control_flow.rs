@@ -13,6 +13,16 @@ fn max(a: u64, b: u64) -> u64 {
     }
 }
 
+#[definition]
+fn classify(n: u64) -> u64 {
+    match n {
+        0 => 0,
+        1 | 3 => 1,
+        x if x < 10 => 2,
+        _ => 3,
+    }
+}
+
 #[test]
 fn test_max() {
     assert_eq!(max(1, 2), 2);
@@ -40,3 +50,27 @@ fn prove_max() {
         "
     };
 }
+
+#[test]
+fn test_classify() {
+    assert_eq!(classify(0), 0);
+    assert_eq!(classify(3), 1);
+    assert_eq!(classify(2), 2);
+    assert_eq!(classify(12), 3);
+}
+
+/// Prove that `classify` agrees with the Rust function on a few inputs, including both sides of
+/// the guard.
+#[test]
+fn prove_classify() {
+    roq::prove! {
+        function classify,
+        inline r"
+            Theorem classify_examples :
+              classify 0 = 0 /\ classify 3 = 1 /\ classify 2 = 2 /\ classify 12 = 3.
+            Proof.
+              repeat split; reflexivity.
+            Qed.
+        "
+    };
+}
@@ -0,0 +1,118 @@
+use roq_derive::definition;
+
+#[definition]
+fn safe_div(a: u64, b: u64) -> Option<u64> {
+    if b == 0 {
+        return None;
+    }
+    Some(a / b)
+}
+
+// Unchecked, since `x + y` can overflow.
+#[definition(unchecked)]
+fn div_sum(a: u64, b: u64, c: u64) -> Option<u64> {
+    let x = safe_div(a, c)?;
+    let y = safe_div(b, c)?;
+    Some(x + y)
+}
+
+#[definition]
+fn parse_digit(c: u64) -> Result<u64, u64> {
+    if c < 48 {
+        return Err(c);
+    }
+    if c > 57 {
+        return Err(c);
+    }
+    Ok(c - 48)
+}
+
+// Unchecked, since the obligations of `* 10` and `+` can't see that digits are below 10.
+#[definition(unchecked)]
+fn parse_pair(hi: u64, lo: u64) -> Result<u64, u64> {
+    Ok(parse_digit(hi)? * 10 + parse_digit(lo)?)
+}
+
+// Unchecked, since the sum can overflow.
+#[definition(unchecked)]
+fn sum_down(n: u64) -> u64 {
+    if n == 0 {
+        return 0;
+    }
+    n + sum_down(n - 1)
+}
+
+#[test]
+fn test_safe_div() {
+    assert_eq!(safe_div(7, 2), Some(3));
+    assert_eq!(safe_div(7, 0), None);
+    assert_eq!(div_sum(6, 4, 2), Some(5));
+    assert_eq!(div_sum(6, 4, 0), None);
+}
+
+#[test]
+fn test_parse_pair() {
+    assert_eq!(parse_pair(52, 50), Ok(42));
+    assert_eq!(parse_pair(52, 120), Err(120));
+    assert_eq!(sum_down(4), 10);
+}
+
+/// Prove that dividing by zero fails however the result is used.
+#[test]
+fn prove_div_sum_zero() {
+    roq::prove! {
+        file "obligations.v",
+        function safe_div,
+        inline r"
+            Next Obligation.
+              guards. lia.
+            Qed.
+        ",
+        function div_sum,
+        inline r"
+            Theorem div_sum_zero : forall a b : nat, div_sum a b 0 = None.
+            Proof.
+              reflexivity.
+            Qed.
+        "
+    };
+}
+
+/// Prove that parsing two digits fails with the first character which isn't one.
+#[test]
+fn prove_parse_pair_error() {
+    roq::prove! {
+        file "obligations.v",
+        function parse_digit,
+        inline r"
+            Next Obligation.
+              guards. lia.
+            Qed.
+        ",
+        function parse_pair,
+        inline r"
+            Theorem parse_pair_error : parse_pair 120 50 = inr 120.
+            Proof.
+              reflexivity.
+            Qed.
+        "
+    };
+}
+
+/// Prove Gauss's formula for the recursion with a guard clause.
+#[test]
+fn prove_sum_down() {
+    roq::prove! {
+        function sum_down,
+        inline r"
+            Require Import Lia.
+
+            Theorem sum_down_formula : forall n : nat, 2 * sum_down n = n * (n + 1).
+            Proof.
+              induction n as [| n IH].
+              - reflexivity.
+              - simpl sum_down. nia.
+            Qed.
+        "
+    };
+}
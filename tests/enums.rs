@@ -0,0 +1,130 @@
+use roq_derive::{definition, inductive};
+
+#[inductive]
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Shape {
+    Empty,
+    Square(u64),
+    Rect { width: u64, height: u64 },
+}
+
+#[inductive]
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Command {
+    Stop,
+    Move { dx: i64, dy: i64 },
+}
+
+#[inductive]
+#[derive(Debug, Clone, PartialEq)]
+enum Tree {
+    Leaf,
+    Node(Vec<Tree>),
+}
+
+// Unchecked, since `side * side` can overflow.
+#[definition(unchecked)]
+fn area(shape: Shape) -> u64 {
+    match shape {
+        Shape::Empty => 0,
+        Shape::Square(side) => side * side,
+        Shape::Rect { width, height } => width * height,
+    }
+}
+
+#[definition]
+fn square(side: u64) -> Shape {
+    if side == 0 {
+        Shape::Empty
+    } else {
+        Shape::Square(side)
+    }
+}
+
+#[definition]
+fn as_rect(shape: Shape) -> Shape {
+    match shape {
+        Shape::Square(side) => Shape::Rect {
+            width: side,
+            height: side,
+        },
+        other => other,
+    }
+}
+
+#[definition]
+fn step(x: i64, command: Command) -> i64 {
+    match command {
+        Command::Stop => x,
+        Command::Move { dx, .. } => x + dx,
+    }
+}
+
+#[test]
+fn test_area() {
+    assert_eq!(area(Shape::Empty), 0);
+    assert_eq!(area(square(3)), 9);
+    assert_eq!(
+        area(Shape::Rect {
+            width: 2,
+            height: 5
+        }),
+        10
+    );
+}
+
+#[test]
+fn test_recursive_vernacular() {
+    assert_ne!(Tree::Node(vec![Tree::Leaf]), Tree::Leaf);
+    let vernacular = <Tree as roq_core::CoqType>::vernacular().to_string();
+    assert_eq!(vernacular.matches("Inductive Tree").count(), 1);
+}
+
+#[test]
+fn test_as_rect() {
+    assert_eq!(
+        as_rect(square(4)),
+        Shape::Rect {
+            width: 4,
+            height: 4
+        }
+    );
+    assert_eq!(as_rect(Shape::Empty), Shape::Empty);
+}
+
+#[test]
+fn test_step() {
+    assert_eq!(step(3, Command::Stop), 3);
+    assert_eq!(step(3, Command::Move { dx: -5, dy: 1 }), -2);
+}
+
+/// Prove that converting a shape to a rectangle doesn't change its area.
+#[test]
+fn prove_as_rect_area() {
+    roq::prove! {
+        function as_rect,
+        function area,
+        inline r"
+            Theorem as_rect_area : forall s : Shape,
+              area (as_rect s) = area s.
+            Proof.
+              intros [| side | [width height]]; reflexivity.
+            Qed.
+        "
+    };
+}
+
+/// Prove that stopping doesn't move.
+#[test]
+fn prove_step_stop() {
+    roq::prove! {
+        function step,
+        inline r"
+            Theorem step_stop : forall x : Z,
+              step x Command_Stop = x.
+            Proof.
+              reflexivity.
+            Qed.
+        "
+    };
+}
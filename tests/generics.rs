@@ -0,0 +1,68 @@
+use roq_derive::definition;
+
+#[definition]
+fn id<T>(x: T) -> T {
+    x
+}
+
+#[definition]
+fn swap<A, B>(pair: (A, B)) -> (B, A) {
+    let (a, b) = pair;
+    (b, a)
+}
+
+#[definition]
+fn pick<T>(flag: bool, a: T, b: T) -> T {
+    if flag {
+        id(a)
+    } else {
+        b
+    }
+}
+
+#[definition]
+fn first_or<'a, T>(values: &'a [T], default: &'a T) -> &'a T {
+    match values.first() {
+        Some(first) => first,
+        None => default,
+    }
+}
+
+#[test]
+fn test_id() {
+    assert_eq!(id(5), 5);
+    assert_eq!(swap((1, true)), (true, 1));
+    assert_eq!(pick(false, "a", "b"), "b");
+}
+
+#[test]
+fn test_first_or() {
+    assert_eq!(first_or(&[3, 4], &0), &3);
+    assert_eq!(first_or(&[], &0), &0);
+}
+
+/// Prove properties of generic functions once, for every type they can be applied to.
+#[test]
+fn prove_generic() {
+    roq::prove! {
+        function swap,
+        function pick,
+        function first_or,
+        inline r"
+            Theorem swap_swap : forall (A B : Type) (pair : A * B), swap (swap pair) = pair.
+            Proof.
+              intros A B [a b]. reflexivity.
+            Qed.
+
+            Theorem pick_true : forall (T : Type) (a b : T), pick true a b = a.
+            Proof.
+              reflexivity.
+            Qed.
+
+            Theorem first_or_nil : forall (T : Type) (default : T), first_or nil default = default.
+            Proof.
+              reflexivity.
+            Qed.
+        "
+    };
+}
@@ -1,28 +1,38 @@
 use roq_derive::definition;
 
 #[definition]
-fn double(a: u64) -> u64 {
-    a + a
+fn double(a: u64) -> Option<u64> {
+    if a >> 63 == 0 {
+        Some(a + a)
+    } else {
+        None
+    }
 }
 
 #[test]
 fn test_double() {
-    assert_eq!(double(1), 2);
+    assert_eq!(double(1), Some(2));
+    assert_eq!(double(u64::MAX), None);
 }
 
-/// Prove that `double(n) = 2 * n`
+/// Prove that `a + a` can't overflow once `a`'s top bit is known to be clear, then compute with
+/// `double`.
 #[test]
 fn prove_double() {
     roq::prove! {
+        file "obligations.v",
         function double,
         inline r"
-            Theorem double_mul : forall n : nat,
-              double n = 2 * n.
+            Next Obligation.
+              unfold fits_u64 in *. guards.
+              rewrite Nat.shiftr_div_pow2, Nat.div_small_iff in * by (apply Nat.pow_nonzero; discriminate).
+              match goal with H : _ < 2 ^ 63 |- _ => apply Nat2N.inj_lt in H; rewrite Nat2N.inj_pow in H end.
+              change (N.of_nat 2 ^ N.of_nat 63)%N with 9223372036854775808%N in *.
+              lia.
+            Qed.
+
+            Theorem double_21 : double 21 = Some 42.
             Proof.
-              intros.
-              unfold double.
-              simpl.
-              rewrite <- plus_n_O.
               reflexivity.
             Qed.
         "
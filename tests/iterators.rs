@@ -0,0 +1,74 @@
+use roq_derive::definition;
+
+// Unchecked, since `x * 2` can overflow.
+#[definition(unchecked)]
+fn doubled(values: &[u64]) -> Vec<u64> {
+    values.iter().map(|x| x * 2).collect()
+}
+
+#[definition]
+fn above(values: &[u64], threshold: u64) -> Vec<u64> {
+    values.iter().filter(|&&x| x > threshold).copied().collect()
+}
+
+#[definition]
+fn from_bits(bits: &[u64]) -> u64 {
+    bits.iter().fold(0, |acc, bit| acc * 2 + bit)
+}
+
+#[definition]
+fn all_positive(values: &[i64]) -> bool {
+    values.iter().all(|x| *x > 0)
+}
+
+#[definition]
+fn weighted(pairs: &[(u64, u64)]) -> u64 {
+    pairs.iter().map(|(weight, value)| weight * value).sum()
+}
+
+#[test]
+fn test_map_filter() {
+    assert_eq!(doubled(&[1, 2, 3]), [2, 4, 6]);
+    assert_eq!(above(&[1, 5, 2, 7], 2), [5, 7]);
+}
+
+#[test]
+fn test_fold() {
+    assert_eq!(from_bits(&[1, 0, 1, 1]), 11);
+    assert!(all_positive(&[1, 2]));
+    assert!(!all_positive(&[1, -2]));
+    assert_eq!(weighted(&[(2, 3), (1, 4)]), 10);
+}
+
+/// Prove that doubling every element keeps the length of the list.
+#[test]
+fn prove_doubled_length() {
+    roq::prove! {
+        function doubled,
+        inline r"
+            Theorem doubled_length : forall values : list nat,
+              length (doubled values) = length values.
+            Proof.
+              intros values. unfold doubled.
+              induction values as [| x rest IH]; simpl; congruence.
+            Qed.
+        "
+    };
+}
+
+/// Prove that every element kept by `above` is above the threshold.
+#[test]
+fn prove_above_threshold() {
+    roq::prove! {
+        function above,
+        inline r"
+            Theorem above_threshold : forall (values : list nat) (threshold x : nat),
+              In x (above values threshold) -> threshold < x.
+            Proof.
+              intros values threshold x H. unfold above in H.
+              apply filter_In in H. destruct H as [_ H].
+              apply Nat.ltb_lt. exact H.
+            Qed.
+        "
+    };
+}
@@ -0,0 +1,97 @@
+use roq_derive::definition;
+
+// Unchecked, since the sum can overflow.
+#[definition(unchecked)]
+fn total(prices: &[u64]) -> u64 {
+    prices.iter().sum()
+}
+
+#[definition]
+fn with_fee(prices: Vec<u64>, fee: u64) -> Vec<u64> {
+    let mut all = prices;
+    all.push(fee);
+    all
+}
+
+#[definition]
+fn describe(values: &[u64]) -> u64 {
+    if values.is_empty() {
+        0
+    } else if values.contains(&0) {
+        1
+    } else {
+        2
+    }
+}
+
+#[definition]
+fn next_after(values: &[i64], i: usize) -> i64 {
+    if let Some(x) = values.get(i) {
+        *x + 1
+    } else {
+        0
+    }
+}
+
+#[definition]
+fn zeros(n: usize) -> Vec<i64> {
+    vec![0; n]
+}
+
+#[test]
+fn test_total() {
+    assert_eq!(total(&[1, 2, 3]), 6);
+    assert_eq!(total(&with_fee(vec![1, 2, 3], 4)), 10);
+}
+
+#[test]
+fn test_describe() {
+    assert_eq!(describe(&[]), 0);
+    assert_eq!(describe(&[3, 0]), 1);
+    assert_eq!(describe(&[3, 4]), 2);
+}
+
+#[test]
+fn test_next_after() {
+    assert_eq!(next_after(&[5, -2], 1), -1);
+    assert_eq!(next_after(&[5, -2], 2), 0);
+    assert_eq!(zeros(2), [0, 0]);
+}
+
+/// Prove that adding a fee increases the total by the fee.
+#[test]
+fn prove_total_with_fee() {
+    roq::prove! {
+        function total,
+        function with_fee,
+        inline r"
+            Require Import Lia.
+
+            Theorem total_with_fee : forall (prices : list nat) (fee : nat),
+              total (with_fee prices fee) = total prices + fee.
+            Proof.
+              intros prices fee. unfold total, with_fee.
+              rewrite list_sum_app. simpl. lia.
+            Qed.
+        "
+    };
+}
+
+/// Prove that there's nothing after the end of a list of zeros.
+#[test]
+fn prove_next_after_zeros() {
+    roq::prove! {
+        function next_after,
+        function zeros,
+        inline r"
+            Theorem next_after_zeros : forall n : nat,
+              next_after (zeros n) n = 0%Z.
+            Proof.
+              intros n. unfold next_after, zeros.
+              rewrite (proj2 (nth_error_None (repeat 0%Z n) n)).
+              - reflexivity.
+              - rewrite repeat_length. constructor.
+            Qed.
+        "
+    };
+}
@@ -0,0 +1,201 @@
+use roq_derive::definition;
+
+#[definition]
+fn sum_to(n: u64) -> u64 {
+    let mut total = 0;
+    for i in 0..n {
+        total += i;
+    }
+    total
+}
+
+// Unchecked, since the total can overflow.
+#[definition(unchecked)]
+fn scaled_count(n: u64, k: u64) -> u64 {
+    let mut total = 0;
+    for _ in 0..n {
+        total += k;
+    }
+    total
+}
+
+// Unchecked, since the sequence overflows after 93 steps.
+#[definition(unchecked)]
+fn fibonacci(n: u64) -> u64 {
+    let mut a = 0;
+    let mut b = 1;
+    for _ in 0..n {
+        let next = a + b;
+        a = b;
+        b = next;
+    }
+    a
+}
+
+#[definition]
+fn alternating(lo: i64, hi: i64) -> i64 {
+    let mut sum = 0;
+    for x in lo..=hi {
+        sum = x - sum;
+    }
+    sum
+}
+
+// Unchecked, since the loop's obligations can't bound `steps`.
+#[definition(unchecked)]
+fn log2(n: u64) -> u64 {
+    let mut x = n;
+    let mut steps = 0;
+    #[roq::decreasing(x)]
+    while x > 1 {
+        x /= 2;
+        steps += 1;
+    }
+    steps
+}
+
+// Unchecked, since the obligations of `1 +` can't bound the count.
+#[definition(unchecked)]
+#[roq::decreasing(hi - lo)]
+fn count_between(lo: u64, hi: u64) -> u64 {
+    if lo < hi {
+        1 + count_between(lo + 1, hi)
+    } else {
+        0
+    }
+}
+
+#[test]
+fn test_sum_to() {
+    assert_eq!(sum_to(0), 0);
+    assert_eq!(sum_to(5), 10);
+    assert_eq!(scaled_count(4, 3), 12);
+}
+
+#[test]
+fn test_fibonacci() {
+    assert_eq!(fibonacci(0), 0);
+    assert_eq!(fibonacci(1), 1);
+    assert_eq!(fibonacci(10), 55);
+}
+
+#[test]
+fn test_alternating() {
+    assert_eq!(alternating(1, 3), 2);
+    assert_eq!(alternating(3, 1), 0);
+    assert_eq!(alternating(-2, 0), -1);
+}
+
+#[test]
+fn test_log2() {
+    assert_eq!(log2(0), 0);
+    assert_eq!(log2(1), 0);
+    assert_eq!(log2(8), 3);
+    assert_eq!(log2(9), 3);
+}
+
+#[test]
+fn test_count_between() {
+    assert_eq!(count_between(2, 5), 3);
+    assert_eq!(count_between(5, 2), 0);
+}
+
+/// Prove that adding `k` on each of `n` iterations gives `n * k`, using the loop's equation.
+#[test]
+fn prove_scaled_count() {
+    roq::prove! {
+        function scaled_count,
+        inline r"
+            Require Import Lia.
+
+            Lemma scaled_count_loop : forall fuel i k total,
+              scaled_count_loop'2 fuel i k total = total + fuel * k.
+            Proof.
+              induction fuel as [| fuel IH]; intros i k total;
+                rewrite scaled_count_loop'2_equation; simpl.
+              - lia.
+              - rewrite IH. lia.
+            Qed.
+
+            Theorem scaled_count_mul : forall n k : nat, scaled_count n k = n * k.
+            Proof.
+              intros n k. unfold scaled_count. cbv zeta.
+              rewrite scaled_count_loop, Nat.sub_0_r. lia.
+            Qed.
+        "
+    };
+}
+
+/// Prove a value of the Fibonacci sequence by computation.
+#[test]
+fn prove_fibonacci_values() {
+    roq::prove! {
+        function fibonacci,
+        inline r"
+            Theorem fibonacci_10 : fibonacci 10 = 55.
+            Proof.
+              reflexivity.
+            Qed.
+        "
+    };
+}
+
+#[test]
+fn test_loop_emitted_first() {
+    let mut emitted = roq::_Emitted::default();
+    let auxiliary = emitted
+        .include_loop("log2", log2::roq::vernacular().unwrap())
+        .unwrap();
+    assert!(auxiliary.contains("Program Fixpoint log2_loop'"));
+    assert!(!auxiliary.contains("Definition log2"));
+    let rest = emitted.include(log2::roq::vernacular().unwrap());
+    assert!(rest.contains("Definition log2"));
+    assert!(!rest.contains("Program Fixpoint"));
+}
+
+#[test]
+fn test_no_loop_to_emit() {
+    assert_eq!(fibonacci(10), 55);
+    let err = roq::try_prove! {
+        loop fibonacci,
+    }
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "`fibonacci` has no loop left with obligations to prove"
+    );
+}
+
+/// Prove that halving `x` while it's more than 1 terminates. The loop's `Program Fixpoint` must be
+/// defined before `log2` is, so it's emitted on its own, and its obligation proved, first.
+#[test]
+fn prove_log2_terminates() {
+    roq::prove! {
+        loop log2,
+        inline r"
+            Require Import Lia.
+
+            Next Obligation.
+              match goal with H : true = Nat.ltb 1 _ |- _ => symmetry in H; apply Nat.ltb_lt in H end.
+              apply Nat.div_lt; lia.
+            Qed.
+        ",
+        function log2
+    };
+}
+
+/// Prove that counting up to `hi` terminates.
+#[test]
+fn prove_count_between_terminates() {
+    roq::prove! {
+        function count_between,
+        inline r"
+            Require Import Lia.
+
+            Next Obligation.
+              match goal with H : true = Nat.ltb _ _ |- _ => symmetry in H; apply Nat.ltb_lt in H end.
+              lia.
+            Qed.
+        "
+    };
+}
@@ -0,0 +1,72 @@
+use roq_derive::{definition, impl_block, record};
+
+#[record]
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Account {
+    balance: u64,
+    limit: u64,
+}
+
+// Unchecked, since `self.limit - self.balance` underflows once the balance is over the limit.
+#[impl_block(unchecked)]
+impl Account {
+    fn new(limit: u64) -> Self {
+        Account { balance: 0, limit }
+    }
+
+    fn headroom(&self) -> u64 {
+        self.limit - self.balance
+    }
+
+    fn can_deposit(&self, amount: u64) -> bool {
+        amount <= self.headroom()
+    }
+
+    fn deposit(self, amount: u64) -> Account {
+        if self.can_deposit(amount) {
+            Account {
+                balance: self.balance + amount,
+                ..self
+            }
+        } else {
+            self
+        }
+    }
+}
+
+#[definition]
+fn open_with(limit: u64, amount: u64) -> Account {
+    Account::deposit(Account::new(limit), amount)
+}
+
+#[test]
+fn test_account() {
+    let account = Account::new(100);
+    assert_eq!(account.headroom(), 100);
+    assert_eq!(account.deposit(30).balance, 30);
+    assert_eq!(account.deposit(130), account);
+    assert_eq!(open_with(100, 30).headroom(), 70);
+}
+
+/// Prove that a deposit never takes an account over its limit.
+#[test]
+fn prove_deposit_within_limit() {
+    roq::prove! {
+        function Account_deposit,
+        function open_with,
+        inline r"
+            Require Import Lia.
+
+            Theorem deposit_within_limit : forall (account : Account) (amount : nat),
+              Account_balance account <= Account_limit account ->
+              Account_balance (Account_deposit account amount) <= Account_limit account.
+            Proof.
+              intros account amount H.
+              unfold Account_deposit, Account_can_deposit, Account_headroom.
+              destruct (Nat.leb amount (Account_limit account - Account_balance account)) eqn:E.
+              - apply Nat.leb_le in E. cbn. lia.
+              - exact H.
+            Qed.
+        ",
+    };
+}
@@ -0,0 +1,75 @@
+use roq_derive::definition;
+
+#[definition(numbers = "N")]
+fn seconds_to_days(secs: u64) -> u64 {
+    secs / 86400
+}
+
+#[definition(numbers = "N")]
+fn page_offset(addr: u64) -> u64 {
+    addr % 4096
+}
+
+#[definition(numbers = "Z")]
+fn midpoint(lo: u64, hi: u64) -> u64 {
+    lo + (hi - lo) / 2
+}
+
+#[definition(numbers = "word")]
+fn previous(x: u64) -> u64 {
+    x - 1
+}
+
+#[test]
+fn test_seconds_to_days() {
+    assert_eq!(seconds_to_days(604800), 7);
+    assert_eq!(seconds_to_days(86399), 0);
+}
+
+#[test]
+fn test_page_offset() {
+    assert_eq!(page_offset(4096), 0);
+    assert_eq!(page_offset(10000), 1808);
+    assert_eq!(midpoint(4, 10), 7);
+    assert_eq!(previous(5), 4);
+}
+
+/// Compute with large constants, which would be far too slow as `nat`s.
+#[test]
+fn prove_large_constants() {
+    roq::prove! {
+        function seconds_to_days,
+        function page_offset,
+        function midpoint,
+        inline r"
+            Theorem seconds_to_days_week : seconds_to_days 604800 = 7%N.
+            Proof.
+              vm_compute. reflexivity.
+            Qed.
+
+            Theorem page_offset_example : page_offset 10000 = 1808%N.
+            Proof.
+              vm_compute. reflexivity.
+            Qed.
+
+            Theorem midpoint_example : midpoint 4096 86400 = 45248%Z.
+            Proof.
+              reflexivity.
+            Qed.
+        "
+    };
+}
+
+/// Prove that a `u64` wraps around when it underflows, as it does without overflow checks.
+#[test]
+fn prove_previous_wraps() {
+    roq::prove! {
+        function previous,
+        inline r"
+            Theorem previous_zero : previous 0 = 18446744073709551615%N.
+            Proof.
+              vm_compute. reflexivity.
+            Qed.
+        "
+    };
+}
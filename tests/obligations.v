@@ -0,0 +1,31 @@
+Require Import Bool NArith ZArith Lia.
+
+(* Turn the comparisons an obligation of checked arithmetic assumes into arithmetic, for lia.
+   Unfold fits_u64 first, if it's used. *)
+Ltac guards :=
+  intros;
+  repeat match goal with
+  | H : true = false |- _ => discriminate H
+  | H : false = true |- _ => discriminate H
+  | H : ?b = ?b |- _ => clear H
+  | H : true = _ |- _ => symmetry in H
+  | H : false = _ |- _ => symmetry in H
+  | H : negb _ = true |- _ => apply negb_true_iff in H
+  | H : negb _ = false |- _ => apply negb_false_iff in H
+  | H : andb _ _ = true |- _ => apply andb_prop in H; destruct H
+  | H : Nat.eqb _ _ = true |- _ => apply Nat.eqb_eq in H
+  | H : Nat.eqb _ _ = false |- _ => apply Nat.eqb_neq in H
+  | H : Nat.ltb _ _ = true |- _ => apply Nat.ltb_lt in H
+  | H : Nat.ltb _ _ = false |- _ => apply Nat.ltb_ge in H
+  | H : Nat.leb _ _ = true |- _ => apply Nat.leb_le in H
+  | H : Nat.leb _ _ = false |- _ => apply Nat.leb_gt in H
+  | H : N.ltb _ _ = true |- _ => apply N.ltb_lt in H
+  | H : N.ltb _ _ = false |- _ => apply N.ltb_ge in H
+  | H : Z.eqb _ _ = true |- _ => apply Z.eqb_eq in H
+  | H : Z.eqb _ _ = false |- _ => apply Z.eqb_neq in H
+  | H : Z.ltb _ _ = true |- _ => apply Z.ltb_lt in H
+  | H : Z.ltb _ _ = false |- _ => apply Z.ltb_ge in H
+  | H : Z.leb _ _ = true |- _ => apply Z.leb_le in H
+  | H : Z.leb _ _ = false |- _ => apply Z.leb_gt in H
+  end;
+  try change (N.pow 2 64) with 18446744073709551616%N in *.
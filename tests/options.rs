@@ -0,0 +1,113 @@
+use roq_derive::definition;
+
+// Unchecked, so that the proof below can rewrite the comparison which a checked subtraction's
+// obligation depends on.
+#[definition(unchecked)]
+fn checked_sub(a: u64, b: u64) -> Option<u64> {
+    if a >= b {
+        Some(a - b)
+    } else {
+        None
+    }
+}
+
+// Unchecked, since `remaining + 1` overflows when nothing's used of a `u64::MAX` limit.
+#[definition(unchecked)]
+fn headroom(limit: u64, used: u64) -> u64 {
+    if let Some(remaining) = checked_sub(limit, used) {
+        remaining + 1
+    } else {
+        0
+    }
+}
+
+// Unchecked, so that the proof below can rewrite the comparison which a checked subtraction's
+// obligation depends on.
+#[definition(unchecked)]
+fn parse_digit(code: u64) -> Result<u64, u64> {
+    if code >= 48 {
+        let digit = code - 48;
+        if digit < 10 {
+            Ok(digit)
+        } else {
+            Err(code)
+        }
+    } else {
+        Err(code)
+    }
+}
+
+#[definition]
+fn digit_or(digit: Result<u64, u64>, default: u64) -> u64 {
+    match digit {
+        Ok(d) => d,
+        Err(_) => default,
+    }
+}
+
+#[definition]
+fn offset(o: Option<i64>, delta: i64) -> Option<i64> {
+    match o {
+        Some(x) => Some(x + delta),
+        None => Some(delta),
+    }
+}
+
+#[test]
+fn test_checked_sub() {
+    assert_eq!(checked_sub(5, 3), Some(2));
+    assert_eq!(checked_sub(3, 5), None);
+    assert_eq!(headroom(5, 3), 3);
+    assert_eq!(headroom(3, 5), 0);
+}
+
+#[test]
+fn test_parse_digit() {
+    assert_eq!(parse_digit(b'7' as u64), Ok(7));
+    assert_eq!(parse_digit(b'x' as u64), Err(b'x' as u64));
+    assert_eq!(digit_or(parse_digit(b'x' as u64), 10), 10);
+}
+
+#[test]
+fn test_offset() {
+    assert_eq!(offset(Some(-3), 5), Some(2));
+    assert_eq!(offset(None, 5), Some(5));
+}
+
+/// Prove that there's headroom whenever the limit hasn't been exceeded.
+#[test]
+fn prove_headroom() {
+    roq::prove! {
+        function headroom,
+        inline r"
+            Theorem headroom_within_limit : forall limit used : nat,
+              used <= limit -> headroom limit used = limit - used + 1.
+            Proof.
+              intros limit used H. unfold headroom, checked_sub.
+              apply Nat.leb_le in H. rewrite H.
+              reflexivity.
+            Qed.
+        "
+    };
+}
+
+/// Prove that codes past the digits fall back to the default.
+#[test]
+fn prove_digit_or_default() {
+    roq::prove! {
+        function parse_digit,
+        function digit_or,
+        inline r"
+            Require Import Lia.
+
+            Theorem digit_or_default : forall code default : nat,
+              58 <= code -> digit_or (parse_digit code) default = default.
+            Proof.
+              intros code default H. unfold digit_or, parse_digit. cbv zeta.
+              replace (Nat.leb 48 code) with true by (symmetry; apply Nat.leb_le; lia).
+              replace (Nat.ltb (code - 48) 10) with false by (symmetry; apply Nat.ltb_ge; lia).
+              reflexivity.
+            Qed.
+        "
+    };
+}
@@ -0,0 +1,109 @@
+use roq_derive::definition;
+
+#[definition]
+fn remaining(used: u64, budget: u64) -> u64 {
+    if used > budget {
+        return 0;
+    }
+    budget - used
+}
+
+#[definition]
+fn increment_below(x: u64, limit: u64) -> u64 {
+    if x < limit {
+        x + 1
+    } else {
+        limit
+    }
+}
+
+#[definition]
+fn ratio(a: u64, b: u64) -> Option<u64> {
+    if b == 0 {
+        return None;
+    }
+    Some(a / b)
+}
+
+#[definition(unchecked)]
+fn sum(a: u64, b: u64) -> u64 {
+    a + b
+}
+
+#[test]
+fn test_remaining() {
+    assert_eq!(remaining(3, 10), 7);
+    assert_eq!(remaining(10, 3), 0);
+}
+
+#[test]
+fn test_increment_below() {
+    assert_eq!(increment_below(3, 10), 4);
+    assert_eq!(increment_below(u64::MAX, u64::MAX), u64::MAX);
+    assert_eq!(ratio(7, 2), Some(3));
+    assert_eq!(ratio(7, 0), None);
+}
+
+/// Prove that none of the functions can overflow, underflow or divide by zero, given that their
+/// arguments are `u64`s, then compute with them.
+#[test]
+fn prove_no_overflow() {
+    roq::prove! {
+        file "obligations.v",
+        function remaining,
+        inline r"
+            Next Obligation.
+              guards. lia.
+            Qed.
+        ",
+        function increment_below,
+        inline r"
+            Next Obligation.
+              unfold fits_u64 in *. guards. lia.
+            Qed.
+        ",
+        function ratio,
+        inline r"
+            Next Obligation.
+              guards. lia.
+            Qed.
+
+            Theorem remaining_example : remaining 3 10 = 7.
+            Proof.
+              reflexivity.
+            Qed.
+
+            Theorem increment_below_example : increment_below 3 10 = 4.
+            Proof.
+              reflexivity.
+            Qed.
+
+            Theorem ratio_zero : forall a : nat, ratio a 0 = None.
+            Proof.
+              reflexivity.
+            Qed.
+        "
+    };
+}
+
+#[test]
+fn test_unchecked() {
+    assert_eq!(sum(3, 4), 7);
+    let vernacular = sum::roq::vernacular().unwrap().to_string();
+    assert!(vernacular.contains("Definition sum (a: nat) (b: nat) : nat"));
+    assert!(!vernacular.contains("Program") && !vernacular.contains("fits_u64"));
+}
+
+/// Prove that without checks, `sum` is the sum of unbounded naturals.
+#[test]
+fn prove_unchecked_sum() {
+    roq::prove! {
+        function sum,
+        inline r"
+            Theorem sum_plus : forall a b : nat, sum a b = a + b.
+            Proof.
+              reflexivity.
+            Qed.
+        "
+    };
+}
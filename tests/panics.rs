@@ -0,0 +1,114 @@
+use roq_derive::definition;
+
+#[definition]
+fn bucket(x: i64) -> u64 {
+    if x > 0 {
+        2
+    } else if x <= 0 {
+        1
+    } else {
+        unreachable!("every integer is positive or not")
+    }
+}
+
+#[definition]
+fn average(total: u64, count: u64) -> u64 {
+    if count == 0 {
+        return 0;
+    }
+    assert!(count > 0);
+    total / count
+}
+
+// Unchecked, since the total can overflow. The assertion is still checked.
+#[definition(unchecked)]
+fn sum_evens(n: u64) -> u64 {
+    let mut total = 0;
+    for i in 0..n {
+        assert_eq!((2 * i) % 2, 0);
+        total += 2 * i;
+    }
+    total
+}
+
+#[test]
+fn test_bucket() {
+    assert_eq!(bucket(5), 2);
+    assert_eq!(bucket(0), 1);
+    assert_eq!(bucket(-5), 1);
+}
+
+#[test]
+fn test_average() {
+    assert_eq!(average(7, 0), 0);
+    assert_eq!(average(7, 2), 3);
+    assert_eq!(sum_evens(3), 6);
+}
+
+/// Prove that `bucket` never reaches `unreachable!`, since an integer which isn't positive is at
+/// most 0.
+#[test]
+fn prove_bucket_no_panic() {
+    roq::prove! {
+        function bucket,
+        inline r"
+            Require Import Lia.
+
+            Next Obligation.
+              match goal with
+              | H1 : false = Z.ltb _ _, H2 : false = Z.leb _ _ |- _ =>
+                  symmetry in H1, H2; apply Z.ltb_ge in H1; apply Z.leb_gt in H2
+              end.
+              lia.
+            Qed.
+
+            Theorem bucket_negative : bucket (-5)%Z = 1.
+            Proof.
+              reflexivity.
+            Qed.
+        "
+    };
+}
+
+/// Prove that the assertion in `average` always holds, and that it never divides by zero, because
+/// of the guard clause before them.
+#[test]
+fn prove_average_no_panic() {
+    roq::prove! {
+        file "obligations.v",
+        function average,
+        inline r"
+            Next Obligation.
+              guards. lia.
+            Qed.
+
+            Next Obligation.
+              guards. lia.
+            Qed.
+        "
+    };
+}
+
+/// Prove that the assertion in the loop of `sum_evens` always holds. The loop's `Fixpoint` is
+/// defined before `sum_evens`, so it's emitted on its own, and its obligation proved, first.
+#[test]
+fn prove_sum_evens_no_panic() {
+    roq::prove! {
+        loop sum_evens,
+        inline r"
+            Require Import Lia.
+
+            Next Obligation.
+              match goal with H : false = Nat.eqb _ _ |- _ => symmetry in H; apply Nat.eqb_neq in H end.
+              lia.
+            Qed.
+        ",
+        function sum_evens,
+        inline r"
+            Theorem sum_evens_3 : sum_evens 3 = 6.
+            Proof.
+              reflexivity.
+            Qed.
+        "
+    };
+}
@@ -0,0 +1,251 @@
+use roq_derive::{definition, record};
+
+#[record]
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Size {
+    width: u64,
+    height: u64,
+}
+
+#[record]
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Window {
+    size: Size,
+    x: i64,
+    y: i64,
+    visible: bool,
+}
+
+// Unchecked, since `size.width * size.height` can overflow.
+#[definition(unchecked)]
+fn area(size: Size) -> u64 {
+    size.width * size.height
+}
+
+#[definition]
+fn open(size: Size) -> Window {
+    Window {
+        size,
+        x: 0,
+        y: 0,
+        visible: true,
+    }
+}
+
+#[definition]
+fn shift(window: Window, dx: i64) -> Window {
+    Window {
+        x: window.x + dx,
+        ..window
+    }
+}
+
+#[definition]
+fn window_area(window: Window) -> u64 {
+    if window.visible {
+        area(window.size)
+    } else {
+        0
+    }
+}
+
+#[definition]
+fn on_screen(window: Window) -> bool {
+    window.visible && window.x >= 0 && window.y >= 0 && window.size.width > 0
+}
+
+#[definition]
+fn same_place(a: Size, b: Size) -> bool {
+    open(a) == open(b)
+}
+
+#[definition]
+fn stays_put(size: Size) -> bool {
+    same_place(size, size)
+}
+
+mod screen {
+    use roq_derive::{definition, record};
+
+    #[record]
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct Point {
+        pub x: i64,
+        pub y: i64,
+    }
+
+    #[definition]
+    pub fn origin() -> Point {
+        Point { x: 0, y: 0 }
+    }
+}
+
+mod paper {
+    use roq_derive::{definition, record};
+
+    #[record]
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct Point {
+        pub x: u64,
+        pub y: u64,
+    }
+
+    #[definition]
+    pub fn origin() -> Point {
+        Point { x: 0, y: 0 }
+    }
+}
+
+#[definition]
+fn first_pixel() -> i64 {
+    screen::origin().x
+}
+
+#[definition]
+fn first_mark() -> u64 {
+    paper::origin().x
+}
+
+#[test]
+fn test_area() {
+    assert_eq!(
+        area(Size {
+            width: 3,
+            height: 4
+        }),
+        12
+    );
+}
+
+#[test]
+fn test_shift() {
+    let window = open(Size {
+        width: 3,
+        height: 4,
+    });
+    assert_eq!(shift(window, -5).x, -5);
+    assert_eq!(shift(window, -5).size, window.size);
+}
+
+#[test]
+fn test_window_area() {
+    let window = open(Size {
+        width: 3,
+        height: 4,
+    });
+    assert_eq!(window_area(window), 12);
+}
+
+#[test]
+fn test_on_screen() {
+    let window = open(Size {
+        width: 3,
+        height: 4,
+    });
+    assert!(on_screen(window));
+    assert!(!on_screen(shift(window, -1)));
+}
+
+#[test]
+fn test_field_types() {
+    let vernacular = on_screen::roq::vernacular().unwrap().to_string();
+    assert!(vernacular.contains("(Z.leb 0%Z (Window_x window)"));
+    assert!(vernacular.contains("(Nat.ltb 0 (Size_width (Window_size window)"));
+    let vernacular = open::roq::vernacular().unwrap().to_string();
+    assert!(vernacular.contains("Window_x := 0%Z"));
+}
+
+#[test]
+fn test_error_located() {
+    let size = Size {
+        width: 3,
+        height: 4,
+    };
+    assert!(same_place(size, size));
+    let Err(err) = same_place::roq::vernacular() else {
+        panic!("expected `==` on records to be rejected");
+    };
+    assert_eq!(
+        err.to_string(),
+        "tests/records.rs:59:5: can't translate `same_place` to Coq: \
+         operator `==` isn't supported on `Window`"
+    );
+}
+
+#[test]
+fn test_callee_error_located() {
+    assert!(stays_put(Size {
+        width: 3,
+        height: 4,
+    }));
+    let err = roq::try_prove! {
+        function stays_put,
+    }
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "tests/records.rs:59:5: can't translate `same_place` to Coq: \
+         operator `==` isn't supported on `Window`"
+    );
+}
+
+#[test]
+fn test_qualified_names() {
+    assert_eq!(first_pixel(), 0);
+    assert_eq!(first_mark(), 0);
+    let vernacular = first_pixel::roq::vernacular().unwrap().to_string();
+    assert!(vernacular.contains("Record screen_Point"));
+    assert!(vernacular.contains("screen_Point_x := 0%Z"));
+    assert!(vernacular.contains("(screen_Point_x (screen_origin)"));
+    let vernacular = first_mark::roq::vernacular().unwrap().to_string();
+    assert!(vernacular.contains("Record paper_Point"));
+    assert!(vernacular.contains("(paper_Point_x (paper_origin)"));
+}
+
+/// Prove things about two records of the same name, defined in different modules.
+#[test]
+fn prove_qualified_names() {
+    roq::prove! {
+        function first_pixel,
+        function first_mark,
+        inline r"
+            Theorem firsts : first_pixel = 0%Z /\ first_mark = 0.
+            Proof.
+              split; reflexivity.
+            Qed.
+        "
+    };
+}
+
+/// Prove that moving a window doesn't change its area.
+#[test]
+fn prove_shift_area() {
+    roq::prove! {
+        function shift,
+        function window_area,
+        inline r"
+            Theorem shift_area : forall (w : Window) (dx : Z),
+              window_area (shift w dx) = window_area w.
+            Proof.
+              intros [size x y visible] dx.
+              reflexivity.
+            Qed.
+        "
+    };
+}
+
+/// Prove that a newly opened window shows its whole area.
+#[test]
+fn prove_open_area() {
+    roq::prove! {
+        function open,
+        function window_area,
+        inline r"
+            Theorem open_area : forall s : Size,
+              window_area (open s) = area s.
+            Proof.
+              reflexivity.
+            Qed.
+        "
+    };
+}
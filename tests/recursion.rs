@@ -0,0 +1,69 @@
+use roq_derive::definition;
+
+// Unchecked, since the product overflows past `20!`.
+#[definition(unchecked)]
+fn factorial(n: u64) -> u64 {
+    if n == 0 {
+        1
+    } else {
+        n * factorial(n - 1)
+    }
+}
+
+// Unchecked, since the sequence overflows after 93 steps.
+#[definition(unchecked)]
+fn fib(n: u64) -> u64 {
+    if n < 2 {
+        n
+    } else {
+        fib(n - 1) + fib(n - 2)
+    }
+}
+
+#[test]
+fn test_factorial() {
+    assert_eq!(factorial(0), 1);
+    assert_eq!(factorial(5), 120);
+}
+
+#[test]
+fn test_fib() {
+    assert_eq!(fib(0), 0);
+    assert_eq!(fib(1), 1);
+    assert_eq!(fib(10), 55);
+}
+
+/// Prove that the translated `factorial` computes the same values as the Rust one.
+#[test]
+fn prove_factorial() {
+    roq::prove! {
+        function factorial,
+        inline r"
+            Theorem factorial_5 : factorial 5 = 120.
+            Proof.
+              reflexivity.
+            Qed.
+        "
+    };
+}
+
+/// Prove that `fib` satisfies its defining recurrence.
+#[test]
+fn prove_fib() {
+    roq::prove! {
+        function fib,
+        inline r"
+            Theorem fib_10 : fib 10 = 55.
+            Proof.
+              reflexivity.
+            Qed.
+
+            Theorem fib_recurrence : forall n : nat,
+              fib (S (S n)) = fib (S n) + fib n.
+            Proof.
+              intros.
+              reflexivity.
+            Qed.
+        "
+    };
+}
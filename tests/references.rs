@@ -0,0 +1,110 @@
+use roq_derive::{definition, impl_block, record};
+
+#[record]
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Counter {
+    count: u64,
+    resets: u64,
+}
+
+// Unchecked, since the count can overflow.
+#[definition(unchecked)]
+fn bump(counter: &mut Counter, amount: u64) -> u64 {
+    counter.count += amount;
+    counter.count
+}
+
+// Unchecked, since the count of resets can overflow.
+#[definition(unchecked)]
+fn reset(counter: &mut Counter) {
+    counter.count = 0;
+    counter.resets += 1;
+}
+
+// Unchecked, since `*from -= amount` underflows when `amount` is more than `*from`.
+#[definition(unchecked)]
+fn transfer(from: &mut u64, to: &mut u64, amount: u64) {
+    *from -= amount;
+    *to += amount;
+}
+
+#[definition]
+fn bump_then_reset(counter: Counter, amount: u64) -> (u64, Counter) {
+    let mut counter = counter;
+    let reached = bump(&mut counter, amount);
+    reset(&mut counter);
+    (reached, counter)
+}
+
+// Unchecked, since the count can overflow.
+#[impl_block(unchecked)]
+impl Counter {
+    fn tick(&mut self) -> bool {
+        self.count += 1;
+        self.count >= 10
+    }
+}
+
+#[test]
+fn test_references() {
+    let mut counter = Counter {
+        count: 1,
+        resets: 0,
+    };
+    assert_eq!(bump(&mut counter, 2), 3);
+    reset(&mut counter);
+    assert_eq!(
+        counter,
+        Counter {
+            count: 0,
+            resets: 1
+        }
+    );
+    let (mut from, mut to) = (5, 2);
+    transfer(&mut from, &mut to, 3);
+    assert_eq!((from, to), (2, 5));
+    assert_eq!(
+        bump_then_reset(counter, 5),
+        (
+            5,
+            Counter {
+                count: 0,
+                resets: 2
+            }
+        )
+    );
+    assert!(!counter.tick());
+}
+
+/// Prove properties of the new values functions give their `&mut` arguments.
+#[test]
+fn prove_new_values() {
+    roq::prove! {
+        function transfer,
+        function bump_then_reset,
+        function Counter_tick,
+        inline r"
+            Require Import Lia.
+
+            Theorem transfer_conserves : forall (from to amount : nat),
+              amount <= from ->
+              let '(_, from', to') := transfer from to amount in from' + to' = from + to.
+            Proof.
+              intros. simpl. lia.
+            Qed.
+
+            Theorem bump_then_reset_resets : forall (counter : Counter) (amount : nat),
+              Counter_count (snd (bump_then_reset counter amount)) = 0 /\
+              Counter_resets (snd (bump_then_reset counter amount)) = S (Counter_resets counter).
+            Proof.
+              intros. simpl. split; [reflexivity | lia].
+            Qed.
+
+            Theorem tick_increments : forall (counter : Counter),
+              Counter_count (snd (Counter_tick counter)) = S (Counter_count counter).
+            Proof.
+              intros. simpl. lia.
+            Qed.
+        ",
+    };
+}
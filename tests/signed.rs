@@ -0,0 +1,120 @@
+use roq_derive::definition;
+
+#[definition]
+fn abs(x: i64) -> i64 {
+    if x < 0 {
+        -x
+    } else {
+        x
+    }
+}
+
+#[definition]
+fn clamp(x: i64, lo: i64, hi: i64) -> i64 {
+    if x < lo {
+        lo
+    } else if hi < x {
+        hi
+    } else {
+        x
+    }
+}
+
+#[definition]
+fn midpoint(a: i32, b: i32) -> i32 {
+    let sum = a + b;
+    sum / 2
+}
+
+#[definition]
+fn ratio(a: i64, b: i64) -> Option<i64> {
+    if b > 0 {
+        Some(a / b)
+    } else {
+        None
+    }
+}
+
+#[test]
+fn test_abs() {
+    assert_eq!(abs(-3), 3);
+    assert_eq!(abs(4), 4);
+}
+
+#[test]
+fn test_clamp() {
+    assert_eq!(clamp(-10, -5, 5), -5);
+    assert_eq!(clamp(10, -5, 5), 5);
+    assert_eq!(clamp(1, -5, 5), 1);
+}
+
+#[test]
+fn test_midpoint() {
+    assert_eq!(midpoint(-7, 2), -2);
+}
+
+#[test]
+fn test_ratio() {
+    assert_eq!(ratio(-7, 2), Some(-3));
+    assert_eq!(ratio(7, 0), None);
+    assert_eq!(ratio(i64::MIN, -1), None);
+}
+
+/// Prove that `ratio` neither divides by zero nor overflows, since it only divides by a positive
+/// number.
+#[test]
+fn prove_ratio() {
+    roq::prove! {
+        file "obligations.v",
+        function ratio,
+        inline r"
+            Next Obligation.
+              guards.
+              match goal with H : andb _ _ = false |- _ => apply andb_false_iff in H; destruct H end;
+              guards; lia.
+            Qed.
+
+            Theorem ratio_example : ratio (-7) 2 = Some (-3)%Z.
+            Proof.
+              reflexivity.
+            Qed.
+        "
+    };
+}
+
+/// Prove that `abs` never returns a negative number.
+#[test]
+fn prove_abs() {
+    roq::prove! {
+        function abs,
+        inline r"
+            Require Import Lia.
+
+            Theorem abs_nonneg : forall x : Z, (0 <= abs x)%Z.
+            Proof.
+              intros. unfold abs.
+              destruct (Z.ltb_spec x 0); lia.
+            Qed.
+        "
+    };
+}
+
+/// Prove that `clamp` and `midpoint` round towards zero like Rust does.
+#[test]
+fn prove_clamp_midpoint() {
+    roq::prove! {
+        function clamp,
+        function midpoint,
+        inline r"
+            Theorem clamp_low : clamp (-10) (-5) 5 = (-5)%Z.
+            Proof.
+              reflexivity.
+            Qed.
+
+            Theorem midpoint_negative : midpoint (-7) 2 = (-2)%Z.
+            Proof.
+              reflexivity.
+            Qed.
+        "
+    };
+}
@@ -0,0 +1,70 @@
+use roq_derive::definition;
+use std::ops::Add;
+
+#[definition]
+fn largest<T: Ord + Copy>(a: T, b: T) -> T {
+    if a < b {
+        b
+    } else {
+        a
+    }
+}
+
+#[definition]
+fn count_equal<T: PartialEq>(values: &[T], x: &T) -> usize {
+    values.iter().filter(|value| value.eq(&x)).count()
+}
+
+#[definition]
+fn times<T>(x: T, n: u64) -> T
+where
+    T: Add<Output = T> + Default + Copy,
+{
+    let mut total = T::default();
+    for _ in 0..n {
+        total = total + x;
+    }
+    total
+}
+
+#[test]
+fn test_largest() {
+    assert_eq!(largest(3, 7), 7);
+    assert_eq!(largest(-3, -7), -3);
+    assert_eq!(count_equal(&[1, 2, 1], &1), 2);
+    assert_eq!(times(3, 4), 12);
+    assert_eq!(times(-2i64, 3), -6);
+}
+
+/// Prove properties of functions with trait bounds once, for every instance of their classes.
+#[test]
+fn prove_bounded() {
+    roq::prove! {
+        function largest,
+        function count_equal,
+        function times,
+        inline r"
+            Theorem largest_same : forall (T : Type) `{Ord T} (a : T), largest a a = a.
+            Proof.
+              intros. unfold largest. destruct (Ord_ltb a a); reflexivity.
+            Qed.
+
+            Theorem count_equal_nil : forall (T : Type) `{PartialEq T} (x : T),
+              count_equal nil x = 0.
+            Proof.
+              reflexivity.
+            Qed.
+
+            Theorem times_zero : forall (T : Type) `{Add T} `{Default T} (x : T),
+              times x 0 = Default_default.
+            Proof.
+              reflexivity.
+            Qed.
+
+            Theorem times_example : times 3%Z 4 = 12%Z.
+            Proof.
+              reflexivity.
+            Qed.
+        "
+    };
+}
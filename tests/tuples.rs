@@ -0,0 +1,76 @@
+use roq_derive::definition;
+
+#[definition]
+fn min_max(a: u64, b: u64) -> (u64, u64) {
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+// Unchecked, since the obligation of `hi - lo` can't see that `min_max` orders the pair.
+#[definition(unchecked)]
+fn spread(a: u64, b: u64) -> u64 {
+    let (lo, hi) = min_max(a, b);
+    hi - lo
+}
+
+#[definition]
+fn swap((x, y): (i64, i64)) -> (i64, i64) {
+    (y, x)
+}
+
+#[definition]
+fn translate(point: (i64, i64), dx: i64, dy: i64) -> (i64, i64) {
+    let (x, y) = point;
+    (x + dx, y + dy)
+}
+
+#[test]
+fn test_min_max() {
+    assert_eq!(min_max(3, 1), (1, 3));
+    assert_eq!(spread(3, 10), 7);
+    assert_eq!(spread(10, 3), 7);
+}
+
+#[test]
+fn test_swap() {
+    assert_eq!(swap((1, -2)), (-2, 1));
+    assert_eq!(translate((1, -2), 3, 4), (4, 2));
+}
+
+/// Prove that the spread doesn't depend on the order of the arguments.
+#[test]
+fn prove_spread_comm() {
+    roq::prove! {
+        function spread,
+        inline r"
+            Require Import Lia.
+
+            Theorem spread_comm : forall a b : nat, spread a b = spread b a.
+            Proof.
+              intros a b. unfold spread, min_max.
+              destruct (Nat.leb a b) eqn:Hab, (Nat.leb b a) eqn:Hba; simpl.
+              - apply Nat.leb_le in Hab, Hba. lia.
+              - reflexivity.
+              - reflexivity.
+              - apply Nat.leb_gt in Hab, Hba. lia.
+            Qed.
+        "
+    };
+}
+
+/// Prove that swapping twice gives back the original pair.
+#[test]
+fn prove_swap_swap() {
+    roq::prove! {
+        function swap,
+        inline r"
+            Theorem swap_swap : forall p : Z * Z, swap (swap p) = p.
+            Proof.
+              intros [x y]. reflexivity.
+            Qed.
+        "
+    };
+}